@@ -0,0 +1,651 @@
+// A small two-pass assembler, mostly for writing tests and patching code into memory. It looks
+// opcodes up in NMOS_OPCODES and CMOS_OPCODES, the same tables the disassembler uses and that
+// process_tables.py generates alongside run_one_op(), so it can't disagree with the CPU.
+//
+// The syntax is the usual sort of thing:
+//
+//     SCREEN = $0200
+//             .org $0400
+//     start:  ldx #0
+//     @loop:  lda message,x       ; @labels belong to the label before them
+//             beq @done
+//             sta SCREEN,x
+//             inx
+//             bne @loop
+//     @done:  jmp *
+//     message: .byte "hi", 0
+//
+// Expressions can use numbers ($hex, %binary, decimal, 'c'), labels, * for the address of the
+// current line, + - * / % & | ^ << >>, unary -, < and > for low and high bytes, and parentheses.
+// Which mnemonics are accepted depends on the CpuVariant: the illegal ones for the NMOS variants
+// that run them, and the 65C02 ones (RMB0-7, SMB0-7, BBR0-7 and BBS0-7 included) for the 65C02.
+
+use std::collections::BTreeMap;
+
+use super::disasm::{is_undocumented, opcode_table};
+use super::{AddrMode, CpuVariant};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    // Counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for AsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+// A run of bytes assembled one after the other. Every .org starts a new one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assembled {
+    pub segments: Vec<Segment>,
+    // Every label and constant, local labels as "global@local".
+    pub labels: BTreeMap<String, u16>,
+}
+
+impl Assembled {
+    // The lowest address anything was assembled to.
+    pub fn origin(&self) -> u16 {
+        self.segments.iter().filter(|s| !s.bytes.is_empty()).map(|s| s.address).min().unwrap_or(0)
+    }
+
+    // Everything from origin() up to the last byte assembled, with any gaps between segments
+    // left as zeroes.
+    pub fn bytes(&self) -> Vec<u8> {
+        let origin = self.origin() as usize;
+        let mut out = Vec::new();
+        for seg in self.segments.iter().filter(|s| !s.bytes.is_empty()) {
+            let start = seg.address as usize - origin;
+            if out.len() < start + seg.bytes.len() {
+                out.resize(start + seg.bytes.len(), 0);
+            }
+            out[start..start + seg.bytes.len()].copy_from_slice(&seg.bytes);
+        }
+        out
+    }
+
+    // Copies every segment to its address in a 64K memory image, wrapping around at $FFFF.
+    pub fn load_into(&self, mem: &mut [u8]) {
+        for seg in self.segments.iter() {
+            for (i, b) in seg.bytes.iter().enumerate() {
+                mem[seg.address.wrapping_add(i as u16) as usize] = *b;
+            }
+        }
+    }
+}
+
+// Assembles 'source', starting at 'origin' unless it says otherwise with .org.
+pub fn assemble(source: &str, origin: u16, variant: CpuVariant) -> Result<Assembled, AsmError> {
+    let mut asm = Asm {
+        variant,
+        origin,
+        symbols: BTreeMap::new(),
+        modes: BTreeMap::new(),
+        pass: 1,
+        pc: origin,
+        scope: String::new(),
+        segments: Vec::new(),
+    };
+    asm.run(source)?;
+    asm.pass = 2;
+    asm.run(source)?;
+
+    let labels = asm.symbols.iter().map(|(k, v)| (k.clone(), *v as u16)).collect();
+    Ok(Assembled { segments: asm.segments, labels })
+}
+
+struct Asm {
+    variant: CpuVariant,
+    origin: u16,
+    symbols: BTreeMap<String, i64>,
+    // The addressing mode picked for each instruction line in the first pass, so the second pass
+    // comes out the same size even when a forward reference turns out to fit in zero page.
+    modes: BTreeMap<usize, AddrMode>,
+    pass: u8,
+    pc: u16,
+    // The last global label, for @local ones.
+    scope: String,
+    segments: Vec<Segment>,
+}
+
+// How an operand was written, before deciding which addressing mode that means.
+enum Operand<'a> {
+    None,
+    Accumulator,
+    Immediate(&'a str),
+    // (e)
+    Indirect(&'a str),
+    // (e,X)
+    IndirectX(&'a str),
+    // (e),Y
+    IndirectY(&'a str),
+    Plain(&'a str),
+    IndexedX(&'a str),
+    IndexedY(&'a str),
+    // zp,target for BBR and BBS.
+    Pair(&'a str, &'a str),
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == '@'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// The leading identifier in 's', if there is one.
+fn ident(s: &str) -> Option<&str> {
+    let mut chars = s.char_indices();
+    match chars.next() {
+        Some((_, c)) if is_ident_start(c) => {}
+        _ => return None,
+    }
+    let end = chars.find(|&(_, c)| !is_ident_char(c)).map(|(i, _)| i).unwrap_or(s.len());
+    Some(&s[..end])
+}
+
+// Cuts off a ';' comment, minding quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut chars = line.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => in_string = !in_string,
+            '\'' if !in_string => {
+                chars.next();
+                chars.next();
+            }
+            ';' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+// Splits on commas that aren't inside parentheses or quotes.
+fn split_top(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut in_string, mut start) = (0, false, 0);
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => in_string = !in_string,
+            '\'' if !in_string => {
+                chars.next();
+                chars.next();
+            }
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                parts.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(s[start..].trim());
+    parts
+}
+
+// If 's' is entirely wrapped in one pair of parentheses, what's inside them.
+fn parenthesized(s: &str) -> Option<&str> {
+    if !s.starts_with('(') || !s.ends_with(')') {
+        return None;
+    }
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return if i == s.len() - 1 { Some(s[1..i].trim()) } else { None };
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_operand(s: &str) -> Operand<'_> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Operand::None;
+    }
+    if s.eq_ignore_ascii_case("a") {
+        return Operand::Accumulator;
+    }
+    if let Some(imm) = s.strip_prefix('#') {
+        return Operand::Immediate(imm.trim());
+    }
+
+    let parts = split_top(s);
+    if parts.len() == 2 {
+        if parts[1].eq_ignore_ascii_case("x") {
+            return Operand::IndexedX(parts[0]);
+        }
+        if parts[1].eq_ignore_ascii_case("y") {
+            return match parenthesized(parts[0]) {
+                Some(inner) => Operand::IndirectY(inner),
+                None => Operand::IndexedY(parts[0]),
+            };
+        }
+        return Operand::Pair(parts[0], parts[1]);
+    }
+
+    if let Some(inner) = parenthesized(s) {
+        let inner_parts = split_top(inner);
+        if inner_parts.len() == 2 && inner_parts[1].eq_ignore_ascii_case("x") {
+            return Operand::IndirectX(inner_parts[0]);
+        }
+        return Operand::Indirect(inner);
+    }
+    Operand::Plain(s)
+}
+
+impl Asm {
+    fn run(&mut self, source: &str) -> Result<(), AsmError> {
+        self.pc = self.origin;
+        self.scope.clear();
+        self.segments = vec![Segment { address: self.origin, bytes: Vec::new() }];
+        for (i, line) in source.lines().enumerate() {
+            self.line(i, line).map_err(|message| AsmError { line: i + 1, message })?;
+        }
+        Ok(())
+    }
+
+    fn line(&mut self, index: usize, line: &str) -> Result<(), String> {
+        let mut rest = strip_comment(line).trim();
+
+        if let Some(name) = ident(rest) {
+            let after = rest[name.len()..].trim_start();
+            if let Some(after) = after.strip_prefix(':') {
+                let pc = self.pc as i64;
+                self.define(name, pc)?;
+                // Labels start a new scope for the @locals after them; constants don't.
+                if !name.starts_with('@') {
+                    self.scope = name.to_string();
+                }
+                rest = after.trim();
+            } else if let Some(expr) = after.strip_prefix('=') {
+                let value = self.eval(expr.trim())?;
+                if let Some(v) = value {
+                    self.define(name, v)?;
+                } else if self.pass == 2 {
+                    return Err(format!("can't work out the value of {}", name));
+                }
+                return Ok(());
+            }
+        }
+        if rest.is_empty() {
+            return Ok(());
+        }
+
+        let split = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let (word, operand) = (rest[..split].to_ascii_lowercase(), rest[split..].trim());
+        if word.starts_with('.') {
+            self.directive(&word, operand)
+        } else {
+            self.instruction(index, &word, operand)
+        }
+    }
+
+    fn full_name(&self, name: &str) -> String {
+        if name.starts_with('@') {
+            format!("{}{}", self.scope, name)
+        } else {
+            name.to_string()
+        }
+    }
+
+    fn define(&mut self, name: &str, value: i64) -> Result<(), String> {
+        let full = self.full_name(name);
+        if self.pass == 1 && self.symbols.contains_key(&full) {
+            return Err(format!("{} is defined more than once", name));
+        }
+        self.symbols.insert(full, value);
+        Ok(())
+    }
+
+    fn emit(&mut self, byte: u8) {
+        self.segments.last_mut().unwrap().bytes.push(byte);
+        self.pc = self.pc.wrapping_add(1);
+    }
+
+    // Range checks only happen in the second pass, when every value is known.
+    fn emit_byte(&mut self, value: Option<i64>, min: i64) -> Result<(), String> {
+        let v = value.unwrap_or(0);
+        if self.pass == 2 && !(min..=0xFF).contains(&v) {
+            return Err(format!("{} doesn't fit in a byte", v));
+        }
+        self.emit(v as u8);
+        Ok(())
+    }
+
+    fn emit_word(&mut self, value: Option<i64>, min: i64) -> Result<(), String> {
+        let v = value.unwrap_or(0);
+        if self.pass == 2 && !(min..=0xFFFF).contains(&v) {
+            return Err(format!("{} doesn't fit in a word", v));
+        }
+        self.emit(v as u8);
+        self.emit((v >> 8) as u8);
+        Ok(())
+    }
+
+    fn emit_branch(&mut self, target: Option<i64>, next: u16) -> Result<(), String> {
+        let offset = target.unwrap_or(next as i64) - next as i64;
+        if self.pass == 2 && !(-128..=127).contains(&offset) {
+            return Err(format!("branch target is {} bytes away", offset));
+        }
+        self.emit(offset as u8);
+        Ok(())
+    }
+
+    fn directive(&mut self, word: &str, operand: &str) -> Result<(), String> {
+        match word {
+            ".org" => {
+                let address = self.eval(operand)?.ok_or_else(|| ".org needs a value that's already known".to_string())?;
+                if !(0..=0xFFFF).contains(&address) {
+                    return Err(format!("${:X} isn't an address", address));
+                }
+                self.pc = address as u16;
+                self.segments.push(Segment { address: self.pc, bytes: Vec::new() });
+            }
+            ".byte" => {
+                for item in split_top(operand) {
+                    if item.len() >= 2 && item.starts_with('"') && item.ends_with('"') {
+                        for b in item[1..item.len() - 1].bytes() {
+                            self.emit(b);
+                        }
+                    } else {
+                        let v = self.eval(item)?;
+                        self.emit_byte(v, -128)?;
+                    }
+                }
+            }
+            ".word" => {
+                for item in split_top(operand) {
+                    let v = self.eval(item)?;
+                    self.emit_word(v, -32768)?;
+                }
+            }
+            _ => return Err(format!("unknown directive {}", word)),
+        }
+        Ok(())
+    }
+
+    // Finds the opcode for a mnemonic and mode, preferring a documented one (so NOP is $EA).
+    // 'bit' picks between RMB0-7 and friends.
+    fn opcode(&self, mnemonic: &str, mode: AddrMode, bit: Option<u8>) -> Option<u8> {
+        let table = opcode_table(self.variant);
+        let matches = |op: u8| {
+            let info = table[op as usize];
+            info.mnemonic == mnemonic && info.mode == mode && match bit {
+                Some(b) => (op >> 4) & 7 == b,
+                None => true,
+            }
+        };
+        let documented = (0..=255u8).find(|&op| matches(op) && !is_undocumented(self.variant, op));
+        if documented.is_some() || !self.variant.has_undocumented() {
+            return documented;
+        }
+        (0..=255u8).find(|&op| matches(op))
+    }
+
+    fn instruction(&mut self, index: usize, word: &str, operand: &str) -> Result<(), String> {
+        // RMB0-7, SMB0-7, BBR0-7 and BBS0-7 share one table entry per group of eight.
+        let (mnemonic, bit) = match (word.get(..3), word.get(3..)) {
+            (Some(base), Some(digit)) if ["rmb", "smb", "bbr", "bbs"].contains(&base) &&
+                                         digit.len() == 1 && digit.as_bytes()[0].is_ascii_digit() => {
+                (base, Some(digit.parse::<u8>().unwrap()))
+            }
+            _ => (word, None),
+        };
+        if matches!(bit, Some(b) if b > 7) {
+            return Err(format!("unknown instruction {}", word));
+        }
+        let has = |mode| self.opcode(mnemonic, mode, bit).is_some();
+        if !ALL_MODES.iter().any(|&m| has(m)) {
+            return Err(format!("unknown instruction {}", word));
+        }
+
+        let parsed = parse_operand(operand);
+        let (mode, exprs) = match self.modes.get(&index) {
+            Some(&mode) if self.pass == 2 => (mode, operand_exprs(&parsed)),
+            _ => {
+                let mode = self.pick_mode(mnemonic, bit, &parsed)?;
+                self.modes.insert(index, mode);
+                (mode, operand_exprs(&parsed))
+            }
+        };
+        let opcode = self.opcode(mnemonic, mode, bit)
+            .ok_or_else(|| format!("{} can't be used like that", word))?;
+
+        let values = exprs.iter().map(|e| self.eval(e)).collect::<Result<Vec<_>, _>>()?;
+        let start = self.pc;
+        self.emit(opcode);
+        match mode {
+            AddrMode::Implied | AddrMode::Accumulator => {}
+            AddrMode::Immediate => self.emit_byte(values[0], -128)?,
+            AddrMode::ZeroPage | AddrMode::ZeroPageX | AddrMode::ZeroPageY | AddrMode::IndirectX |
+            AddrMode::IndirectY | AddrMode::ZeroPageIndirect => self.emit_byte(values[0], 0)?,
+            AddrMode::Absolute | AddrMode::AbsoluteX | AddrMode::AbsoluteY | AddrMode::Indirect |
+            AddrMode::AbsoluteXIndirect => self.emit_word(values[0], 0)?,
+            AddrMode::Relative => self.emit_branch(values[0], start.wrapping_add(2))?,
+            AddrMode::ZeroPageRelative => {
+                self.emit_byte(values[0], 0)?;
+                self.emit_branch(values[1], start.wrapping_add(3))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn pick_mode(&self, mnemonic: &str, bit: Option<u8>, operand: &Operand) -> Result<AddrMode, String> {
+        let has = |mode| self.opcode(mnemonic, mode, bit).is_some();
+        let first = |modes: &[AddrMode]| modes.iter().cloned().find(|&m| has(m));
+        // Zero page if the value's known this early and fits, otherwise absolute.
+        let sized = |expr: &str, zp: AddrMode, abs: AddrMode| -> Result<Option<AddrMode>, String> {
+            let small = match self.eval(expr)? {
+                Some(v) => (0..=0xFF).contains(&v),
+                None => false,
+            };
+            Ok(if small && has(zp) { Some(zp) } else { first(&[abs, zp]) })
+        };
+
+        let mode = match *operand {
+            Operand::None => first(&[AddrMode::Implied, AddrMode::Accumulator]),
+            Operand::Accumulator => first(&[AddrMode::Accumulator]),
+            Operand::Immediate(_) => first(&[AddrMode::Immediate]),
+            Operand::Indirect(_) => first(&[AddrMode::Indirect, AddrMode::ZeroPageIndirect]),
+            Operand::IndirectX(_) => first(&[AddrMode::IndirectX, AddrMode::AbsoluteXIndirect]),
+            Operand::IndirectY(_) => first(&[AddrMode::IndirectY]),
+            Operand::IndexedX(e) => sized(e, AddrMode::ZeroPageX, AddrMode::AbsoluteX)?,
+            Operand::IndexedY(e) => sized(e, AddrMode::ZeroPageY, AddrMode::AbsoluteY)?,
+            Operand::Plain(e) => match first(&[AddrMode::Relative]) {
+                Some(m) => Some(m),
+                None => sized(e, AddrMode::ZeroPage, AddrMode::Absolute)?,
+            },
+            Operand::Pair(_, _) => first(&[AddrMode::ZeroPageRelative]),
+        };
+        mode.ok_or_else(|| format!("{} can't be used like that", mnemonic))
+    }
+
+    // Evaluates an expression. None means it uses a label that isn't defined yet, which is only
+    // allowed in the first pass.
+    fn eval(&self, text: &str) -> Result<Option<i64>, String> {
+        let mut e = Expr { chars: text.chars().collect(), pos: 0, asm: self };
+        let v = e.binary(0)?;
+        e.skip_space();
+        if e.pos != e.chars.len() {
+            return Err(format!("don't understand {:?}", text));
+        }
+        Ok(v)
+    }
+}
+
+const ALL_MODES: [AddrMode; 16] = [
+    AddrMode::Implied, AddrMode::Accumulator, AddrMode::Immediate, AddrMode::ZeroPage,
+    AddrMode::ZeroPageX, AddrMode::ZeroPageY, AddrMode::Relative, AddrMode::Absolute,
+    AddrMode::AbsoluteX, AddrMode::AbsoluteY, AddrMode::Indirect, AddrMode::IndirectX,
+    AddrMode::IndirectY, AddrMode::ZeroPageIndirect, AddrMode::AbsoluteXIndirect,
+    AddrMode::ZeroPageRelative,
+];
+
+fn operand_exprs<'a>(operand: &Operand<'a>) -> Vec<&'a str> {
+    match *operand {
+        Operand::None | Operand::Accumulator => vec![],
+        Operand::Immediate(e) | Operand::Indirect(e) | Operand::IndirectX(e) | Operand::IndirectY(e) |
+        Operand::Plain(e) | Operand::IndexedX(e) | Operand::IndexedY(e) => vec![e],
+        Operand::Pair(a, b) => vec![a, b],
+    }
+}
+
+struct Expr<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    asm: &'a Asm,
+}
+
+// Binary operators from loosest to tightest.
+const PRECEDENCE: [&[&str]; 6] = [&["|"], &["^"], &["&"], &["<<", ">>"], &["+", "-"], &["*", "/", "%"]];
+
+impl<'a> Expr<'a> {
+    fn skip_space(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_space();
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        self.skip_space();
+        let op: Vec<char> = op.chars().collect();
+        if self.chars[self.pos..].starts_with(&op) {
+            self.pos += op.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Option<i64>, String> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        'outer: loop {
+            for &op in PRECEDENCE[level].iter() {
+                if self.eat(op) {
+                    let right = self.binary(level + 1)?;
+                    left = match (left, right) {
+                        (Some(l), Some(r)) => Some(match op {
+                            "|" => l | r,
+                            "^" => l ^ r,
+                            "&" => l & r,
+                            "<<" => l.wrapping_shl(r as u32 & 63),
+                            ">>" => l.wrapping_shr(r as u32 & 63),
+                            "+" => l.wrapping_add(r),
+                            "-" => l.wrapping_sub(r),
+                            "*" => l.wrapping_mul(r),
+                            "/" if r == 0 => return Err("division by zero".to_string()),
+                            "/" => l.wrapping_div(r),
+                            "%" if r == 0 => return Err("division by zero".to_string()),
+                            _ => l.wrapping_rem(r),
+                        }),
+                        _ => None,
+                    };
+                    continue 'outer;
+                }
+            }
+            return Ok(left);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Option<i64>, String> {
+        if self.eat("-") {
+            return Ok(self.unary()?.map(|v| v.wrapping_neg()));
+        }
+        if self.eat("<") {
+            return Ok(self.unary()?.map(|v| v & 0xFF));
+        }
+        if self.eat(">") {
+            return Ok(self.unary()?.map(|v| (v >> 8) & 0xFF));
+        }
+        self.primary()
+    }
+
+    fn digits(&mut self, radix: u32) -> Result<Option<i64>, String> {
+        let start = self.pos;
+        while self.pos < self.chars.len() && self.chars[self.pos].is_digit(radix) {
+            self.pos += 1;
+        }
+        let s: String = self.chars[start..self.pos].iter().collect();
+        i64::from_str_radix(&s, radix).map(Some).map_err(|_| "bad number".to_string())
+    }
+
+    fn primary(&mut self) -> Result<Option<i64>, String> {
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let v = self.binary(0)?;
+                if !self.eat(")") {
+                    return Err("missing )".to_string());
+                }
+                Ok(v)
+            }
+            Some('*') => {
+                self.pos += 1;
+                Ok(Some(self.asm.pc as i64))
+            }
+            Some('$') => {
+                self.pos += 1;
+                self.digits(16)
+            }
+            Some('%') => {
+                self.pos += 1;
+                self.digits(2)
+            }
+            Some('\'') => {
+                match (self.chars.get(self.pos + 1), self.chars.get(self.pos + 2)) {
+                    (Some(&c), Some(&'\'')) => {
+                        self.pos += 3;
+                        Ok(Some(c as i64))
+                    }
+                    _ => Err("bad character constant".to_string()),
+                }
+            }
+            Some(c) if c.is_ascii_digit() => self.digits(10),
+            Some(c) if is_ident_start(c) => {
+                let start = self.pos;
+                self.pos += 1;
+                while self.pos < self.chars.len() && is_ident_char(self.chars[self.pos]) {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect();
+                match self.asm.symbols.get(&self.asm.full_name(&name)) {
+                    Some(&v) => Ok(Some(v)),
+                    None if self.asm.pass == 1 => Ok(None),
+                    None => Err(format!("{} isn't defined", name)),
+                }
+            }
+            _ => Err("expected a value".to_string()),
+        }
+    }
+}
@@ -0,0 +1,483 @@
+// Breakpoints and watchpoints that CPU::exec() checks for itself, so stopping on an address or a
+// memory access doesn't take a custom Backplane::each_instr():
+//
+//     let id = cpu.breakpoints.watch(WatchKind::Write, 0xD020, 0xD021);
+//     cpu.breakpoints.get_mut(id).unwrap().condition = Some(Condition::parse("A == $40 && [$D012] > 100")?);
+//     match cpu.exec(&mut mem, 100_000) {
+//         StopReason::Watchpoint(hit) => ...,
+//         ...
+//     }
+//
+// Execute breakpoints stop exec() before the instruction runs, with StopReason::Breakpoint; going
+// on from there runs that instruction rather than stopping again. That includes the first
+// instruction of an interrupt handler, which gets checked once the interrupt has been taken. Read
+// and write watchpoints stop it after the instruction that made the access, with
+// StopReason::Watchpoint. Either way last_hit() says which breakpoint it was. CPU::step() doesn't
+// look at any of this.
+
+use super::{Access, Backplane, CPU};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    // The pc arriving in the range, i.e. an ordinary breakpoint.
+    Execute,
+    // Reads from memory in the range. The instruction's own opcode and operand fetches don't
+    // count, but everything else on the bus does: data, the stack, and (in cycle-accurate mode)
+    // dummy reads.
+    Read,
+    Write,
+    ReadWrite,
+}
+
+// A condition on the machine's state, e.g. "A == $40 && [$D012] > 100". There are:
+//
+//     numbers         $D012, %1010, 100
+//     registers       A X Y SP P PC, and the flags N V D I Z C (0 or 1); any case
+//     memory          [address], one byte, read through Backplane::read() (so careful with
+//                     addresses where reading does something)
+//     operators       || && | ^ & == != < <= > >= << >> + - * / %, unary ! - ~, and parentheses,
+//                     with C's precedence
+//
+// Anything that isn't zero counts as true. Dividing by zero gives zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    text: String,
+    expr: Node,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    A,
+    X,
+    Y,
+    Sp,
+    P,
+    Pc,
+    Flag(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Number(i64),
+    Register(Register),
+    Memory(Box<Node>),
+    Unary(&'static str, Box<Node>),
+    Binary(&'static str, Box<Node>, Box<Node>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConditionError {
+    // Where in the text it went wrong, counting characters from 0.
+    pub position: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConditionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "at column {}: {}", self.position + 1, self.message)
+    }
+}
+
+impl std::error::Error for ConditionError {}
+
+// Binary operators from loosest to tightest. Longer operators come before their prefixes.
+const PRECEDENCE: [&[&str]; 10] = [
+    &["||"], &["&&"], &["|"], &["^"], &["&"], &["==", "!="], &["<=", ">=", "<", ">"],
+    &["<<", ">>"], &["+", "-"], &["*", "/", "%"],
+];
+
+const FLAGS: [(&str, u8); 6] = [("n", 0x80), ("v", 0x40), ("d", 0x08), ("i", 0x04), ("z", 0x02), ("c", 0x01)];
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn error<T>(&self, message: &str) -> Result<T, ConditionError> {
+        Err(ConditionError { position: self.pos, message: message.to_string() })
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        while self.pos < self.chars.len() && self.chars[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        self.peek();
+        let op: Vec<char> = op.chars().collect();
+        // "|" and "&" mustn't take the first half of "||" and "&&", nor "<" of "<<".
+        let next = self.chars.get(self.pos + op.len()).cloned();
+        let split = op.len() == 1 && next == Some(op[0]) && "|&<>".contains(op[0]);
+        if self.chars[self.pos..].starts_with(&op) && !split {
+            self.pos += op.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Node, ConditionError> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+        let mut left = self.binary(level + 1)?;
+        'outer: loop {
+            for &op in PRECEDENCE[level].iter() {
+                if self.eat(op) {
+                    let right = self.binary(level + 1)?;
+                    left = Node::Binary(op, Box::new(left), Box::new(right));
+                    continue 'outer;
+                }
+            }
+            return Ok(left);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, ConditionError> {
+        for &op in ["!", "-", "~"].iter() {
+            if self.peek() == op.chars().next() && !(op == "!" && self.chars.get(self.pos + 1) == Some(&'=')) {
+                self.pos += 1;
+                return Ok(Node::Unary(op, Box::new(self.unary()?)));
+            }
+        }
+        self.primary()
+    }
+
+    fn digits(&mut self, radix: u32) -> Result<Node, ConditionError> {
+        let start = self.pos;
+        while self.pos < self.chars.len() && self.chars[self.pos].is_digit(radix) {
+            self.pos += 1;
+        }
+        let s: String = self.chars[start..self.pos].iter().collect();
+        match i64::from_str_radix(&s, radix) {
+            Ok(v) => Ok(Node::Number(v)),
+            Err(_) => {
+                self.pos = start;
+                self.error("bad number")
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Node, ConditionError> {
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let v = self.binary(0)?;
+                if !self.eat(")") {
+                    return self.error("missing )");
+                }
+                Ok(v)
+            }
+            Some('[') => {
+                self.pos += 1;
+                let v = self.binary(0)?;
+                if !self.eat("]") {
+                    return self.error("missing ]");
+                }
+                Ok(Node::Memory(Box::new(v)))
+            }
+            Some('$') => {
+                self.pos += 1;
+                self.digits(16)
+            }
+            Some('%') => {
+                self.pos += 1;
+                self.digits(2)
+            }
+            Some(c) if c.is_ascii_digit() => self.digits(10),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let start = self.pos;
+                while self.pos < self.chars.len() && self.chars[self.pos].is_ascii_alphanumeric() {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[start..self.pos].iter().collect::<String>().to_ascii_lowercase();
+                let register = match name.as_str() {
+                    "a" => Register::A,
+                    "x" => Register::X,
+                    "y" => Register::Y,
+                    "sp" | "s" => Register::Sp,
+                    "p" => Register::P,
+                    "pc" => Register::Pc,
+                    _ => match FLAGS.iter().find(|&&(f, _)| f == name) {
+                        Some(&(_, bit)) => Register::Flag(bit),
+                        None => {
+                            self.pos = start;
+                            return self.error(&format!("no register called {}", name));
+                        }
+                    },
+                };
+                Ok(Node::Register(register))
+            }
+            _ => self.error("expected a value"),
+        }
+    }
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Result<Condition, ConditionError> {
+        let mut p = Parser { chars: text.chars().collect(), pos: 0 };
+        let expr = p.binary(0)?;
+        if p.peek().is_some() {
+            return p.error("don't understand this");
+        }
+        Ok(Condition { text: text.trim().to_string(), expr })
+    }
+
+    pub fn eval<T: Backplane>(&self, cpu: &CPU, mem: &T) -> i64 {
+        eval(&self.expr, cpu, mem)
+    }
+
+    pub fn is_true<T: Backplane>(&self, cpu: &CPU, mem: &T) -> bool {
+        self.eval(cpu, mem) != 0
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+fn eval<T: Backplane>(node: &Node, cpu: &CPU, mem: &T) -> i64 {
+    match node {
+        Node::Number(v) => *v,
+        Node::Register(r) => match *r {
+            Register::A => cpu.a.0 as i64,
+            Register::X => cpu.x.0 as i64,
+            Register::Y => cpu.y.0 as i64,
+            Register::Sp => cpu.sp.0 as i64,
+            Register::P => cpu.status.bits() as i64,
+            Register::Pc => cpu.pc.0 as i64,
+            Register::Flag(bit) => (cpu.status.bits() & bit != 0) as i64,
+        },
+        Node::Memory(address) => mem.read(eval(address, cpu, mem) as u16) as i64,
+        Node::Unary(op, v) => {
+            let v = eval(v, cpu, mem);
+            match *op {
+                "!" => (v == 0) as i64,
+                "-" => v.wrapping_neg(),
+                _ => !v,
+            }
+        }
+        Node::Binary(op, l, r) => {
+            // && and || don't look at the right-hand side unless they have to, which matters for
+            // memory reads.
+            let l = eval(l, cpu, mem);
+            match *op {
+                "&&" => return (l != 0 && eval(r, cpu, mem) != 0) as i64,
+                "||" => return (l != 0 || eval(r, cpu, mem) != 0) as i64,
+                _ => {}
+            }
+            let r = eval(r, cpu, mem);
+            match *op {
+                "|" => l | r,
+                "^" => l ^ r,
+                "&" => l & r,
+                "==" => (l == r) as i64,
+                "!=" => (l != r) as i64,
+                "<" => (l < r) as i64,
+                "<=" => (l <= r) as i64,
+                ">" => (l > r) as i64,
+                ">=" => (l >= r) as i64,
+                "<<" => l.wrapping_shl(r as u32 & 63),
+                ">>" => l.wrapping_shr(r as u32 & 63),
+                "+" => l.wrapping_add(r),
+                "-" => l.wrapping_sub(r),
+                "*" => l.wrapping_mul(r),
+                "/" => l.checked_div(r).unwrap_or(0),
+                _ => l.checked_rem(r).unwrap_or(0),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub kind: WatchKind,
+    // The addresses covered, inclusive.
+    pub from: u16,
+    pub to: u16,
+    // Only counts as a hit if this holds (checked before the instruction for Execute, after it for
+    // the others).
+    pub condition: Option<Condition>,
+    // Let this many hits go by before stopping.
+    pub ignore: u64,
+    // Hits so far, stopped at or not.
+    pub hits: u64,
+    pub enabled: bool,
+}
+
+impl Breakpoint {
+    pub fn new(kind: WatchKind, from: u16, to: u16) -> Breakpoint {
+        Breakpoint { kind, from, to, condition: None, ignore: 0, hits: 0, enabled: true }
+    }
+
+    pub fn covers(&self, address: u16) -> bool {
+        address >= self.from && address <= self.to
+    }
+
+    fn watches(&self, write: bool) -> bool {
+        match self.kind {
+            WatchKind::Execute => false,
+            WatchKind::Read => !write,
+            WatchKind::Write => write,
+            WatchKind::ReadWrite => true,
+        }
+    }
+}
+
+// What set off a stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hit {
+    pub id: u32,
+    // The address that matched: the pc for an execute breakpoint, otherwise the one accessed.
+    pub address: u16,
+    pub write: bool,
+    // The instruction that did it (for execute breakpoints, the one about to run).
+    pub pc: u16,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Breakpoints {
+    list: Vec<(u32, Breakpoint)>,
+    next_id: u32,
+    last_hit: Option<Hit>,
+    // Set after stopping at an execute breakpoint, so that carrying on runs the instruction there
+    // instead of stopping again straight away.
+    resume_at: Option<u16>,
+    // Reused from one instruction to the next for the accesses it makes.
+    scratch: Vec<Access>,
+}
+
+impl Breakpoints {
+    pub fn new() -> Breakpoints {
+        Breakpoints::default()
+    }
+
+    // Returns an id for get(), get_mut() and remove().
+    pub fn add(&mut self, breakpoint: Breakpoint) -> u32 {
+        self.next_id += 1;
+        self.list.push((self.next_id, breakpoint));
+        self.next_id
+    }
+
+    pub fn break_at(&mut self, pc: u16) -> u32 {
+        self.add(Breakpoint::new(WatchKind::Execute, pc, pc))
+    }
+
+    pub fn watch(&mut self, kind: WatchKind, from: u16, to: u16) -> u32 {
+        self.add(Breakpoint::new(kind, from, to))
+    }
+
+    pub fn remove(&mut self, id: u32) -> bool {
+        let before = self.list.len();
+        self.list.retain(|&(i, _)| i != id);
+        self.list.len() != before
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+        self.resume_at = None;
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Breakpoint> {
+        self.list.iter().find(|&&(i, _)| i == id).map(|(_, b)| b)
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Breakpoint> {
+        self.list.iter_mut().find(|&&mut (i, _)| i == id).map(|(_, b)| b)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &Breakpoint)> {
+        self.list.iter().map(|(i, b)| (*i, b))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    // Have the next exec() run the instruction at 'pc' rather than stop at a breakpoint there, the
+    // way it does after stopping at one. For debuggers that step from wherever the pc happens to
+    // be, breakpoint or not.
+    pub fn resume_at(&mut self, pc: u16) {
+        self.resume_at = Some(pc);
+    }
+
+    // What stopped exec() most recently, if it was one of these.
+    pub fn last_hit(&self) -> Option<Hit> {
+        self.last_hit
+    }
+
+    pub(crate) fn has_watchpoints(&self) -> bool {
+        self.list.iter().any(|(_, b)| b.enabled && b.kind != WatchKind::Execute)
+    }
+
+    // Counts a hit on each breakpoint that matches, and returns the first that's run out of
+    // ignores.
+    fn hit<T: Backplane>(&mut self, cpu: &CPU, mem: &T, pc: u16, matches: &dyn Fn(&Breakpoint) -> Option<(u16, bool)>) -> Option<Hit> {
+        let mut stop = None;
+        for (id, b) in self.list.iter_mut() {
+            if !b.enabled {
+                continue;
+            }
+            let (address, write) = match matches(b) {
+                Some(m) => m,
+                None => continue,
+            };
+            if let Some(ref c) = b.condition {
+                if !c.is_true(cpu, mem) {
+                    continue;
+                }
+            }
+            b.hits += 1;
+            if b.hits > b.ignore && stop.is_none() {
+                stop = Some(Hit { id: *id, address, write, pc });
+            }
+        }
+        if stop.is_some() {
+            self.last_hit = stop;
+        }
+        stop
+    }
+
+    // Called before each instruction.
+    pub(crate) fn check_execute<T: Backplane>(&mut self, cpu: &CPU, mem: &T) -> Option<Hit> {
+        let pc = cpu.pc.0;
+        if self.resume_at == Some(pc) {
+            return None;
+        }
+        let hit = self.hit(cpu, mem, pc, &|b| {
+            if b.kind == WatchKind::Execute && b.covers(pc) { Some((pc, false)) } else { None }
+        });
+        if hit.is_some() {
+            self.resume_at = Some(pc);
+        }
+        hit
+    }
+
+    // Called once an instruction has actually run (rather than the CPU sitting in WAI, say), which
+    // is what getting past a breakpoint means.
+    pub(crate) fn ran_instruction(&mut self) {
+        self.resume_at = None;
+    }
+
+    pub(crate) fn take_scratch(&mut self) -> Vec<Access> {
+        let mut v = std::mem::take(&mut self.scratch);
+        v.clear();
+        v
+    }
+
+    pub(crate) fn put_scratch(&mut self, v: Vec<Access>) {
+        self.scratch = v;
+    }
+
+    // Called after each instruction with the accesses it made, other than fetching itself.
+    pub(crate) fn check_accesses<T: Backplane>(&mut self, cpu: &CPU, mem: &T, pc: u16, accesses: &[Access]) -> Option<Hit> {
+        self.hit(cpu, mem, pc, &|b| {
+            accesses.iter().find(|a| b.watches(a.write) && b.covers(a.address)).map(|a| (a.address, a.write))
+        })
+    }
+}
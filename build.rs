@@ -0,0 +1,46 @@
+// Compiles the original fake6502.c into a static library, for the differential tests in
+// tests/differential.rs that run it side by side with the Rust port. This calls the C compiler
+// directly (CC, or plain cc) instead of pulling in a crate for one file. If there's no C compiler
+// around, it says so and the differential tests skip themselves; nothing else needs the C code.
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=fake6502.c");
+    println!("cargo:rerun-if-env-changed=CC");
+    println!("cargo:rerun-if-env-changed=AR");
+    println!("cargo:rustc-check-cfg=cfg(fake6502_c)");
+
+    let src = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("fake6502.c");
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let ar = env::var("AR").unwrap_or_else(|_| "ar".to_string());
+    let obj = out.join("fake6502.o");
+    let lib = out.join("libfake6502c.a");
+
+    let compiled = Command::new(&cc)
+        .args(["-c", "-O2", "-fPIC", "-w"])
+        .arg(&src)
+        .arg("-o")
+        .arg(&obj)
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    let archived = compiled && Command::new(&ar)
+        .arg("crs")
+        .arg(&lib)
+        .arg(&obj)
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+
+    if !archived {
+        println!("cargo:warning=couldn't build fake6502.c with {} and {}; skipping the differential tests", cc, ar);
+        return;
+    }
+
+    println!("cargo:rustc-link-search=native={}", out.display());
+    println!("cargo:rustc-cfg=fake6502_c");
+}
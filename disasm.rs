@@ -0,0 +1,179 @@
+// A disassembler, driven by NMOS_OPCODES and CMOS_OPCODES so it always agrees with the CPU about
+// what each opcode is.
+
+use super::{AddrMode, Backplane, CpuVariant, OpInfo, CMOS_OPCODES, NMOS_OPCODES};
+
+// The NMOS mnemonics that aren't in the datasheet. (The extra NOPs are caught separately.)
+const UNDOCUMENTED: [&str; 20] = [
+    "slo", "rla", "sre", "rra", "sax", "lax", "dcp", "isb", "anc", "alr", "arr", "ane", "sha",
+    "tas", "shy", "shx", "lxa", "las", "sbx", "usbc",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    // Show undocumented opcodes by their usual names (LAX, DCP and so on). Otherwise they come
+    // out as ".byte $xx", like most monitors do.
+    pub illegal_mnemonics: bool,
+    // Put the address and the instruction's bytes in front, e.g. "0400  A9 01     LDA #$01".
+    pub hex_dump: bool,
+    pub uppercase: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options { illegal_mnemonics: true, hex_dump: false, uppercase: true }
+    }
+}
+
+// One decoded instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: u16,
+    pub opcode: u8,
+    pub mnemonic: &'static str,
+    pub mode: AddrMode,
+    // The operand bytes as a little-endian number. For ZeroPageRelative (BBR/BBS) that's the zero
+    // page address in the low byte and the branch offset in the high byte.
+    pub operand: u16,
+    pub length: u16,
+    // Base cycle count, as in OpInfo.
+    pub cycles: u8,
+    // See is_undocumented().
+    pub undocumented: bool,
+}
+
+pub fn opcode_table(variant: CpuVariant) -> &'static [OpInfo; 256] {
+    if variant.is_cmos() {
+        &CMOS_OPCODES
+    } else {
+        &NMOS_OPCODES
+    }
+}
+
+// Whether an opcode is one of the NMOS illegal ones, including its extra NOPs and the JAMs. The
+// 65C02 doesn't have any.
+pub fn is_undocumented(variant: CpuVariant, opcode: u8) -> bool {
+    let mnemonic = opcode_table(variant)[opcode as usize].mnemonic;
+    !variant.is_cmos() && (UNDOCUMENTED.contains(&mnemonic) || mnemonic == "jam" ||
+                           (mnemonic == "nop" && opcode != 0xEA))
+}
+
+// Decodes the instruction at 'address'. Anything is an instruction to a 6502, so this always
+// succeeds; the operand wraps around from $FFFF to $0000 like the CPU would.
+pub fn decode<T: Backplane>(mem: &T, address: u16, variant: CpuVariant) -> Instruction {
+    let opcode = mem.read(address);
+    let info = opcode_table(variant)[opcode as usize];
+    let length = 1 + info.mode.operand_len();
+    let operand = match length {
+        1 => 0,
+        2 => mem.read(address.wrapping_add(1)) as u16,
+        _ => mem.read(address.wrapping_add(1)) as u16 | (mem.read(address.wrapping_add(2)) as u16) << 8,
+    };
+    let undocumented = is_undocumented(variant, opcode);
+    // A CPU without the undocumented instructions runs them as NOPs (with the same addressing, so
+    // the same length and cycles), so show them that way. The JAMs jam regardless.
+    let mnemonic = if undocumented && !variant.has_undocumented() && info.mnemonic != "jam" {
+        "nop"
+    } else {
+        info.mnemonic
+    };
+
+    Instruction {
+        address,
+        opcode,
+        mnemonic,
+        mode: info.mode,
+        operand,
+        length,
+        cycles: info.cycles,
+        undocumented,
+    }
+}
+
+impl Instruction {
+    // The mnemonic as written, with the bit number the 65C02's RMB, SMB, BBR and BBS carry in
+    // their opcodes.
+    pub fn name(&self) -> String {
+        match self.mnemonic {
+            "rmb" | "smb" | "bbr" | "bbs" => format!("{}{}", self.mnemonic, (self.opcode >> 4) & 7),
+            m => m.to_string(),
+        }
+    }
+
+    // Where a branch goes if it's taken.
+    pub fn branch_target(&self) -> Option<u16> {
+        let next = self.address.wrapping_add(self.length);
+        match self.mode {
+            AddrMode::Relative => Some(next.wrapping_add(self.operand as i8 as u16)),
+            AddrMode::ZeroPageRelative => Some(next.wrapping_add((self.operand >> 8) as i8 as u16)),
+            _ => None,
+        }
+    }
+
+    pub fn operand_text(&self) -> String {
+        let op = self.operand;
+        match self.mode {
+            AddrMode::Implied => String::new(),
+            AddrMode::Accumulator => "A".to_string(),
+            AddrMode::Immediate => format!("#${:02X}", op),
+            AddrMode::ZeroPage => format!("${:02X}", op),
+            AddrMode::ZeroPageX => format!("${:02X},X", op),
+            AddrMode::ZeroPageY => format!("${:02X},Y", op),
+            AddrMode::Relative => format!("${:04X}", self.branch_target().unwrap()),
+            AddrMode::Absolute => format!("${:04X}", op),
+            AddrMode::AbsoluteX => format!("${:04X},X", op),
+            AddrMode::AbsoluteY => format!("${:04X},Y", op),
+            AddrMode::Indirect => format!("(${:04X})", op),
+            AddrMode::IndirectX => format!("(${:02X},X)", op),
+            AddrMode::IndirectY => format!("(${:02X}),Y", op),
+            AddrMode::ZeroPageIndirect => format!("(${:02X})", op),
+            AddrMode::AbsoluteXIndirect => format!("(${:04X},X)", op),
+            AddrMode::ZeroPageRelative => format!("${:02X},${:04X}", op & 0xFF, self.branch_target().unwrap()),
+        }
+    }
+
+    pub fn format(&self, options: &Options) -> String {
+        let mut text = if self.undocumented && !options.illegal_mnemonics {
+            format!(".byte ${:02X}", self.opcode)
+        } else {
+            let operand = self.operand_text();
+            if operand.is_empty() {
+                self.name()
+            } else {
+                format!("{} {}", self.name(), operand)
+            }
+        };
+        text = if options.uppercase { text.to_uppercase() } else { text.to_lowercase() };
+
+        if options.hex_dump {
+            // A ".byte" only covers the opcode.
+            let len = if self.undocumented && !options.illegal_mnemonics { 1 } else { self.length };
+            let bytes = [self.opcode, self.operand as u8, (self.operand >> 8) as u8];
+            let mut dump = String::new();
+            for b in bytes[..len as usize].iter() {
+                dump += &format!("{:02X} ", b);
+            }
+            text = format!("{:04X}  {:<9} {}", self.address, dump, text);
+        }
+        text
+    }
+}
+
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.format(&Options::default()))
+    }
+}
+
+// Disassembles 'count' instructions starting at 'address', one per line.
+pub fn disassemble<T: Backplane>(mem: &T, address: u16, count: usize, variant: CpuVariant, options: &Options) -> String {
+    let mut out = String::new();
+    let mut pc = address;
+    for _ in 0..count {
+        let inst = decode(mem, pc, variant);
+        out += &inst.format(options);
+        out.push('\n');
+        pc = pc.wrapping_add(if inst.undocumented && !options.illegal_mnemonics { 1 } else { inst.length });
+    }
+    out
+}
@@ -117,8 +117,38 @@
 
 use std::num::Wrapping;
 
-const UNDOCUMENTED: bool = false;
-const NES_CPU: bool = false;
+// The two defines above became CpuVariant, which is picked at runtime in CPU::new() so that one
+// program can emulate more than one kind of machine. If you know you'll never need a particular
+// feature, the "no-undocumented", "no-bcd" and "no-65c02" Cargo features compile it out again, the
+// same way leaving UNDOCUMENTED undefined or defining NES_CPU did: every variant then behaves as if
+// it doesn't have it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuVariant {
+    // A stock NMOS 6502. Undocumented opcodes are treated as NOPs (apart from the JAM ones).
+    Nmos6502,
+    // An NMOS 6502 with the (more predictable) undocumented opcodes, like the C64's 6510.
+    Nmos6502Illegal,
+    // The NES's Ricoh 2A03: an NMOS 6502 core, undocumented opcodes included, but with decimal
+    // mode disconnected.
+    Ricoh2A03,
+    // The WDC 65C02, which includes the Rockwell bit instructions (RMB/SMB/BBR/BBS) as well as
+    // WAI and STP. This one runs from its own opcode table; see run_one_op_65c02().
+    Wdc65C02,
+}
+
+impl CpuVariant {
+    fn has_bcd(self) -> bool {
+        !cfg!(feature = "no-bcd") && self != CpuVariant::Ricoh2A03
+    }
+
+    fn has_undocumented(self) -> bool {
+        !cfg!(feature = "no-undocumented") && matches!(self, CpuVariant::Nmos6502Illegal | CpuVariant::Ricoh2A03)
+    }
+
+    fn is_cmos(self) -> bool {
+        !cfg!(feature = "no-65c02") && self == CpuVariant::Wdc65C02
+    }
+}
 
 //#define FLAG_CARRY     0x01
 //#define FLAG_ZERO      0x02
@@ -139,6 +169,92 @@ const FLAG_CONSTANT:    u8 = 0x20;
 const FLAG_OVERFLOW:    u8 = 0x40;
 const FLAG_SIGN:        u8 = 0x80;
 
+// The processor status register. The break and constant bits don't really exist inside the chip;
+// they only show up in the copy of the register that gets pushed to the stack, where bit 5 is
+// always set and bit 4 tells a BRK or PHP apart from a hardware interrupt. Status keeps them
+// around anyway since that's what the C version does, but to_pushed() and from_pulled() are the
+// way to go between the register and what's on the stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Status(u8);
+
+impl Status {
+    pub fn from_bits(bits: u8) -> Status {
+        Status(bits)
+    }
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    // The byte pushed by PHP or BRK (from_software true) or by an IRQ or NMI (false).
+    pub fn to_pushed(self, from_software: bool) -> u8 {
+        if from_software {
+            self.0 | FLAG_BREAK | FLAG_CONSTANT
+        } else {
+            (self.0 & !FLAG_BREAK) | FLAG_CONSTANT
+        }
+    }
+
+    // What PLP and RTI leave in the register after pulling a byte off the stack.
+    pub fn from_pulled(byte: u8) -> Status {
+        Status((byte & !FLAG_BREAK) | FLAG_CONSTANT)
+    }
+
+    fn get(self, flag: u8) -> bool {
+        self.0 & flag != 0
+    }
+
+    fn set(&mut self, flag: u8, on: bool) {
+        if on {
+            self.0 |= flag;
+        } else {
+            self.0 &= !flag;
+        }
+    }
+
+    pub fn carry(self) -> bool { self.get(FLAG_CARRY) }
+    pub fn zero(self) -> bool { self.get(FLAG_ZERO) }
+    pub fn interrupt(self) -> bool { self.get(FLAG_INTERRUPT) }
+    pub fn decimal(self) -> bool { self.get(FLAG_DECIMAL) }
+    pub fn break_flag(self) -> bool { self.get(FLAG_BREAK) }
+    pub fn overflow(self) -> bool { self.get(FLAG_OVERFLOW) }
+    pub fn negative(self) -> bool { self.get(FLAG_SIGN) }
+
+    pub fn set_carry(&mut self, on: bool) { self.set(FLAG_CARRY, on) }
+    pub fn set_zero(&mut self, on: bool) { self.set(FLAG_ZERO, on) }
+    pub fn set_interrupt(&mut self, on: bool) { self.set(FLAG_INTERRUPT, on) }
+    pub fn set_decimal(&mut self, on: bool) { self.set(FLAG_DECIMAL, on) }
+    pub fn set_break_flag(&mut self, on: bool) { self.set(FLAG_BREAK, on) }
+    pub fn set_overflow(&mut self, on: bool) { self.set(FLAG_OVERFLOW, on) }
+    pub fn set_negative(&mut self, on: bool) { self.set(FLAG_SIGN, on) }
+}
+
+impl From<u8> for Status {
+    fn from(bits: u8) -> Status {
+        Status(bits)
+    }
+}
+
+impl From<Status> for u8 {
+    fn from(status: Status) -> u8 {
+        status.0
+    }
+}
+
+// Prints the flags the way most monitors do, e.g. "Nv-bdIzC": upper case for set, lower case for
+// clear, with a '-' for the constant bit.
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let names = [(FLAG_SIGN, 'N'), (FLAG_OVERFLOW, 'V'), (FLAG_CONSTANT, '-'), (FLAG_BREAK, 'B'),
+                     (FLAG_DECIMAL, 'D'), (FLAG_INTERRUPT, 'I'), (FLAG_ZERO, 'Z'), (FLAG_CARRY, 'C')];
+        for &(flag, name) in names.iter() {
+            let c = if flag == FLAG_CONSTANT || self.get(flag) { name } else { name.to_ascii_lowercase() };
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
 //#define BASE_STACK     0x100
 /* I'm guessing at the type here... */
 const BASE_STACK: u16 = 0x100;
@@ -160,12 +276,13 @@ pub struct CPU {
     pub a: Wrapping<u8>,
     pub x: Wrapping<u8>,
     pub y: Wrapping<u8>,
-    pub status: u8,
+    pub status: Status,
 
     /* Helper variables: */
-    pub instructions_ran: u32,
-    pub clockticks: u32,
-    clockgoal: u32,
+    pub variant: CpuVariant,
+    pub instructions_ran: u64,
+    pub clockticks: u64,
+    clockgoal: u64,
     // This variable is here because some C code tests whether the current addressing mode is 'acc'
     // (accumulator) by checking against the function lookup tables the C version of this emulator
     // used. Since I opted to turn those into a match in the Rust version, I needed a different way
@@ -174,21 +291,77 @@ pub struct CPU {
     // Some of these, perhaps especially 'result', are probably not needed in the struct, but for a
     // direct port, it's easiest, perhaps, to start out not trying to reason about whether a
     // variable's state being carried over between calls will matter.
-    oldpc: Wrapping<u16>,
+    oldpc: u16,
     // EA = Effective Address? This one *is* needed: Addressing modes are implemented as functions
     // that read a byte and set this value to the appropriate address.
-    ea: Wrapping<u16>,
-    reladdr: Wrapping<u16>,
+    ea: u16,
+    reladdr: u16,
     //uint8_t penaltyop, penaltyaddr;
     penaltyop: u8,
     penaltyaddr: u8,
-    value: Wrapping<u16>,
-    result: Wrapping<u16>,
+    value: u16,
+    result: u16,
     opcode: u8,
     oldstatus: u8,
+    // These two are only here so step() can report them. The addressing mode functions set 'mode'
+    // and the branch instructions set 'branch_taken'.
+    mode: AddrMode,
+    branch_taken: bool,
+    // Set by the 65C02's WAI.
+    waiting: bool,
+    // Whether the last execute_instruction() started by taking an interrupt.
+    interrupted: bool,
+
+    // Do every bus access the real chip does, in the order it does them, dummy reads and all,
+    // instead of just the ones needed to get the right answer? Slower, but it's what you want if
+    // anything behind the Backplane cares when (or how often) it gets read or written. This is
+    // only implemented for the NMOS variants; the 65C02 ignores it.
+    pub cycle_accurate: bool,
+    // True while an instruction is being run in cycle-accurate mode, along with the byte that's
+    // been read (or is about to be written) and a count of bus cycles so far.
+    bus_mode: bool,
+    latch: u8,
+    bus_cycles: u32,
+    // Set by the NMOS JAM opcodes and the 65C02's STP. See is_jammed().
+    jam: Option<JamReason>,
 
     // Call the Backplane::each_instr() method after every instruction?
     pub do_callback: bool,
+
+    // Have exec() stop with StopReason::TrapDetected when an instruction leaves the pc where it
+    // was, i.e. a "JMP *" or a taken "BNE *". Test ROMs park the CPU in loops like that to say
+    // they've passed or failed, and a real program basically never does.
+    pub detect_traps: bool,
+
+    // Where trace lines go while tracing is on. See trace_to().
+    tracer: Option<trace::Tracer>,
+
+    // Breakpoints and watchpoints for exec() to stop at; see breakpoints.rs.
+    pub breakpoints: breakpoints::Breakpoints,
+
+    // What's been run, for going backwards. See record_history().
+    history: Option<history::History>,
+
+    // The "magic constant" ANE ($8B) and LXA ($AB) OR into the accumulator. It varies from chip to
+    // chip (and with temperature...), so pick whatever the software you're running expects.
+    // $EE is the usual choice.
+    pub ane_magic: u8,
+    pub lxa_magic: u8,
+
+    // Interrupt line state. See set_irq() and set_nmi().
+    irq_lines: u32,
+    nmi_line: bool,
+    nmi_pending: bool,
+    // The RDY and SO input pins. See set_rdy() and set_so().
+    rdy_line: bool,
+    rdy_hold: u32,
+    so_line: bool,
+    // How many cycles the CPU has spent stalled by RDY, in total.
+    pub rdy_stalled_cycles: u64,
+    // CLI, SEI and PLP change the interrupt flag too late for the interrupt poll that follows them
+    // to notice, so after one of those this holds the value of the flag the poll should use
+    // instead.
+    delayed_i: Option<bool>,
 }
 
 //externally supplied functions
@@ -203,7 +376,131 @@ pub trait Backplane {
     fn read(&self, address: u16) -> u8;
     fn write(&mut self, address: u16, value: u8);
 
-    fn each_instr(&mut self, cpu: &mut CPU) -> bool;
+    // Called after every instruction when CPU::do_callback is set. What it returns decides whether
+    // CPU::exec() carries on; see Control.
+    fn each_instr(&mut self, cpu: &mut CPU) -> Control;
+
+    // Called as CPU cycles go by inside CPU::exec() and CPU::step(), so that timers, video chips
+    // and the like can stay in lockstep with the CPU. Normally it gets called once per
+    // instruction with the number of cycles the instruction took (interrupt sequence included);
+    // in cycle-accurate mode it's called with 1 right after every single bus access instead.
+    // Implementing it is optional.
+    fn tick(&mut self, _cycles: u32) {
+    }
+
+    // The RDY pin as a device on the bus sees it, for DMA that starts and stops in the middle of
+    // an instruction. In cycle-accurate mode this is asked before every read cycle, and the CPU
+    // stays put, calling tick(1) each cycle, until it says true again; otherwise it's only asked
+    // between instructions, like CPU::set_rdy(). Implementing it is optional.
+    fn ready(&mut self) -> bool {
+        true
+    }
+
+    // /NMI as a device on the bus drives it, for NMIs that come in partway through an
+    // instruction. Return true once for each time the device pulls the line; that latches an NMI
+    // the same as CPU::set_nmi() does. It's asked right after every tick(), so in cycle-accurate
+    // mode that's after every bus access, and otherwise once per instruction. Implementing it is
+    // optional.
+    fn nmi(&mut self) -> bool {
+        false
+    }
+}
+
+// The addressing modes, one per addr_* function below. (instruction_tables.json uses the C
+// version's names for them: imp, acc, imm, zp, zpx, zpy, rel, abso, absx, absy, ind, indx, indy.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddrMode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Relative,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    Indirect,
+    IndirectX,
+    IndirectY,
+    // 65C02 only: (zp), (abs,X) for JMP, and the zp,rel of BBR/BBS.
+    ZeroPageIndirect,
+    AbsoluteXIndirect,
+    ZeroPageRelative,
+}
+
+impl AddrMode {
+    // How many operand bytes follow the opcode.
+    pub fn operand_len(self) -> u16 {
+        match self {
+            AddrMode::Implied | AddrMode::Accumulator => 0,
+            AddrMode::Immediate | AddrMode::ZeroPage | AddrMode::ZeroPageX | AddrMode::ZeroPageY |
+            AddrMode::Relative | AddrMode::IndirectX | AddrMode::IndirectY |
+            AddrMode::ZeroPageIndirect => 1,
+            AddrMode::Absolute | AddrMode::AbsoluteX | AddrMode::AbsoluteY | AddrMode::Indirect |
+            AddrMode::AbsoluteXIndirect | AddrMode::ZeroPageRelative => 2,
+        }
+    }
+}
+
+// Why the CPU stopped fetching instructions. Either way, 'pc' is the address of the opcode
+// responsible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JamReason {
+    // One of the NMOS JAM opcodes ($02, $12, ... $F2).
+    Jam { opcode: u8, pc: u16 },
+    // The 65C02's STP instruction.
+    Stopped { pc: u16 },
+}
+
+// What Backplane::each_instr() tells CPU::exec() to do next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    // Stop, and hand this code back in StopReason::HostRequested. What it means is up to you.
+    Stop(u32),
+    // Stop because the host considers the instruction at the new pc a breakpoint.
+    Breakpoint,
+}
+
+// Why CPU::exec() returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    // Ran through the whole budget (or reached the exec_until() target).
+    BudgetExhausted,
+    // Backplane::each_instr() returned Control::Stop with this code.
+    HostRequested(u32),
+    // Backplane::each_instr() returned Control::Breakpoint, or the CPU got to an execute breakpoint
+    // in CPU::breakpoints; this is the pc it stopped at.
+    Breakpoint(u16),
+    // The CPU is jammed (or STPed); see CPU::jam_reason().
+    Jammed,
+    // With CPU::detect_traps set, an instruction jumped or branched back to itself, which test
+    // ROMs do to signal that they're done. This is the address of that instruction.
+    TrapDetected(u16),
+    // A read or write watchpoint in CPU::breakpoints went off. (Execute breakpoints there stop
+    // with Breakpoint, like the Control::Breakpoint ones.)
+    Watchpoint(breakpoints::Hit),
+}
+
+// What CPU::step() hands back about the instruction it ran.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepResult {
+    // False if no instruction ran, because the CPU is jammed, waiting in a WAI or stalled on RDY.
+    // Then pc is where it's stopped, opcode and mode are still those of the last instruction that
+    // did run (the JAM, STP or WAI, say), ea is None and branch_taken is false.
+    pub ran: bool,
+    // Address the opcode was fetched from.
+    pub pc: u16,
+    pub opcode: u8,
+    pub mode: AddrMode,
+    // The effective address the addressing mode worked out, or the branch target for branches.
+    // None for implied and accumulator instructions, which don't have one.
+    pub ea: Option<u16>,
+    // Cycles taken, including the page-crossing penalty, the extra cycles for taken branches, and
+    // the interrupt sequence if an interrupt was taken first.
+    pub cycles: u32,
+    pub branch_taken: bool,
 }
 
 impl CPU {
@@ -214,28 +511,52 @@ impl CPU {
     // flexibility on the caller's part and perhaps less borrowing tangles if we do not require the
     // CPU struct to own its memory.
 
-    pub fn new() -> CPU {
+    pub fn new(variant: CpuVariant) -> CPU {
         CPU {
             pc: Wrapping(0),
             sp: Wrapping(0xFD),
             a: Wrapping(0),
             x: Wrapping(0),
             y: Wrapping(0),
-            status: Wrapping(0),
+            status: Status(0),
             addr_acc: false,
+            variant,
             instructions_ran: 0,
             clockticks: 0,
             clockgoal: 0,
-            oldpc: Wrapping(0),
-            ea: Wrapping(0),
-            reladdr: Wrapping(0),
+            oldpc: 0,
+            ea: 0,
+            reladdr: 0,
             penaltyaddr: 0,
             penaltyop: 0,
-            value: Wrapping(0),
-            result: Wrapping(0),
+            value: 0,
+            result: 0,
             opcode: 0,
             oldstatus: 0,
-            do_callback: true
+            mode: AddrMode::Implied,
+            branch_taken: false,
+            waiting: false,
+            interrupted: false,
+            cycle_accurate: false,
+            bus_mode: false,
+            latch: 0,
+            bus_cycles: 0,
+            jam: None,
+            do_callback: true,
+            detect_traps: false,
+            tracer: None,
+            breakpoints: breakpoints::Breakpoints::new(),
+            history: None,
+            ane_magic: 0xEE,
+            lxa_magic: 0xEE,
+            irq_lines: 0,
+            nmi_line: false,
+            nmi_pending: false,
+            rdy_line: true,
+            rdy_hold: 0,
+            so_line: false,
+            rdy_stalled_cycles: 0,
+            delayed_i: None,
         }
     }
 
@@ -260,9 +581,9 @@ impl CPU {
     //    sp -= 2;
     //}
     fn push16<T: Backplane>(&mut self, mem: &mut T, pushval: u16) {
-        mem.write(BASE_STACK + (self.sp as u16), ((pushval >> 8) & 0x00FF) as u8);
+        self.bus_write(mem, BASE_STACK + (self.sp.0 as u16), ((pushval >> 8) & 0x00FF) as u8);
         self.sp -= 1;
-        mem.write(BASE_STACK + (self.sp as u16), (pushval & 0x00FF) as u8);
+        self.bus_write(mem, BASE_STACK + (self.sp.0 as u16), (pushval & 0x00FF) as u8);
         self.sp -= 1;
     }
 
@@ -270,7 +591,7 @@ impl CPU {
     //    write6502(BASE_STACK + sp--, pushval);
     //}
     fn push8<T: Backplane>(&mut self, mem: &mut T, pushval: u8) {
-        mem.write(BASE_STACK + (self.sp as u16), pushval);
+        self.bus_write(mem, BASE_STACK + (self.sp.0 as u16), pushval);
         self.sp -= 1;
     }
 
@@ -280,9 +601,9 @@ impl CPU {
     //    sp += 2;
     //    return(temp16);
     //}
-    fn pull16<T: Backplane>(&mut self, mem: &T) -> u16 {
-        let mut val: u16 = mem.read(BASE_STACK + ((self.sp as u16 + 1) & 0x00FF)) as u16;
-        val            |= (mem.read(BASE_STACK + ((self.sp as u16 + 2) & 0x00FF)) as u16) << 8;
+    fn pull16<T: Backplane>(&mut self, mem: &mut T) -> u16 {
+        let mut val: u16 = self.bus_read(mem, BASE_STACK + ((self.sp.0 as u16 + 1) & 0x00FF)) as u16;
+        val            |= (self.bus_read(mem, BASE_STACK + ((self.sp.0 as u16 + 2) & 0x00FF)) as u16) << 8;
         self.sp += 2;
         val
     }
@@ -290,10 +611,9 @@ impl CPU {
     //uint8_t pull8() {
     //    return (read6502(BASE_STACK + ++sp));
     //}
-    fn pull8<T: Backplane>(&mut self, mem: &T) -> u8 {
-        let val = mem.read(BASE_STACK + (self.sp as u16));
-        self.sp += 1;
-        val
+    fn pull8<T: Backplane>(&mut self, mem: &mut T) -> u8 {
+        self.sp += Wrapping(1);
+        self.bus_read(mem, BASE_STACK + (self.sp.0 as u16))
     }
 
     //void reset6502() {
@@ -304,25 +624,54 @@ impl CPU {
     //    sp = 0xFD;
     //    status |= FLAG_CONSTANT;
     //}
-    fn reset<T: Backplane>(&mut self, mem: &T) {
+    //
+    // The C version didn't touch the interrupt flag or the cycle count here, but a real 6502 comes
+    // out of reset with interrupts disabled, having spent 7 cycles on the (dummy) interrupt
+    // sequence, so we do both.
+    pub fn reset<T: Backplane>(&mut self, mem: &T) {
         self.pc = Wrapping(mem.read(0xFFFC) as u16 | ((mem.read(0xFFFD) as u16) << 8));
         self.a = Wrapping(0);
         self.x = Wrapping(0);
         self.y = Wrapping(0);
         self.sp = Wrapping(0xFD);
-        self.status |= FLAG_CONSTANT;
+        self.status.0 |= FLAG_CONSTANT | FLAG_INTERRUPT;
+        self.clockticks += 7;
+        // Those cycles aren't part of anyone's exec() budget, so the next exec() starts counting
+        // from here rather than taking them back out as overshoot.
+        self.clockgoal = self.clockticks;
+        // An NMI edge that came in before the reset is forgotten; the lines themselves are driven
+        // from outside, so they stay as they are.
+        self.nmi_pending = false;
+        self.delayed_i = None;
+        self.waiting = false;
+        self.jam = None;
+        // The 65C02 also clears decimal mode on reset; the NMOS chips leave it random.
+        if self.variant.is_cmos() {
+            self.flagclear(FLAG_DECIMAL);
+        }
     }
 
 
     //addressing mode functions, calculates effective addresses
+    //
+    // In cycle-accurate mode these don't do anything; bus_addressing() has already done the work
+    // (with all the bus accesses in the right order) by the time they're called.
     //static void imp() { //implied
     //}
     fn addr_implied<T: Backplane>(&mut self, _mem: &T) {
+        if self.bus_mode {
+            return;
+        }
+        self.mode = AddrMode::Implied;
     }
 
     //static void acc() { //accumulator
     //}
     fn addr_accumulator<T: Backplane>(&mut self, _mem: &T) {
+        if self.bus_mode {
+            return;
+        }
+        self.mode = AddrMode::Accumulator;
         self.addr_acc = true;
     }
 
@@ -330,34 +679,50 @@ impl CPU {
     //    ea = pc++;
     //}
     fn addr_immediate<T: Backplane>(&mut self, _mem: &T) {
-        self.ea = Wrapping(self.pc as u16);
-        self.pc = Wrapping(self.pc + Wrapping(1));
+        if self.bus_mode {
+            return;
+        }
+        self.mode = AddrMode::Immediate;
+        self.ea = self.pc.0;
+        self.pc += Wrapping(1);
     }
 
     //static void zp() { //zero-page
     //    ea = (uint16_t)read6502((uint16_t)pc++);
     //}
     fn addr_zeropage<T: Backplane>(&mut self, mem: &T) {
+        if self.bus_mode {
+            return;
+        }
+        self.mode = AddrMode::ZeroPage;
         self.ea = mem.read(self.pc.0) as u16;
-        self.pc = self.pc + Wrapping(1);
+        self.pc += Wrapping(1);
     }
 
     //static void zpx() { //zero-page,X
     //    ea = ((uint16_t)read6502((uint16_t)pc++) + (uint16_t)x) & 0xFF; //zero-page wraparound
     //}
     fn addr_zeropage_x<T: Backplane>(&mut self, mem: &T) {
-        self.ea = Wrapping(mem.read(self.pc.0) as u16 + (self.x.0 as u16)) & 0x00FF;
+        if self.bus_mode {
+            return;
+        }
+        self.mode = AddrMode::ZeroPageX;
+        self.ea = (mem.read(self.pc.0) as u16 + self.x.0 as u16) & 0x00FF;
         // ( the & 0x00FF thing for zero-page wraparound)
-        self.pc = self.pc + Wrapping(1);
+        self.pc += Wrapping(1);
     }
 
     //static void zpy() { //zero-page,Y
     //    ea = ((uint16_t)read6502((uint16_t)pc++) + (uint16_t)y) & 0xFF; //zero-page wraparound
     //}
     fn addr_zeropage_y<T: Backplane>(&mut self, mem: &T) {
-        self.ea = mem.read(self.pc.0) as u16 + (self.y.0 as u16 & 0x00FF);
-        // ( the & 0x00FF thing maybe for zero-page wraparound? blehhh)
-        self.pc = self.pc + Wrapping(1);
+        if self.bus_mode {
+            return;
+        }
+        self.mode = AddrMode::ZeroPageY;
+        self.ea = (mem.read(self.pc.0) as u16 + self.y.0 as u16) & 0x00FF;
+        // (zero-page wraparound, same as addr_zeropage_x)
+        self.pc += Wrapping(1);
     }
 
     //static void rel() { //relative for branch ops (8-bit immediate value, sign-extended)
@@ -365,11 +730,15 @@ impl CPU {
     //    if (reladdr & 0x80) reladdr |= 0xFF00;
     //}
     fn addr_relative_branch<T: Backplane>(&mut self, mem: &T) {
+        if self.bus_mode {
+            return;
+        }
+        self.mode = AddrMode::Relative;
         self.reladdr = mem.read(self.pc.0) as u16;
         if self.reladdr & 0x0080 != 0 {
             self.reladdr |= 0xFF00;
         }
-        self.pc = self.pc + Wrapping(1);
+        self.pc += Wrapping(1);
     }
 
     //static void abso() { //absolute
@@ -377,9 +746,13 @@ impl CPU {
     //    pc += 2;
     //}
     fn addr_absolute<T: Backplane>(&mut self, mem: &T) {
+        if self.bus_mode {
+            return;
+        }
+        self.mode = AddrMode::Absolute;
         self.ea = mem.read(self.pc.0) as u16;
-        self.ea |= (mem.read(self.pc + 1) as u16) << 8;
-        self.pc = self.pc + Wrapping(2);
+        self.ea |= (mem.read(self.pc.0.wrapping_add(1)) as u16) << 8;
+        self.pc += Wrapping(2);
     }
 
     //static void absx() { //absolute,X
@@ -395,17 +768,20 @@ impl CPU {
     //    pc += 2;
     //}
     fn addr_absolute_x<T: Backplane>(&mut self, mem: &T) {
-        let startpage: u16;
-        self.ea = mem.read(self.pc.0) as u16 | (mem.read(self.pc + 1) as u16) << 8;
-        startpage = self.ea & 0xFF00;
-        self.ea += self.x.0 as u16;
+        if self.bus_mode {
+            return;
+        }
+        self.mode = AddrMode::AbsoluteX;
+        self.ea = mem.read(self.pc.0) as u16 | (mem.read(self.pc.0.wrapping_add(1)) as u16) << 8;
+        let startpage: u16 = self.ea & 0xFF00;
+        self.ea = self.ea.wrapping_add(self.x.0 as u16);
 
         if startpage != (self.ea & 0xFF00) {
             // original source: "one cycle penalty for page-crossing on some opcodes"
             self.penaltyaddr = 1;
         }
 
-        self.pc = self.pc + Wrapping(2);
+        self.pc += Wrapping(2);
     }
 
     //static void absy() { //absolute,Y
@@ -421,17 +797,20 @@ impl CPU {
     //    pc += 2;
     //}
     fn addr_absolute_y<T: Backplane>(&mut self, mem: &T) {
-        let startpage: u16;
-        self.ea = mem.read(self.pc.0) as u16 | (mem.read(self.pc + 1) as u16) << 8;
-        startpage = self.ea & 0xFF00;
-        self.ea += self.y.0 as u16;
+        if self.bus_mode {
+            return;
+        }
+        self.mode = AddrMode::AbsoluteY;
+        self.ea = mem.read(self.pc.0) as u16 | (mem.read(self.pc.0.wrapping_add(1)) as u16) << 8;
+        let startpage: u16 = self.ea & 0xFF00;
+        self.ea = self.ea.wrapping_add(self.y.0 as u16);
 
         if startpage != (self.ea & 0xFF00) {
             // original source: "one cycle penalty for page-crossing on some opcodes"
             self.penaltyaddr = 1;
         }
 
-        self.pc = self.pc + Wrapping(2);
+        self.pc += Wrapping(2);
     }
 
     //static void ind() { //indirect
@@ -442,13 +821,20 @@ impl CPU {
     //    pc += 2;
     //}
     fn addr_indirect<T: Backplane>(&mut self, mem: &T) {
-        let eahelp: u16;
-        let eahelp2: u16;
-        eahelp = mem.read(self.pc.0) as u16 | (mem.read(self.pc + 1) as u16) << 8;
+        if self.bus_mode {
+            return;
+        }
+        self.mode = AddrMode::Indirect;
+        let eahelp: u16 = mem.read(self.pc.0) as u16 | (mem.read(self.pc.0.wrapping_add(1)) as u16) << 8;
         // original source: "replicate 6502 page-boundary wraparound bug"
-        eahelp2 = (eahelp & 0xFF00) | ((eahelp + 1) & 0x00FF);
+        // (The 65C02 fixed the bug, and spends an extra cycle doing so; see its ticks table.)
+        let eahelp2: u16 = if self.variant.is_cmos() {
+            eahelp.wrapping_add(1)
+        } else {
+            (eahelp & 0xFF00) | (eahelp.wrapping_add(1) & 0x00FF)
+        };
         self.ea = mem.read(eahelp) as u16 | (mem.read(eahelp2) as u16) << 8;
-        self.pc = self.pc + Wrapping(2);
+        self.pc += Wrapping(2);
     }
 
     //static void indx() { // (indirect,X)
@@ -457,10 +843,13 @@ impl CPU {
     //    ea = (uint16_t)read6502(eahelp & 0x00FF) | ((uint16_t)read6502((eahelp+1) & 0x00FF) << 8);
     //}
     fn addr_indirect_x<T: Backplane>(&mut self, mem: &T) {
-        let eahelp: u16;
-        eahelp = (mem.read(self.pc.0) as u16 + self.x.0 as u16) & 0x00FF; // original: "zero-page wraparound for table"
+        if self.bus_mode {
+            return;
+        }
+        self.mode = AddrMode::IndirectX;
+        let eahelp: u16 = (mem.read(self.pc.0) as u16 + self.x.0 as u16) & 0x00FF; // original: "zero-page wraparound for table"
         self.ea = mem.read(eahelp & 0x00FF) as u16 | (mem.read((eahelp + 1) & 0x00FF) as u16) << 8;
-        self.pc = self.pc + Wrapping(1);
+        self.pc += Wrapping(1);
     }
 
     //static void indy() { // (indirect),Y
@@ -476,12 +865,16 @@ impl CPU {
     //    }
     //}
     fn addr_indirect_y<T: Backplane>(&mut self, mem: &T) {
+        if self.bus_mode {
+            return;
+        }
+        self.mode = AddrMode::IndirectY;
         let eahelp: u16 = mem.read(self.pc.0) as u16;
-        self.pc = self.pc + Wrapping(1);
+        self.pc += Wrapping(1);
         let eahelp2: u16 = (eahelp & 0xFF00) | ((eahelp + 1) & 0x00FF); // original: "zero-page wraparound"
         self.ea = mem.read(eahelp) as u16 | ((mem.read(eahelp2) as u16) << 8);
         let startpage: u16 = self.ea & 0xFF00;
-        self.ea += self.y.0 as u16;
+        self.ea = self.ea.wrapping_add(self.y.0 as u16);
 
         if startpage != (self.ea & 0xFF00) { // original: "one-cycle penalty for page crossing on some opcodes"
             self.penaltyaddr = 1;
@@ -489,6 +882,39 @@ impl CPU {
     }
 
 
+    // The 65C02 adds a few addressing modes of its own, which the C version never had.
+
+    // (zp): like (zp),Y, without the Y.
+    fn addr_zeropage_indirect<T: Backplane>(&mut self, mem: &T) {
+        self.mode = AddrMode::ZeroPageIndirect;
+        let eahelp: u16 = mem.read(self.pc.0) as u16;
+        self.pc += Wrapping(1);
+        let eahelp2: u16 = (eahelp + 1) & 0x00FF; // zero-page wraparound
+        self.ea = mem.read(eahelp) as u16 | ((mem.read(eahelp2) as u16) << 8);
+    }
+
+    // (abs,X): only used by JMP.
+    fn addr_absolute_x_indirect<T: Backplane>(&mut self, mem: &T) {
+        self.mode = AddrMode::AbsoluteXIndirect;
+        let mut eahelp: u16 = mem.read(self.pc.0) as u16 | (mem.read(self.pc.0.wrapping_add(1)) as u16) << 8;
+        eahelp = eahelp.wrapping_add(self.x.0 as u16);
+        self.ea = mem.read(eahelp) as u16 | ((mem.read(eahelp.wrapping_add(1)) as u16) << 8);
+        self.pc += Wrapping(2);
+    }
+
+    // zp,rel: BBR and BBS have both a zero-page address to test and a branch offset. The address
+    // goes in ea and the offset in reladdr, same as addr_relative_branch() would leave it.
+    fn addr_zeropage_relative<T: Backplane>(&mut self, mem: &T) {
+        self.mode = AddrMode::ZeroPageRelative;
+        self.ea = mem.read(self.pc.0) as u16;
+        self.reladdr = mem.read(self.pc.0.wrapping_add(1)) as u16;
+        if self.reladdr & 0x0080 != 0 {
+            self.reladdr |= 0xFF00;
+        }
+        self.pc += Wrapping(2);
+    }
+
+
     //static uint16_t getvalue() {
     //    if (addrtable[opcode] == acc) return((uint16_t)a);
     //        else return((uint16_t)read6502(ea));
@@ -497,6 +923,9 @@ impl CPU {
         // But why is it u16...?
         if self.addr_acc {
             self.a.0 as u16
+        } else if self.bus_mode {
+            // Cycle-accurate mode has already read the operand at the right moment.
+            self.latch as u16
         } else {
             mem.read(self.ea) as u16
         }
@@ -506,7 +935,7 @@ impl CPU {
     //    return((uint16_t)read6502(ea) | ((uint16_t)read6502(ea+1) << 8));
     //}
     fn getvalue_16<T: Backplane>(&mut self, mem: &T) -> u16 {
-        mem.read(self.ea) as u16 | ((mem.read(self.ea + 1) as u16) << 8)
+        mem.read(self.ea) as u16 | ((mem.read(self.ea.wrapping_add(1)) as u16) << 8)
     }
 
     //static void putvalue(uint16_t saveval) {
@@ -515,7 +944,10 @@ impl CPU {
     //}
     fn putvalue<T: Backplane>(&mut self, mem: &mut T, saveval: u16) {
         if self.addr_acc {
-            self.a = (saveval & 0x00FF) as u8;
+            self.a = Wrapping((saveval & 0x00FF) as u8);
+        } else if self.bus_mode {
+            // ...and will do the write itself, once the instruction is done with it.
+            self.latch = (saveval & 0x00FF) as u8;
         } else {
             mem.write(self.ea, (saveval & 0x00FF) as u8);
         }
@@ -543,11 +975,11 @@ impl CPU {
     //
     // We could just write it out by hand every time. But these probably don't cost *too* much.
     fn flagset(&mut self, flag: u8) {
-        self.status |= flag;
+        self.status.0 |= flag;
     }
 
     fn flagclear(&mut self, flag: u8) {
-        self.status &= !flag;
+        self.status.0 &= !flag;
     }
 
     //flag calculation macros
@@ -598,9 +1030,9 @@ impl CPU {
         }
     }
 
-    fn flagcalc_overflow(&mut self, n: Wrapping<u16>, m: Wrapping<u8>, o: Wrapping<u16>) {
+    fn flagcalc_overflow(&mut self, n: u16, m: u8, o: u16) {
         // (The ^ is not exponentiation but bitwise XOR. Most people probably know this.)
-        if (n ^ Wrapping(m as u16)) & ((n ^ o) & Wrapping(0x0080)) != 0 {
+        if (n ^ m as u16) & ((n ^ o) & 0x0080) != 0 {
             self.flagset(FLAG_OVERFLOW);
         } else {
             self.flagclear(FLAG_OVERFLOW);
@@ -608,8 +1040,8 @@ impl CPU {
     }
 
     //#define saveaccum(n) a = (uint8_t)((n) & 0x00FF)
-    fn save_accumulator(&mut self, n: Wrapping<u16>) {
-        self.a = (n & 0x00FF) as u8;
+    fn save_accumulator(&mut self, n: u16) {
+        self.a = Wrapping((n & 0x00FF) as u8);
     }
 
 
@@ -620,8 +1052,12 @@ impl CPU {
         self.penaltyop = 1;
     //    value = getvalue();
         self.value = self.getvalue(mem);
+        if self.variant.is_cmos() && self.variant.has_bcd() && self.status.0 & FLAG_DECIMAL != 0 {
+            return self.adc_decimal_cmos();
+        }
     //    result = (uint16_t)a + value + (uint16_t)(status & FLAG_CARRY);
-        self.result = self.a.0 as u16 + self.value + (self.status & FLAG_CARRY) as u16;
+        let carry = (self.status.0 & FLAG_CARRY) as u16;
+        self.result = self.a.0 as u16 + self.value + carry;
     //    carrycalc(result);
     //    zerocalc(result);
     //    overflowcalc(result, a, value);
@@ -630,17 +1066,16 @@ impl CPU {
         // trying to use a value (e.g., self.result) I've already borrowed (by calling, e.g.,
         // self.flagcalc_carry). It feels like there ought to be a less dumb way to do it, but for
         // now this at least compiles...
-        let (r, a, v) = (self.result.0, self.a.0, self.value.0);
+        let (r, a, v) = (self.result, self.a.0, self.value);
         self.flagcalc_carry(r);
         self.flagcalc_zero(r);
         self.flagcalc_overflow(r, a, v);
         self.flagcalc_sign(r);
         
-        // I don't *really* want an actual if statement to be compiled here but I decided to
-        // continue in the optimistic assumption that the Rust compiler and/or LLVM will notice
-        // it's a const value and optimize it away.
+        // This used to be a const, so the if went away at compile time; now it's a check of the
+        // CPU variant (unless the "no-bcd" feature is on, in which case it's a constant again).
     //    #ifndef NES_CPU
-        if !NES_CPU {
+        if self.variant.has_bcd() {
     //    if (status & FLAG_DECIMAL) {
     //        clearcarry();
             
@@ -655,18 +1090,10 @@ impl CPU {
     //        clockticks6502++;
     //    }
     //    #endif
-            if self.status & FLAG_DECIMAL != 0 {
-                self.flagclear(FLAG_CARRY);
-
-                if (self.a.0 & 0x0F) > 0x09 {
-                    self.a += Wrapping(0x06);
-                }
-                if (self.a.0 & 0xF0) > 0x90 {
-                    self.a += Wrapping(0x60);
-                    self.flagset(FLAG_CARRY);
-                }
-
-                self.clockticks += 1;
+            // The C version adjusted a here and then overwrote it with the binary result below,
+            // and charged a cycle the NMOS chips don't actually take.
+            if self.status.0 & FLAG_DECIMAL != 0 {
+                self.adc_decimal_nmos(carry);
             }
         }
        
@@ -698,6 +1125,10 @@ impl CPU {
 
     //static void asl() {
     fn inst_asl<T: Backplane>(&mut self, mem: &mut T) {
+        // On the 65C02, shifts only take the extra abs,X cycle when a page is crossed.
+        if self.variant.is_cmos() {
+            self.penaltyop = 1;
+        }
     //    value = getvalue();
     //    result = value << 1;
         self.value = self.getvalue(mem);
@@ -724,14 +1155,18 @@ impl CPU {
     //        if ((oldpc & 0xFF00) != (pc & 0xFF00)) clockticks6502 += 2; //check if jump crossed a page boundary
     //            else clockticks6502++;
     //    }
-        if (self.status & FLAG_CARRY) == 0 {
-            self.oldpc = self.pc;
-            self.pc = self.pc + Wrapping(self.reladdr);
-            if (self.oldpc & 0xFF00) != (self.pc & 0xFF00) {
-                // original: "check if jump crossed a page boundary"
-                self.clockticks += 2;
-            } else {
-                self.clockticks += 1;
+        if (self.status.0 & FLAG_CARRY) == 0 {
+            self.oldpc = self.pc.0;
+            self.pc += Wrapping(self.reladdr);
+            self.branch_taken = true;
+            // (In cycle-accurate mode run_one_op_bus() counts these as it does the dummy reads.)
+            if !self.bus_mode {
+                if (self.oldpc & 0xFF00) != (self.pc.0 & 0xFF00) {
+                    // original: "check if jump crossed a page boundary"
+                    self.clockticks += 2;
+                } else {
+                    self.clockticks += 1;
+                }
             }
         }
     //}
@@ -746,14 +1181,18 @@ impl CPU {
     //            else clockticks6502++;
     //    }
     //}
-        if (self.status & FLAG_CARRY) == FLAG_CARRY {
-            self.oldpc = self.pc;
-            self.pc = self.pc + Wrapping(self.reladdr);
-            if (self.oldpc & 0xFF00) != (self.pc & 0xFF00) {
-                // original: "check if jump crossed a page boundary"
-                self.clockticks += 2;
-            } else {
-                self.clockticks += 1;
+        if (self.status.0 & FLAG_CARRY) == FLAG_CARRY {
+            self.oldpc = self.pc.0;
+            self.pc += Wrapping(self.reladdr);
+            self.branch_taken = true;
+            // (In cycle-accurate mode run_one_op_bus() counts these as it does the dummy reads.)
+            if !self.bus_mode {
+                if (self.oldpc & 0xFF00) != (self.pc.0 & 0xFF00) {
+                    // original: "check if jump crossed a page boundary"
+                    self.clockticks += 2;
+                } else {
+                    self.clockticks += 1;
+                }
             }
         }
     }
@@ -767,14 +1206,18 @@ impl CPU {
     //            else clockticks6502++;
     //    }
     //}
-        if (self.status & FLAG_ZERO) == FLAG_ZERO {
-            self.oldpc = self.pc;
-            self.pc = self.pc + Wrapping(self.reladdr);
-            if (self.oldpc & 0xFF00) != (self.pc & 0xFF00) {
-                // original: "check if jump crossed a page boundary"
-                self.clockticks += 2;
-            } else {
-                self.clockticks += 1;
+        if (self.status.0 & FLAG_ZERO) == FLAG_ZERO {
+            self.oldpc = self.pc.0;
+            self.pc += Wrapping(self.reladdr);
+            self.branch_taken = true;
+            // (In cycle-accurate mode run_one_op_bus() counts these as it does the dummy reads.)
+            if !self.bus_mode {
+                if (self.oldpc & 0xFF00) != (self.pc.0 & 0xFF00) {
+                    // original: "check if jump crossed a page boundary"
+                    self.clockticks += 2;
+                } else {
+                    self.clockticks += 1;
+                }
             }
         }
     }
@@ -792,7 +1235,12 @@ impl CPU {
 
         let r = self.result;
         self.flagcalc_zero(r);
-        self.status = (self.status & 0x3F) | (self.value & 0x00C0) as u8;
+        // The 65C02's BIT #imm only sets Z. (The penalty is for its BIT abs,X; NMOS never uses
+        // an indexed mode for BIT, so this doesn't matter there.)
+        if self.mode != AddrMode::Immediate {
+            self.status.0 = (self.status.0 & 0x3F) | (self.value & 0x00C0) as u8;
+        }
+        self.penaltyop = 1;
     }
 
     //static void bmi() {
@@ -804,14 +1252,18 @@ impl CPU {
     //            else clockticks6502++;
     //    }
     //}
-        if (self.status & FLAG_SIGN) == FLAG_SIGN {
-            self.oldpc = self.pc;
-            self.pc = self.pc + Wrapping(self.reladdr);
-            if (self.oldpc & 0xFF00) != (self.pc & 0xFF00) {
-                // original: "check if jump crossed a page boundary"
-                self.clockticks += 2;
-            } else {
-                self.clockticks += 1;
+        if (self.status.0 & FLAG_SIGN) == FLAG_SIGN {
+            self.oldpc = self.pc.0;
+            self.pc += Wrapping(self.reladdr);
+            self.branch_taken = true;
+            // (In cycle-accurate mode run_one_op_bus() counts these as it does the dummy reads.)
+            if !self.bus_mode {
+                if (self.oldpc & 0xFF00) != (self.pc.0 & 0xFF00) {
+                    // original: "check if jump crossed a page boundary"
+                    self.clockticks += 2;
+                } else {
+                    self.clockticks += 1;
+                }
             }
         }
     }
@@ -825,14 +1277,18 @@ impl CPU {
     //            else clockticks6502++;
     //    }
     //}
-        if (self.status & FLAG_ZERO) == 0 {
-            self.oldpc = self.pc;
-            self.pc = self.pc + Wrapping(self.reladdr);
-            if (self.oldpc & 0xFF00) != (self.pc & 0xFF00) {
-                // original: "check if jump crossed a page boundary"
-                self.clockticks += 2;
-            } else {
-                self.clockticks += 1;
+        if (self.status.0 & FLAG_ZERO) == 0 {
+            self.oldpc = self.pc.0;
+            self.pc += Wrapping(self.reladdr);
+            self.branch_taken = true;
+            // (In cycle-accurate mode run_one_op_bus() counts these as it does the dummy reads.)
+            if !self.bus_mode {
+                if (self.oldpc & 0xFF00) != (self.pc.0 & 0xFF00) {
+                    // original: "check if jump crossed a page boundary"
+                    self.clockticks += 2;
+                } else {
+                    self.clockticks += 1;
+                }
             }
         }
     }
@@ -846,14 +1302,18 @@ impl CPU {
     //            else clockticks6502++;
     //    }
     //}
-        if (self.status & FLAG_SIGN) == 0 {
-            self.oldpc = self.pc;
-            self.pc = self.pc + Wrapping(self.reladdr);
-            if (self.oldpc & 0xFF00) != (self.pc & 0xFF00) {
-                // original: "check if jump crossed a page boundary"
-                self.clockticks += 2;
-            } else {
-                self.clockticks += 1;
+        if (self.status.0 & FLAG_SIGN) == 0 {
+            self.oldpc = self.pc.0;
+            self.pc += Wrapping(self.reladdr);
+            self.branch_taken = true;
+            // (In cycle-accurate mode run_one_op_bus() counts these as it does the dummy reads.)
+            if !self.bus_mode {
+                if (self.oldpc & 0xFF00) != (self.pc.0 & 0xFF00) {
+                    // original: "check if jump crossed a page boundary"
+                    self.clockticks += 2;
+                } else {
+                    self.clockticks += 1;
+                }
             }
         }
     }
@@ -866,12 +1326,25 @@ impl CPU {
     //    setinterrupt(); //set interrupt flag
     //    pc = (uint16_t)read6502(0xFFFE) | ((uint16_t)read6502(0xFFFF) << 8);
     //}
-        self.pc = self.pc + Wrapping(1);
-        let (pc, stat) = (self.pc, self.status);
+        self.pc += Wrapping(1);
+        let (pc, stat) = (self.pc.0, self.status.to_pushed(true));
         self.push16(mem, pc); // original: "push next instruction address onto stack"
-        self.push8(mem, stat | FLAG_BREAK); // original: "push CPU status to stack"
+        self.push8(mem, stat); // original: "push CPU status to stack"
         self.flagset(FLAG_INTERRUPT);
-        self.pc = mem.read(0xFFFE) as u16 | ((mem.read(0xFFFF) as u16) << 8);
+        if self.variant.is_cmos() {
+            self.flagclear(FLAG_DECIMAL);
+        }
+        // If an NMI comes in while BRK is still pushing things, the real chip "hijacks" the BRK:
+        // the pushed status still has the break flag set, but the NMI vector gets used, and the
+        // NMI is considered handled. (That takes Backplane::nmi() in cycle-accurate mode; nothing
+        // else can change the lines partway through an instruction.)
+        let vector = if self.nmi_pending {
+            self.nmi_pending = false;
+            0xFFFA
+        } else {
+            0xFFFE
+        };
+        self.pc = Wrapping(self.bus_read(mem, vector) as u16 | ((self.bus_read(mem, vector + 1) as u16) << 8));
     }
 
     //static void bvc() {
@@ -883,14 +1356,18 @@ impl CPU {
     //            else clockticks6502++;
     //    }
     //}
-        if (self.status & FLAG_OVERFLOW) == 0 {
-            self.oldpc = self.pc;
-            self.pc = self.pc + Wrapping(self.reladdr);
-            if (self.oldpc & 0xFF00) != (self.pc & 0xFF00) {
-                // original: "check if jump crossed a page boundary"
-                self.clockticks += 2;
-            } else {
-                self.clockticks += 1;
+        if (self.status.0 & FLAG_OVERFLOW) == 0 {
+            self.oldpc = self.pc.0;
+            self.pc += Wrapping(self.reladdr);
+            self.branch_taken = true;
+            // (In cycle-accurate mode run_one_op_bus() counts these as it does the dummy reads.)
+            if !self.bus_mode {
+                if (self.oldpc & 0xFF00) != (self.pc.0 & 0xFF00) {
+                    // original: "check if jump crossed a page boundary"
+                    self.clockticks += 2;
+                } else {
+                    self.clockticks += 1;
+                }
             }
         }
     }
@@ -904,14 +1381,18 @@ impl CPU {
     //            else clockticks6502++;
     //    }
     //}
-        if (self.status & FLAG_OVERFLOW) == FLAG_OVERFLOW {
-            self.oldpc = self.pc;
-            self.pc = self.pc + Wrapping(self.reladdr);
-            if (self.oldpc & 0xFF00) != (self.pc & 0xFF00) {
-                // original: "check if jump crossed a page boundary"
-                self.clockticks += 2;
-            } else {
-                self.clockticks += 1;
+        if (self.status.0 & FLAG_OVERFLOW) == FLAG_OVERFLOW {
+            self.oldpc = self.pc.0;
+            self.pc += Wrapping(self.reladdr);
+            self.branch_taken = true;
+            // (In cycle-accurate mode run_one_op_bus() counts these as it does the dummy reads.)
+            if !self.bus_mode {
+                if (self.oldpc & 0xFF00) != (self.pc.0 & 0xFF00) {
+                    // original: "check if jump crossed a page boundary"
+                    self.clockticks += 2;
+                } else {
+                    self.clockticks += 1;
+                }
             }
         }
     }
@@ -951,18 +1432,18 @@ impl CPU {
     //    value = getvalue();
         self.value = self.getvalue(mem);
     //    result = (uint16_t)a - value;
-        self.result = self.a.0 as u16 - self.value;
+        self.result = (self.a.0 as u16).wrapping_sub(self.value);
        
     //    if (a >= (uint8_t)(value & 0x00FF)) setcarry();
     //        else clearcarry();
-        if self.a >= (self.value & 0x00FF) as u8 {
+        if self.a.0 >= (self.value & 0x00FF) as u8 {
             self.flagset(FLAG_CARRY);
         } else {
             self.flagclear(FLAG_CARRY);
         }
     //    if (a == (uint8_t)(value & 0x00FF)) setzero();
     //        else clearzero();
-        if self.a == (self.value & 0x00FF) as u8 {
+        if self.a.0 == (self.value & 0x00FF) as u8 {
             self.flagset(FLAG_ZERO);
         } else {
             self.flagclear(FLAG_ZERO);
@@ -978,19 +1459,19 @@ impl CPU {
     //    value = getvalue();
         self.value = self.getvalue(mem);
     //    result = (uint16_t)x - value;
-        self.result = self.x.0 as u16 - self.value;
+        self.result = (self.x.0 as u16).wrapping_sub(self.value);
        
     //    if (x >= (uint8_t)(value & 0x00FF)) setcarry();
     //        else clearcarry();
     //    if (x == (uint8_t)(value & 0x00FF)) setzero();
     //        else clearzero();
-        if self.x >= (self.value & 0x00FF) as u8 {
+        if self.x.0 >= (self.value & 0x00FF) as u8 {
             self.flagset(FLAG_CARRY);
         } else {
             self.flagclear(FLAG_CARRY);
         }
 
-        if self.x == (self.value & 0x00FF) as u8 {
+        if self.x.0 == (self.value & 0x00FF) as u8 {
             self.flagset(FLAG_ZERO);
         } else {
             self.flagclear(FLAG_ZERO);
@@ -1007,19 +1488,19 @@ impl CPU {
     //    value = getvalue();
     //    result = (uint16_t)y - value;
         self.value = self.getvalue(mem);
-        self.result = self.y.0 as u16 - self.value;
+        self.result = (self.y.0 as u16).wrapping_sub(self.value);
        
     //    if (y >= (uint8_t)(value & 0x00FF)) setcarry();
     //        else clearcarry();
     //    if (y == (uint8_t)(value & 0x00FF)) setzero();
     //        else clearzero();
-        if self.y >= (self.value & 0x00FF) as u8 {
+        if self.y.0 >= (self.value & 0x00FF) as u8 {
             self.flagset(FLAG_CARRY);
         } else {
             self.flagclear(FLAG_CARRY);
         }
 
-        if self.y == (self.value & 0x00FF) as u8 {
+        if self.y.0 == (self.value & 0x00FF) as u8 {
             self.flagset(FLAG_ZERO);
         } else {
             self.flagclear(FLAG_ZERO);
@@ -1036,7 +1517,7 @@ impl CPU {
     //    value = getvalue();
     //    result = value - 1;
         self.value = self.getvalue(mem);
-        self.result = self.value - 1;
+        self.result = self.value.wrapping_sub(1);
        
     //    zerocalc(result);
     //    signcalc(result);
@@ -1142,7 +1623,7 @@ impl CPU {
     //static void jmp() {
     fn inst_jmp<T: Backplane>(&mut self, _mem: &mut T) {
     //    pc = ea;
-        self.pc = self.ea;
+        self.pc = Wrapping(self.ea);
     //}
     }
 
@@ -1150,9 +1631,9 @@ impl CPU {
     fn inst_jsr<T: Backplane>(&mut self, mem: &mut T) {
     //    push16(pc - 1);
     //    pc = ea;
-        let pc = self.pc - 1;
+        let pc = self.pc.0.wrapping_sub(1);
         self.push16(mem, pc);
-        self.pc = self.ea;
+        self.pc = Wrapping(self.ea);
     //}
     }
 
@@ -1163,7 +1644,7 @@ impl CPU {
     //    a = (uint8_t)(value & 0x00FF);
         self.penaltyop = 1;
         self.value = self.getvalue(mem);
-        self.a = (self.value & 0x00FF) as u8;
+        self.a = Wrapping((self.value & 0x00FF) as u8);
        
     //    zerocalc(a);
     //    signcalc(a);
@@ -1180,7 +1661,7 @@ impl CPU {
     //    x = (uint8_t)(value & 0x00FF);
         self.penaltyop = 1;
         self.value = self.getvalue(mem);
-        self.x = (self.value & 0x00FF) as u8;
+        self.x = Wrapping((self.value & 0x00FF) as u8);
        
     //    zerocalc(x);
     //    signcalc(x);
@@ -1197,7 +1678,7 @@ impl CPU {
     //    y = (uint8_t)(value & 0x00FF);
         self.penaltyop = 1;
         self.value = self.getvalue(mem);
-        self.y = (self.value & 0x00FF) as u8;
+        self.y = Wrapping((self.value & 0x00FF) as u8);
        
     //    zerocalc(y);
     //    signcalc(y);
@@ -1209,6 +1690,10 @@ impl CPU {
 
     //static void lsr() {
     fn inst_lsr<T: Backplane>(&mut self, mem: &mut T) {
+        // On the 65C02, shifts only take the extra abs,X cycle when a page is crossed.
+        if self.variant.is_cmos() {
+            self.penaltyop = 1;
+        }
     //    value = getvalue();
     //    result = value >> 1;
         self.value = self.getvalue(mem);
@@ -1277,7 +1762,7 @@ impl CPU {
     //static void pha() {
     fn inst_pha<T: Backplane>(&mut self, mem: &mut T) {
     //    push8(a);
-        let a = self.a;
+        let a = self.a.0;
         self.push8(mem, a);
     //}
     }
@@ -1285,7 +1770,7 @@ impl CPU {
     //static void php() {
     fn inst_php<T: Backplane>(&mut self, mem: &mut T) {
     //    push8(status | FLAG_BREAK);
-        let s = self.status | FLAG_BREAK;
+        let s = self.status.to_pushed(true);
         self.push8(mem, s);
     //}
     }
@@ -1293,7 +1778,7 @@ impl CPU {
     //static void pla() {
     fn inst_pla<T: Backplane>(&mut self, mem: &mut T) {
     //    a = pull8();
-        self.a = self.pull8(mem);
+        self.a = Wrapping(self.pull8(mem));
        
     //    zerocalc(a);
     //    signcalc(a);
@@ -1306,16 +1791,20 @@ impl CPU {
     //static void plp() {
     fn inst_plp<T: Backplane>(&mut self, mem: &mut T) {
     //    status = pull8() | FLAG_CONSTANT;
-        self.status = self.pull8(mem) | FLAG_CONSTANT;
+        self.status = Status::from_pulled(self.pull8(mem));
     //}
     }
 
     //static void rol() {
     fn inst_rol<T: Backplane>(&mut self, mem: &mut T) {
+        // On the 65C02, shifts only take the extra abs,X cycle when a page is crossed.
+        if self.variant.is_cmos() {
+            self.penaltyop = 1;
+        }
     //    value = getvalue();
     //    result = (value << 1) | (status & FLAG_CARRY);
         self.value = self.getvalue(mem);
-        self.result = (self.value << 1) | (self.status & FLAG_CARRY) as u16;
+        self.result = (self.value << 1) | (self.status.0 & FLAG_CARRY) as u16;
        
     //    carrycalc(result);
     //    zerocalc(result);
@@ -1332,9 +1821,14 @@ impl CPU {
 
     //static void ror() {
     fn inst_ror<T: Backplane>(&mut self, mem: &mut T) {
+        // On the 65C02, shifts only take the extra abs,X cycle when a page is crossed.
+        if self.variant.is_cmos() {
+            self.penaltyop = 1;
+        }
     //    value = getvalue();
     //    result = (value >> 1) | ((status & FLAG_CARRY) << 7);
         self.value = self.getvalue(mem);
+        self.result = (self.value >> 1) | ((self.status.0 & FLAG_CARRY) as u16) << 7;
        
     //    if (value & 1) setcarry();
     //        else clearcarry();
@@ -1345,9 +1839,11 @@ impl CPU {
         } else {
             self.flagclear(FLAG_CARRY);
         }
+        let r = self.result;
+        self.flagcalc_zero(r);
+        self.flagcalc_sign(r);
        
     //    putvalue(result);
-        let r = self.result;
         self.putvalue(mem, r);
     //}
     }
@@ -1357,9 +1853,9 @@ impl CPU {
     //    status = pull8();
     //    value = pull16();
     //    pc = value;
-        self.status = self.pull8(mem);
+        self.status = Status::from_pulled(self.pull8(mem));
         self.value = self.pull16(mem);
-        self.pc = self.value;
+        self.pc = Wrapping(self.value);
     //}
     }
 
@@ -1368,7 +1864,7 @@ impl CPU {
     //    value = pull16();
     //    pc = value + 1;
         self.value = self.pull16(mem);
-        self.pc = self.value + 1;
+        self.pc = Wrapping(self.value.wrapping_add(1));
     //}
     }
 
@@ -1379,20 +1875,24 @@ impl CPU {
     //    result = (uint16_t)a + value + (uint16_t)(status & FLAG_CARRY);
         self.penaltyop = 1;
         self.value = self.getvalue(mem) ^ 0x00FF;
-        self.result = Wrapping(self.a.0 as u16) + self.value + Wrapping((self.status & FLAG_CARRY) as u16);
+        if self.variant.is_cmos() && self.variant.has_bcd() && self.status.0 & FLAG_DECIMAL != 0 {
+            return self.sbc_decimal_cmos();
+        }
+        let carry = (self.status.0 & FLAG_CARRY) as u16;
+        self.result = self.a.0 as u16 + self.value + carry;
        
     //    carrycalc(result);
     //    zerocalc(result);
     //    overflowcalc(result, a, value);
     //    signcalc(result);
-        let (r, a, v) = (self.result.0, self.a.0, self.value.0);
+        let (r, a, v) = (self.result, self.a.0, self.value);
         self.flagcalc_carry(r);
         self.flagcalc_zero(r);
         self.flagcalc_overflow(r, a, v);
         self.flagcalc_sign(r);
 
     //    #ifndef NES_CPU
-        if !NES_CPU {
+        if self.variant.has_bcd() {
     //    if (status & FLAG_DECIMAL) {
     //        clearcarry();
             
@@ -1406,21 +1906,9 @@ impl CPU {
     //        }
             
     //        clockticks6502++;
-            if (self.status & FLAG_DECIMAL) != 0 {
-                self.flagclear(FLAG_CARRY);
-
-                // TODO: This is definitely going to overflow sometimes, we should probably have
-                // figured out what to do earlier...
-                self.a -= Wrapping(0x66);
-                if (self.a.0 self.a & 0x0F) > 0x09 {
-                    self.a += Wrapping(0x06);
-                }
-                if (self.a.0 self.a & 0xF0) > 0x90 {
-                    self.a += Wrapping(0x60);
-                    self.flagset(FLAG_CARRY);
-                }
-
-                self.clockticks += 1;
+            // (Same problem as in inst_adc().)
+            if (self.status.0 & FLAG_DECIMAL) != 0 {
+                self.sbc_decimal_nmos(carry);
             }
     //    }
     //    #endif
@@ -1549,15 +2037,219 @@ impl CPU {
     //}
     }
 
+    // NMOS decimal mode. Z has already been set from the binary sum, and N and V come from the sum
+    // with only the low digit adjusted; C and the result are the properly adjusted BCD. 'c' is
+    // the carry going in, since the flag has been overwritten by now. The result goes in 'result'
+    // for inst_adc() to save.
+    fn adc_decimal_nmos(&mut self, c: u16) {
+        let a = self.a.0 as u16;
+        let v = self.value & 0x00FF;
+
+        let mut lo = (a & 0x0F) + (v & 0x0F) + c;
+        if lo > 0x09 {
+            lo += 0x06;
+        }
+        let mut hi = (a >> 4) + (v >> 4) + if lo > 0x0F { 1 } else { 0 };
+        let r = hi << 4;
+        self.flagcalc_sign(r);
+        self.flagcalc_overflow(r, a as u8, v);
+        if hi > 0x09 {
+            hi += 0x06;
+        }
+        self.flagcalc_carry(hi << 4);
+        self.result = (hi << 4) | (lo & 0x0F);
+    }
+
+    // NMOS SBC only adjusts the result; the flags all come from the binary subtraction. 'value'
+    // is still inverted, the way inst_sbc() left it.
+    fn sbc_decimal_nmos(&mut self, carry: u16) {
+        let a = self.a.0 as i16;
+        let v = (self.value ^ 0x00FF) as i16;
+        let borrow = 1 - carry as i16;
+
+        let mut lo = (a & 0x0F) - (v & 0x0F) - borrow;
+        let mut hi = (a >> 4) - (v >> 4);
+        if lo < 0 {
+            lo -= 0x06;
+            hi -= 1;
+        }
+        if hi < 0 {
+            hi -= 0x06;
+        }
+        self.result = (((hi << 4) | (lo & 0x0F)) & 0x00FF) as u16;
+    }
+
+    // 65C02 decimal mode. Unlike the NMOS chips, the 65C02 sets N and Z from the decimal result
+    // (and takes an extra cycle to do it). V comes out the same as on NMOS.
+    fn adc_decimal_cmos(&mut self) {
+        let a = self.a.0 as u16;
+        let v = self.value & 0x00FF;
+        let c = (self.status.0 & FLAG_CARRY) as u16;
+
+        let mut lo = (a & 0x0F) + (v & 0x0F) + c;
+        if lo > 0x09 {
+            lo = ((lo + 0x06) & 0x0F) + 0x10;
+        }
+        let mut r = (a & 0xF0) + (v & 0xF0) + lo;
+        let a8 = self.a.0;
+        self.flagcalc_overflow(r, a8, v);
+        if r > 0x9F {
+            r += 0x60;
+        }
+
+        self.flagcalc_carry(r);
+        self.flagcalc_zero(r);
+        self.flagcalc_sign(r);
+        self.a = Wrapping((r & 0x00FF) as u8);
+        self.clockticks += 1;
+    }
+
+    fn sbc_decimal_cmos(&mut self) {
+        // inst_sbc() has already inverted the operand for the binary case; undo that.
+        let a = self.a.0 as i16;
+        let v = (self.value ^ 0x00FF) as i16;
+        let borrow = 1 - (self.status.0 & FLAG_CARRY) as i16;
+
+        let bin = a - v - borrow;
+        let lo = (a & 0x0F) - (v & 0x0F) - borrow;
+        let mut r = bin;
+        if r < 0 {
+            r -= 0x60;
+        }
+        if lo < 0 {
+            r -= 0x06;
+        }
+
+        if bin >= 0 {
+            self.flagset(FLAG_CARRY);
+        } else {
+            self.flagclear(FLAG_CARRY);
+        }
+        if (a ^ v) & (a ^ bin) & 0x80 != 0 {
+            self.flagset(FLAG_OVERFLOW);
+        } else {
+            self.flagclear(FLAG_OVERFLOW);
+        }
+        let r = (r & 0x00FF) as u16;
+        self.flagcalc_zero(r);
+        self.flagcalc_sign(r);
+        self.a = Wrapping(r as u8);
+        self.clockticks += 1;
+    }
+
+    //65C02 instructions (none of these exist in the C version)
+
+    // The NMOS branches above each spell this out; the 65C02 ones share it.
+    fn take_branch(&mut self) {
+        self.oldpc = self.pc.0;
+        self.pc += Wrapping(self.reladdr);
+        self.branch_taken = true;
+        if !self.bus_mode {
+            if (self.oldpc & 0xFF00) != (self.pc.0 & 0xFF00) {
+                self.clockticks += 2;
+            } else {
+                self.clockticks += 1;
+            }
+        }
+    }
+
+    fn inst_bra<T: Backplane>(&mut self, _mem: &mut T) {
+        self.take_branch();
+    }
+
+    // For RMB, SMB, BBR and BBS, which bit to use is in bits 4-6 of the opcode.
+    fn inst_bbr<T: Backplane>(&mut self, mem: &mut T) {
+        let bit = (self.opcode >> 4) & 0x07;
+        if mem.read(self.ea) & (1 << bit) == 0 {
+            self.take_branch();
+        }
+    }
+
+    fn inst_bbs<T: Backplane>(&mut self, mem: &mut T) {
+        let bit = (self.opcode >> 4) & 0x07;
+        if mem.read(self.ea) & (1 << bit) != 0 {
+            self.take_branch();
+        }
+    }
+
+    fn inst_rmb<T: Backplane>(&mut self, mem: &mut T) {
+        let bit = (self.opcode >> 4) & 0x07;
+        let v = mem.read(self.ea) & !(1 << bit);
+        mem.write(self.ea, v);
+    }
+
+    fn inst_smb<T: Backplane>(&mut self, mem: &mut T) {
+        let bit = (self.opcode >> 4) & 0x07;
+        let v = mem.read(self.ea) | (1 << bit);
+        mem.write(self.ea, v);
+    }
+
+    fn inst_phx<T: Backplane>(&mut self, mem: &mut T) {
+        let x = self.x.0;
+        self.push8(mem, x);
+    }
+
+    fn inst_phy<T: Backplane>(&mut self, mem: &mut T) {
+        let y = self.y.0;
+        self.push8(mem, y);
+    }
+
+    fn inst_plx<T: Backplane>(&mut self, mem: &mut T) {
+        self.x = Wrapping(self.pull8(mem));
+        let x = self.x.0 as u16;
+        self.flagcalc_zero(x);
+        self.flagcalc_sign(x);
+    }
+
+    fn inst_ply<T: Backplane>(&mut self, mem: &mut T) {
+        self.y = Wrapping(self.pull8(mem));
+        let y = self.y.0 as u16;
+        self.flagcalc_zero(y);
+        self.flagcalc_sign(y);
+    }
+
+    fn inst_stz<T: Backplane>(&mut self, mem: &mut T) {
+        self.putvalue(mem, 0);
+    }
+
+    // TSB and TRB set Z the way BIT does, from A AND memory, then set or clear the bits of A in
+    // memory.
+    fn inst_tsb<T: Backplane>(&mut self, mem: &mut T) {
+        let v = mem.read(self.ea);
+        self.flagcalc_zero((v & self.a.0) as u16);
+        mem.write(self.ea, v | self.a.0);
+    }
+
+    fn inst_trb<T: Backplane>(&mut self, mem: &mut T) {
+        let v = mem.read(self.ea);
+        self.flagcalc_zero((v & self.a.0) as u16);
+        mem.write(self.ea, v & !self.a.0);
+    }
+
+    fn inst_wai<T: Backplane>(&mut self, _mem: &mut T) {
+        self.waiting = true;
+    }
+
+    fn inst_stp<T: Backplane>(&mut self, _mem: &mut T) {
+        let pc = self.pc.0.wrapping_sub(1);
+        self.jam = Some(JamReason::Stopped { pc });
+    }
+
     //undocumented instructions
-    // TODO: Provide actual implementations.
+    //
+    // These only do anything on CPU variants that have the undocumented opcodes; otherwise they're
+    // NOPs, as they were in the C version with UNDOCUMENTED left undefined.
     //#ifdef UNDOCUMENTED
     //    static void lax() {
     //        lda();
     //        ldx();
     //    }
     fn inst_lax<T: Backplane>(&mut self, mem: &mut T) {
-        self.inst_nop(mem);
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        self.inst_lda(mem);
+        self.inst_ldx(mem);
     }
 
     //    static void sax() {
@@ -1567,7 +2259,15 @@ impl CPU {
     //        if (penaltyop && penaltyaddr) clockticks6502--;
     //    }
     fn inst_sax<T: Backplane>(&mut self, mem: &mut T) {
-        self.inst_nop(mem);
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        // (The C version did the STA and STX writes too, but only the last one sticks.)
+        let ax = (self.a.0 & self.x.0) as u16;
+        self.putvalue(mem, ax);
+        if self.penaltyop != 0 && self.penaltyaddr != 0 {
+            self.clockticks -= 1;
+        }
     }
 
     //    static void dcp() {
@@ -1576,7 +2276,14 @@ impl CPU {
     //        if (penaltyop && penaltyaddr) clockticks6502--;
     //    }
     fn inst_dcp<T: Backplane>(&mut self, mem: &mut T) {
-        self.inst_nop(mem);
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        self.inst_dec(mem);
+        self.inst_cmp(mem);
+        if self.penaltyop != 0 && self.penaltyaddr != 0 {
+            self.clockticks -= 1;
+        }
     }
 
     //    static void isb() {
@@ -1585,7 +2292,14 @@ impl CPU {
     //        if (penaltyop && penaltyaddr) clockticks6502--;
     //    }
     fn inst_isb<T: Backplane>(&mut self, mem: &mut T) {
-        self.inst_nop(mem);
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        self.inst_inc(mem);
+        self.inst_sbc(mem);
+        if self.penaltyop != 0 && self.penaltyaddr != 0 {
+            self.clockticks -= 1;
+        }
     }
 
     //    static void slo() {
@@ -1594,7 +2308,14 @@ impl CPU {
     //        if (penaltyop && penaltyaddr) clockticks6502--;
     //    }
     fn inst_slo<T: Backplane>(&mut self, mem: &mut T) {
-        self.inst_nop(mem);
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        self.inst_asl(mem);
+        self.inst_ora(mem);
+        if self.penaltyop != 0 && self.penaltyaddr != 0 {
+            self.clockticks -= 1;
+        }
     }
 
     //    static void rla() {
@@ -1603,7 +2324,14 @@ impl CPU {
     //        if (penaltyop && penaltyaddr) clockticks6502--;
     //    }
     fn inst_rla<T: Backplane>(&mut self, mem: &mut T) {
-        self.inst_nop(mem);
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        self.inst_rol(mem);
+        self.inst_and(mem);
+        if self.penaltyop != 0 && self.penaltyaddr != 0 {
+            self.clockticks -= 1;
+        }
     }
 
     //    static void sre() {
@@ -1612,7 +2340,14 @@ impl CPU {
     //        if (penaltyop && penaltyaddr) clockticks6502--;
     //    }
     fn inst_sre<T: Backplane>(&mut self, mem: &mut T) {
-        self.inst_nop(mem);
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        self.inst_lsr(mem);
+        self.inst_eor(mem);
+        if self.penaltyop != 0 && self.penaltyaddr != 0 {
+            self.clockticks -= 1;
+        }
     }
 
     //    static void rra() {
@@ -1620,28 +2355,506 @@ impl CPU {
     //        adc();
     //        if (penaltyop && penaltyaddr) clockticks6502--;
     //    }
-     fn inst_rra<T: Backplane>(&mut self, mem: &mut T) {
-        self.inst_nop(mem);
+    fn inst_rra<T: Backplane>(&mut self, mem: &mut T) {
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        self.inst_ror(mem);
+        self.inst_adc(mem);
+        if self.penaltyop != 0 && self.penaltyaddr != 0 {
+            self.clockticks -= 1;
+        }
     }
 
-    //#else
-    //    #define lax nop
-    //    #define sax nop
-    //    #define dcp nop
-    //    #define isb nop
-    //    #define slo nop
-    //    #define rla nop
-    //    #define sre nop
-    //    #define rra nop
+    // The rest of the undocumented opcodes weren't in the C version at all. Descriptions of what
+    // they do are from "No More Secrets" (the NMOS 6510 unintended opcodes document).
+
+    // ANC: AND, then copy N into C.
+    fn inst_anc<T: Backplane>(&mut self, mem: &mut T) {
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        self.inst_and(mem);
+        if self.status.0 & FLAG_SIGN != 0 {
+            self.flagset(FLAG_CARRY);
+        } else {
+            self.flagclear(FLAG_CARRY);
+        }
+    }
+
+    // ALR (a.k.a. ASR): AND, then LSR A.
+    fn inst_alr<T: Backplane>(&mut self, mem: &mut T) {
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        let t = self.a.0 & self.getvalue(mem) as u8;
+        if t & 0x01 != 0 {
+            self.flagset(FLAG_CARRY);
+        } else {
+            self.flagclear(FLAG_CARRY);
+        }
+        self.a = Wrapping(t >> 1);
+        let a = self.a.0 as u16;
+        self.flagcalc_zero(a);
+        self.flagcalc_sign(a);
+    }
+
+    // ARR: AND, then ROR A, except that C and V come out of the adder in a strange way, and in
+    // decimal mode there's a BCD fixup on top of that.
+    fn inst_arr<T: Backplane>(&mut self, mem: &mut T) {
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        let t = self.a.0 & self.getvalue(mem) as u8;
+        let carry_in = self.status.0 & FLAG_CARRY;
+        let mut r = (t >> 1) | (carry_in << 7);
+
+        if self.variant.has_bcd() && self.status.0 & FLAG_DECIMAL != 0 {
+            // N is the old carry, Z is from the unfixed result, V from bit 6 changing.
+            if carry_in != 0 {
+                self.flagset(FLAG_SIGN);
+            } else {
+                self.flagclear(FLAG_SIGN);
+            }
+            self.flagcalc_zero(r as u16);
+            if (t ^ r) & 0x40 != 0 {
+                self.flagset(FLAG_OVERFLOW);
+            } else {
+                self.flagclear(FLAG_OVERFLOW);
+            }
+            if (t & 0x0F) + (t & 0x01) > 0x05 {
+                r = (r & 0xF0) | (r.wrapping_add(0x06) & 0x0F);
+            }
+            if (t as u16 & 0xF0) + (t as u16 & 0x10) > 0x50 {
+                r = r.wrapping_add(0x60);
+                self.flagset(FLAG_CARRY);
+            } else {
+                self.flagclear(FLAG_CARRY);
+            }
+        } else {
+            self.flagcalc_zero(r as u16);
+            self.flagcalc_sign(r as u16);
+            if r & 0x40 != 0 {
+                self.flagset(FLAG_CARRY);
+            } else {
+                self.flagclear(FLAG_CARRY);
+            }
+            if ((r >> 6) ^ (r >> 5)) & 0x01 != 0 {
+                self.flagset(FLAG_OVERFLOW);
+            } else {
+                self.flagclear(FLAG_OVERFLOW);
+            }
+        }
+        self.a = Wrapping(r);
+    }
+
+    // SBX (a.k.a. AXS): X = (A AND X) - operand, setting flags like CMP does (no borrow in, and
+    // decimal mode doesn't apply).
+    fn inst_sbx<T: Backplane>(&mut self, mem: &mut T) {
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        let t = self.a.0 & self.x.0;
+        let v = self.getvalue(mem) as u8;
+        if t >= v {
+            self.flagset(FLAG_CARRY);
+        } else {
+            self.flagclear(FLAG_CARRY);
+        }
+        self.x = Wrapping(t.wrapping_sub(v));
+        let x = self.x.0 as u16;
+        self.flagcalc_zero(x);
+        self.flagcalc_sign(x);
+    }
+
+    // ANE (a.k.a. XAA): A = (A OR magic) AND X AND operand. Unstable on real hardware; see
+    // ane_magic.
+    fn inst_ane<T: Backplane>(&mut self, mem: &mut T) {
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        let v = self.getvalue(mem) as u8;
+        self.a = Wrapping((self.a.0 | self.ane_magic) & self.x.0 & v);
+        let a = self.a.0 as u16;
+        self.flagcalc_zero(a);
+        self.flagcalc_sign(a);
+    }
+
+    // LXA (a.k.a. LAX #imm): A = X = (A OR magic) AND operand. Unstable too; see lxa_magic.
+    fn inst_lxa<T: Backplane>(&mut self, mem: &mut T) {
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        let v = self.getvalue(mem) as u8;
+        self.a = Wrapping((self.a.0 | self.lxa_magic) & v);
+        self.x = self.a;
+        let a = self.a.0 as u16;
+        self.flagcalc_zero(a);
+        self.flagcalc_sign(a);
+    }
+
+    // LAS (a.k.a. LAR): A = X = SP = memory AND SP.
+    fn inst_las<T: Backplane>(&mut self, mem: &mut T) {
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        self.penaltyop = 1;
+        let v = self.getvalue(mem) as u8;
+        self.sp = Wrapping(v & self.sp.0);
+        self.a = self.sp;
+        self.x = self.sp;
+        let a = self.a.0 as u16;
+        self.flagcalc_zero(a);
+        self.flagcalc_sign(a);
+    }
+
+    // SHA, SHX, SHY and TAS are the "unstable" stores. They store a register ANDed with the high
+    // byte of the base address plus one, and if indexing crossed a page, the value stored also
+    // replaces the high byte of the address it's stored to.
+    fn store_and_high<T: Backplane>(&mut self, mem: &mut T, value: u8, index: u8) {
+        let base = self.ea.wrapping_sub(index as u16);
+        let v = value & ((base >> 8) as u8).wrapping_add(1);
+        if (base & 0xFF00) != (self.ea & 0xFF00) {
+            self.ea = ((v as u16) << 8) | (self.ea & 0x00FF);
+        }
+        self.putvalue(mem, v as u16);
+    }
+
+    // SHA (a.k.a. AHX, AXA): stores A AND X AND (H+1).
+    fn inst_sha<T: Backplane>(&mut self, mem: &mut T) {
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        let (v, y) = (self.a.0 & self.x.0, self.y.0);
+        self.store_and_high(mem, v, y);
+    }
+
+    // SHX (a.k.a. SXA, XAS): stores X AND (H+1).
+    fn inst_shx<T: Backplane>(&mut self, mem: &mut T) {
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        let (v, y) = (self.x.0, self.y.0);
+        self.store_and_high(mem, v, y);
+    }
+
+    // SHY (a.k.a. SYA, SAY): stores Y AND (H+1).
+    fn inst_shy<T: Backplane>(&mut self, mem: &mut T) {
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        let (v, x) = (self.y.0, self.x.0);
+        self.store_and_high(mem, v, x);
+    }
+
+    // TAS (a.k.a. XAS, SHS): SP = A AND X, then stores SP AND (H+1).
+    fn inst_tas<T: Backplane>(&mut self, mem: &mut T) {
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        self.sp = Wrapping(self.a.0 & self.x.0);
+        let (v, y) = (self.sp.0, self.y.0);
+        self.store_and_high(mem, v, y);
+    }
+
+    // USBC: $EB, which is just SBC #imm again.
+    fn inst_usbc<T: Backplane>(&mut self, mem: &mut T) {
+        if !self.variant.has_undocumented() {
+            return self.inst_nop(mem);
+        }
+        self.inst_sbc(mem);
+    }
+
+    // JAM (a.k.a. KIL, HLT): the CPU locks up until it's reset. This happens whether or not the
+    // variant "has" the undocumented opcodes, since a real NMOS chip has no choice in the matter.
+    fn inst_jam<T: Backplane>(&mut self, _mem: &mut T) {
+        let pc = self.pc.0.wrapping_sub(1);
+        self.jam = Some(JamReason::Jam { opcode: self.opcode, pc });
+    }
+
+    //#else
+    //    #define lax nop
+    //    #define sax nop
+    //    #define dcp nop
+    //    #define isb nop
+    //    #define slo nop
+    //    #define rla nop
+    //    #define sre nop
+    //    #define rra nop
     //#endif
 
 
+    // Cycle-accurate mode. Everything the core reads or writes goes through these two, so that in
+    // cycle-accurate mode each access counts as one bus cycle.
+    fn bus_read<T: Backplane>(&mut self, mem: &mut T, address: u16) -> u8 {
+        if self.bus_mode {
+            // RDY stops the CPU on read cycles only; writes go ahead regardless.
+            while !mem.ready() {
+                self.clockticks += 1;
+                self.rdy_stalled_cycles += 1;
+                self.bus_tick(mem, 1);
+            }
+        }
+        let value = mem.read(address);
+        if self.bus_mode {
+            self.bus_cycles += 1;
+            self.bus_tick(mem, 1);
+        }
+        value
+    }
+
+    fn bus_write<T: Backplane>(&mut self, mem: &mut T, address: u16, value: u8) {
+        mem.write(address, value);
+        if self.bus_mode {
+            self.bus_cycles += 1;
+            self.bus_tick(mem, 1);
+        }
+    }
+
+    // Backplane::tick(), and then a look at whether anything on the bus pulled /NMI meanwhile.
+    fn bus_tick<T: Backplane>(&mut self, mem: &mut T, cycles: u32) {
+        mem.tick(cycles);
+        if mem.nmi() {
+            self.nmi_pending = true;
+        }
+    }
+
+    // Runs one (NMOS) instruction doing exactly one bus access per cycle, in the order the real
+    // chip does them. The addressing is done here, the instruction itself by the usual inst_*
+    // functions (through run_one_op(), with the addr_* functions switched off), working on
+    // 'latch' instead of memory; then any writes happen afterwards. The cycle count is simply the
+    // number of accesses, so nothing from the ticks table or the penalty flags is used.
+    fn run_one_op_bus<T: Backplane>(&mut self, mem: &mut T) {
+        self.bus_cycles = 0;
+        // The addressing here never sets penaltyaddr, but the undocumented instructions still look
+        // at it (to take back the cycle the C version's STA/STX would have added).
+        self.penaltyop = 0;
+        self.penaltyaddr = 0;
+        let pc = self.pc.0;
+        self.opcode = self.bus_read(mem, pc);
+        self.pc += Wrapping(1);
+        self.flagset(FLAG_CONSTANT);
+        self.addr_acc = false;
+        self.branch_taken = false;
+
+        let old_i = self.status.0 & FLAG_INTERRUPT != 0;
+        if self.opcode == 0x20 {
+            // JSR reads the high byte of its operand *after* pushing the return address, so it
+            // can't go through the usual addressing.
+            self.bus_jsr(mem);
+        } else {
+            let info = NMOS_OPCODES[self.opcode as usize];
+            let access = bus_access(self.opcode, info.mode);
+            self.bus_addressing(mem, info.mode, access);
+
+            let orig = self.latch;
+            self.run_one_op(mem);
+
+            match access {
+                BusAccess::Write => {
+                    let (ea, v) = (self.ea, self.latch);
+                    self.bus_write(mem, ea, v);
+                }
+                BusAccess::ReadModifyWrite => {
+                    // The NMOS chips write the unmodified value back before the real one.
+                    let (ea, v) = (self.ea, self.latch);
+                    self.bus_write(mem, ea, orig);
+                    self.bus_write(mem, ea, v);
+                }
+                _ => {}
+            }
+
+            if self.branch_taken {
+                // One dummy read of the next opcode while the offset is added to PCL, and
+                // another (from the wrong page) if PCH then needs fixing.
+                let (from, to) = (self.oldpc, self.pc.0);
+                self.bus_read(mem, from);
+                if (from & 0xFF00) != (to & 0xFF00) {
+                    self.bus_read(mem, (from & 0xFF00) | (to & 0x00FF));
+                }
+            }
+            if self.opcode == 0x60 {
+                // RTS reads the pulled address before incrementing it.
+                let pc = self.pc.0.wrapping_sub(1);
+                self.bus_read(mem, pc);
+            }
+        }
+
+        match self.opcode {
+            // CLI, SEI, PLP
+            0x58 | 0x78 | 0x28 => { self.delayed_i = Some(old_i); }
+            _ => {}
+        };
+        self.clockticks += u64::from(self.bus_cycles);
+        self.bus_mode = false;
+    }
+
+    fn bus_jsr<T: Backplane>(&mut self, mem: &mut T) {
+        self.mode = AddrMode::Absolute;
+        let pc = self.pc.0;
+        let lo = self.bus_read(mem, pc) as u16;
+        self.pc += Wrapping(1);
+        // Dummy read of the stack while the CPU stashes the low byte internally.
+        let sp = BASE_STACK + self.sp.0 as u16;
+        self.bus_read(mem, sp);
+        let ret = self.pc.0;
+        self.push16(mem, ret);
+        let hi = self.bus_read(mem, ret) as u16;
+        self.ea = lo | (hi << 8);
+        self.pc = Wrapping(self.ea);
+    }
+
+    // The cycle-accurate counterpart of the addr_* functions.
+    fn bus_addressing<T: Backplane>(&mut self, mem: &mut T, mode: AddrMode, access: BusAccess) {
+        self.mode = mode;
+        match mode {
+            AddrMode::Implied | AddrMode::Accumulator => {
+                self.addr_acc = mode == AddrMode::Accumulator;
+                // Every one-byte instruction reads the byte after the opcode and ignores it.
+                let pc = self.pc.0;
+                self.bus_read(mem, pc);
+                match self.opcode {
+                    // PLP, PLA, RTI and RTS also read the stack before they increment SP.
+                    0x28 | 0x68 | 0x40 | 0x60 => {
+                        let sp = BASE_STACK + self.sp.0 as u16;
+                        self.bus_read(mem, sp);
+                    }
+                    _ => {}
+                };
+            }
+            AddrMode::Immediate => {
+                self.ea = self.pc.0;
+                self.pc += Wrapping(1);
+            }
+            AddrMode::ZeroPage => {
+                let pc = self.pc.0;
+                self.ea = self.bus_read(mem, pc) as u16;
+                self.pc += Wrapping(1);
+            }
+            AddrMode::ZeroPageX | AddrMode::ZeroPageY => {
+                let index = if mode == AddrMode::ZeroPageX { self.x.0 } else { self.y.0 };
+                let pc = self.pc.0;
+                let base = self.bus_read(mem, pc);
+                self.pc += Wrapping(1);
+                // Reads the unindexed address while adding.
+                self.bus_read(mem, base as u16);
+                self.ea = base.wrapping_add(index) as u16;
+            }
+            AddrMode::Absolute | AddrMode::Indirect => {
+                let pc = self.pc.0;
+                let lo = self.bus_read(mem, pc) as u16;
+                let hi = self.bus_read(mem, pc.wrapping_add(1)) as u16;
+                self.pc += Wrapping(2);
+                self.ea = lo | (hi << 8);
+                if mode == AddrMode::Indirect {
+                    // JMP (ind), page wraparound bug and all.
+                    let ptr = self.ea;
+                    let lo = self.bus_read(mem, ptr) as u16;
+                    let hi = self.bus_read(mem, (ptr & 0xFF00) | (ptr.wrapping_add(1) & 0x00FF)) as u16;
+                    self.ea = lo | (hi << 8);
+                }
+            }
+            AddrMode::AbsoluteX | AddrMode::AbsoluteY => {
+                let index = if mode == AddrMode::AbsoluteX { self.x.0 } else { self.y.0 };
+                let pc = self.pc.0;
+                let lo = self.bus_read(mem, pc) as u16;
+                let hi = self.bus_read(mem, pc.wrapping_add(1)) as u16;
+                self.pc += Wrapping(2);
+                self.bus_indexed(mem, lo | (hi << 8), index, access);
+            }
+            AddrMode::IndirectX => {
+                let pc = self.pc.0;
+                let ptr = self.bus_read(mem, pc);
+                self.pc += Wrapping(1);
+                self.bus_read(mem, ptr as u16);
+                let ptr = ptr.wrapping_add(self.x.0);
+                let lo = self.bus_read(mem, ptr as u16) as u16;
+                let hi = self.bus_read(mem, ptr.wrapping_add(1) as u16) as u16;
+                self.ea = lo | (hi << 8);
+            }
+            AddrMode::IndirectY => {
+                let pc = self.pc.0;
+                let ptr = self.bus_read(mem, pc);
+                self.pc += Wrapping(1);
+                let lo = self.bus_read(mem, ptr as u16) as u16;
+                let hi = self.bus_read(mem, ptr.wrapping_add(1) as u16) as u16;
+                let index = self.y.0;
+                self.bus_indexed(mem, lo | (hi << 8), index, access);
+            }
+            AddrMode::Relative => {
+                let pc = self.pc.0;
+                self.reladdr = self.bus_read(mem, pc) as u16;
+                if self.reladdr & 0x0080 != 0 {
+                    self.reladdr |= 0xFF00;
+                }
+                self.pc += Wrapping(1);
+            }
+            // Only the NMOS chips are run this way so far, so these never come up, but there's no
+            // reason to fall over if they do: the accesses are there, if not the dummy cycles.
+            AddrMode::ZeroPageIndirect => {
+                let pc = self.pc.0;
+                let ptr = self.bus_read(mem, pc);
+                self.pc += Wrapping(1);
+                let lo = self.bus_read(mem, ptr as u16) as u16;
+                let hi = self.bus_read(mem, ptr.wrapping_add(1) as u16) as u16;
+                self.ea = lo | (hi << 8);
+            }
+            AddrMode::AbsoluteXIndirect => {
+                let pc = self.pc.0;
+                let lo = self.bus_read(mem, pc) as u16;
+                let hi = self.bus_read(mem, pc.wrapping_add(1)) as u16;
+                self.pc += Wrapping(2);
+                let ptr = (lo | (hi << 8)).wrapping_add(self.x.0 as u16);
+                let lo = self.bus_read(mem, ptr) as u16;
+                let hi = self.bus_read(mem, ptr.wrapping_add(1)) as u16;
+                self.ea = lo | (hi << 8);
+            }
+            AddrMode::ZeroPageRelative => {
+                let pc = self.pc.0;
+                self.ea = self.bus_read(mem, pc) as u16;
+                self.reladdr = self.bus_read(mem, pc.wrapping_add(1)) as u16;
+                if self.reladdr & 0x0080 != 0 {
+                    self.reladdr |= 0xFF00;
+                }
+                self.pc += Wrapping(2);
+            }
+        }
+
+        match access {
+            BusAccess::Read | BusAccess::ReadModifyWrite => {
+                let ea = self.ea;
+                self.latch = self.bus_read(mem, ea);
+            }
+            _ => {}
+        }
+    }
+
+    // abs,X / abs,Y / (zp),Y: the CPU first reads from the address with only the low byte fixed
+    // up. Reads can stop there if no page was crossed; otherwise (and always, for writes and
+    // read-modify-writes) that read was a dummy and the real access comes after.
+    fn bus_indexed<T: Backplane>(&mut self, mem: &mut T, base: u16, index: u8, access: BusAccess) {
+        self.ea = base.wrapping_add(index as u16);
+        let crossed = (base & 0xFF00) != (self.ea & 0xFF00);
+        if crossed || access != BusAccess::Read {
+            let wrong = (base & 0xFF00) | (self.ea & 0x00FF);
+            self.bus_read(mem, wrong);
+        }
+    }
+
     //void nmi6502() {
     //    push16(pc);
     //    push8(status);
     //    status |= FLAG_INTERRUPT;
     //    pc = (uint16_t)read6502(0xFFFA) | ((uint16_t)read6502(0xFFFB) << 8);
     //}
+    //
+    // Like the lines, this does nothing to a jammed CPU.
+    pub fn nmi<T: Backplane>(&mut self, mem: &mut T) {
+        if self.jam.is_some() {
+            return;
+        }
+        self.interrupt(mem, 0xFFFA);
+    }
 
     //void irq6502() {
     //    push16(pc);
@@ -1649,54 +2862,364 @@ impl CPU {
     //    status |= FLAG_INTERRUPT;
     //    pc = (uint16_t)read6502(0xFFFE) | ((uint16_t)read6502(0xFFFF) << 8);
     //}
+    //
+    // The C version would take the IRQ even with the interrupt flag set, which isn't how the chip
+    // behaves; here it's simply ignored in that case, or if the CPU is jammed. Returns whether the
+    // IRQ was taken.
+    pub fn irq<T: Backplane>(&mut self, mem: &mut T) -> bool {
+        if self.status.0 & FLAG_INTERRUPT != 0 || self.jam.is_some() {
+            return false;
+        }
+        self.interrupt(mem, 0xFFFE);
+        true
+    }
+
+    // Shared by nmi() and irq(). Hardware interrupts push the status register with the break flag
+    // *clear* (only BRK and PHP push it set), which is how an IRQ handler tells the two apart.
+    fn interrupt<T: Backplane>(&mut self, mem: &mut T, vector: u16) {
+        // The real chip spends the first two cycles of the sequence reading (and ignoring) the
+        // opcode it was about to run.
+        if self.bus_mode {
+            let pc = self.pc.0;
+            self.bus_read(mem, pc);
+            self.bus_read(mem, pc);
+        }
+        let pc = self.pc.0;
+        self.push16(mem, pc);
+        let stat = self.status.to_pushed(false);
+        self.push8(mem, stat);
+        self.flagset(FLAG_INTERRUPT);
+        // (This is what gets a WAI going again when nmi() or irq() is called directly.)
+        self.waiting = false;
+        // The 65C02 clears decimal mode on the way into any interrupt handler.
+        if self.variant.is_cmos() {
+            self.flagclear(FLAG_DECIMAL);
+        }
+        self.pc = Wrapping(self.bus_read(mem, vector) as u16 | ((self.bus_read(mem, vector + 1) as u16) << 8));
+        self.clockticks += 7;
+    }
+
+    // The functions above deliver an interrupt right away, which is handy but isn't how real
+    // hardware does it. These model the actual lines instead, and exec() looks at them between
+    // instructions.
+    //
+    // /IRQ is level-triggered: a device holds it asserted until it's been acknowledged, and any
+    // number of devices can be holding it at once. So each device gets one or more bits of
+    // 'source' (which bits is up to the caller) and the line counts as asserted as long as any of
+    // them are set.
+    pub fn set_irq(&mut self, source: u32, asserted: bool) {
+        if asserted {
+            self.irq_lines |= source;
+        } else {
+            self.irq_lines &= !source;
+        }
+    }
+
+    pub fn irq_line(&self) -> bool {
+        self.irq_lines != 0
+    }
+
+    // /NMI is edge-triggered: only the transition to asserted is latched, and holding the line
+    // doesn't cause any more NMIs until it's been released and asserted again.
+    pub fn set_nmi(&mut self, asserted: bool) {
+        if asserted && !self.nmi_line {
+            self.nmi_pending = true;
+        }
+        self.nmi_line = asserted;
+    }
+
+    pub fn nmi_line(&self) -> bool {
+        self.nmi_line
+    }
+
+    // RDY: while it's low ('ready' false) the CPU stops on its next read cycle and stays there,
+    // which is how DMA gets the bus on the NES and how the C64's VIC-II steals cycles on bad
+    // lines. (Writes don't stop for it, but seeing as this is only ever looked at between
+    // instructions, the next cycle is always a read anyway: an opcode fetch or the start of an
+    // interrupt. For RDY dropping in the middle of an instruction, see Backplane::ready().)
+    // exec() still counts the stalled cycles against its budget and adds them up in
+    // rdy_stalled_cycles, but doesn't call the callback for them, since no instruction ran; use
+    // hold_rdy() or Backplane::ready() to let go of RDY again without leaving exec().
+    pub fn set_rdy(&mut self, ready: bool) {
+        self.rdy_line = ready;
+    }
+
+    // Convenience for DMA: holds RDY low for the next 'cycles' cycles and then lets it go, without
+    // having to count them off in the callback.
+    pub fn hold_rdy(&mut self, cycles: u32) {
+        self.rdy_hold += cycles;
+    }
+
+    pub fn rdy_line(&self) -> bool {
+        self.rdy_line && self.rdy_hold == 0
+    }
+
+    // SO ("set overflow"): pulling it low sets the overflow flag. Only the falling edge counts.
+    // The 1541 drive wires its byte-ready signal to this and waits in a BVC * loop.
+    pub fn set_so(&mut self, asserted: bool) {
+        if asserted && !self.so_line {
+            self.flagset(FLAG_OVERFLOW);
+        }
+        self.so_line = asserted;
+    }
+
+    // Whether the CPU is sitting in a WAI (65C02 only) until an interrupt line gets asserted.
+    pub fn is_waiting(&self) -> bool {
+        self.waiting
+    }
+
+    // Whether the CPU has locked up (see JamReason). exec() returns early when this happens, and
+    // only reset() will get it going again.
+    pub fn is_jammed(&self) -> bool {
+        self.jam.is_some()
+    }
+
+    pub fn jam_reason(&self) -> Option<JamReason> {
+        self.jam
+    }
+
+    // Called by exec() before each instruction. NMI wins over IRQ if both are pending.
+    fn poll_interrupts<T: Backplane>(&mut self, mem: &mut T) {
+        let masked = match self.delayed_i.take() {
+            Some(i) => i,
+            None => self.status.0 & FLAG_INTERRUPT != 0,
+        };
+
+        self.interrupted = true;
+        if self.nmi_pending {
+            self.nmi_pending = false;
+            self.interrupt(mem, 0xFFFA);
+        } else if self.irq_lines != 0 && !masked {
+            self.interrupt(mem, 0xFFFE);
+        } else {
+            self.interrupted = false;
+        }
+    }
 
     //uint8_t callexternal = 0;
     //void (*loopexternal)();
 
     //void exec6502(uint32_t tickcount) {
-    pub fn exec<T: Backplane>(&mut self, mem: &mut T, tickcount: u32) {
+    pub fn exec<T: Backplane>(&mut self, mem: &mut T, tickcount: u32) -> StopReason {
     //    clockgoal6502 += tickcount;
-        self.clockgoal += tickcount;
+        self.clockgoal = self.clockgoal.saturating_add(u64::from(tickcount));
+        self.run_to_goal(mem)
+    }
+
+    // Like exec(), but runs until clockticks reaches an absolute cycle count rather than for a
+    // number of cycles on top of the last goal. Handy for hosts that schedule everything against
+    // one master clock. A target that's already been passed runs nothing.
+    pub fn exec_until<T: Backplane>(&mut self, mem: &mut T, target: u64) -> StopReason {
+        self.clockgoal = target;
+        self.run_to_goal(mem)
+    }
+
+    // How many cycles the last exec() or exec_until() went past its goal, since the last
+    // instruction usually doesn't finish exactly on it. exec() takes these back out of the next
+    // budget; exec_until() callers can do the same by subtracting them from their next target.
+    // Zero if the goal wasn't reached because the callback or a jam stopped things early.
+    pub fn overshoot(&self) -> u64 {
+        self.clockticks.saturating_sub(self.clockgoal)
+    }
 
+    fn run_to_goal<T: Backplane>(&mut self, mem: &mut T) -> StopReason {
     //    while (clockticks6502 < clockgoal6502) {
         while self.clockticks < self.clockgoal {
+            // Once the CPU has jammed there's nothing more to do, so don't sit here burning the
+            // budget.
+            if self.jam.is_some() {
+                return StopReason::Jammed;
+            }
+            let (ran, stalled) = (self.instructions_ran, self.rdy_stalled_cycles);
+            let pc = if self.breakpoints.is_empty() {
+                self.run_instruction(mem)
+            } else {
+                match self.run_instruction_checked(mem) {
+                    Ok(pc) => pc,
+                    Err(reason) => return reason,
+                }
+            };
+            // (Sitting in WAI or stalled on RDY doesn't count; no instruction ran.)
+            if self.detect_traps && self.instructions_ran != ran && self.pc.0 == pc {
+                return StopReason::TrapDetected(pc);
+            }
+
+    //        if (callexternal) (*loopexternal)();
+            if self.do_callback && !self.stalled_on_rdy(ran, stalled) {
+                match mem.each_instr(self) {
+                    Control::Continue => {}
+                    Control::Stop(code) => return StopReason::HostRequested(code),
+                    Control::Breakpoint => return StopReason::Breakpoint(self.pc.0),
+                }
+            }
+
+    //    }
+    //}
+        }
+
+        if self.jam.is_some() {
+            StopReason::Jammed
+        } else {
+            StopReason::BudgetExhausted
+        }
+    }
+
+    //void step6502() {
+    //    ...
+    //    clockgoal6502 = clockticks6502;
+    //
+    //    instructions++;
+    //
+    //    if (callexternal) (*loopexternal)();
+    //}
+    //
+    // Executes exactly one instruction (after taking any interrupt that's pending, the same way
+    // exec() would) and reports what happened, for debuggers and test harnesses that want to go
+    // one instruction at a time. The return value of the callback is ignored, since there's
+    // nothing left to break out of. On a jammed CPU this does nothing and reports 0 cycles; in a
+    // WAI or stalled on RDY it spends one cycle. (See StepResult::ran.)
+    pub fn step<T: Backplane>(&mut self, mem: &mut T) -> StepResult {
+        let start = self.clockticks;
+        let (ran, stalled) = (self.instructions_ran, self.rdy_stalled_cycles);
+        let pc = self.run_instruction(mem);
+        self.clockgoal = self.clockticks;
+        let did_run = self.instructions_ran != ran;
+
+        let ea = match self.mode {
+            _ if !did_run => None,
+            AddrMode::Implied | AddrMode::Accumulator => None,
+            // For branches, report where the branch goes (or would have gone).
+            AddrMode::Relative => {
+                let from = if self.branch_taken { self.oldpc } else { self.pc.0 };
+                Some(from.wrapping_add(self.reladdr))
+            }
+            _ => Some(self.ea),
+        };
+        let result = StepResult {
+            ran: did_run,
+            pc,
+            opcode: self.opcode,
+            mode: self.mode,
+            ea,
+            cycles: (self.clockticks - start) as u32,
+            branch_taken: did_run && self.branch_taken,
+        };
+
+        if self.do_callback && !self.stalled_on_rdy(ran, stalled) {
+            mem.each_instr(self);
+        }
+
+        result
+    }
+
+    // Whether the last go around spent its cycle stalled on RDY rather than running anything,
+    // given instructions_ran and rdy_stalled_cycles from before it.
+    fn stalled_on_rdy(&self, ran: u64, stalled: u64) -> bool {
+        self.instructions_ran == ran && self.rdy_stalled_cycles != stalled
+    }
+
+    // The body of the exec() loop, shared with step(). Returns the address the instruction was
+    // fetched from.
+    fn run_instruction<T: Backplane>(&mut self, mem: &mut T) -> u16 {
+        if self.history.is_some() {
+            self.run_instruction_logged(mem, false, Vec::new(), false).0
+        } else {
+            self.execute_instruction(mem, false)
+        }
+    }
+
+    // With 'stop_after_interrupt' set, taking an interrupt is as far as this goes, leaving the
+    // first instruction of the handler for next time.
+    fn execute_instruction<T: Backplane>(&mut self, mem: &mut T, stop_after_interrupt: bool) -> u16 {
+        self.interrupted = false;
+
+        // A jammed CPU doesn't do anything at all, not even take interrupts; only reset() gets it
+        // going again.
+        if self.jam.is_some() {
+            return self.pc.0;
+        }
+
+        // After WAI (65C02 only) the CPU sits still until an interrupt line is asserted, whether
+        // or not the interrupt actually gets taken. Time keeps passing while it waits, a cycle per
+        // call, so the each_instr() callback gets a chance to assert something.
+        if self.waiting && !self.nmi_pending && self.irq_lines == 0 {
+            self.clockticks += 1;
+            self.bus_tick(mem, 1);
+            return self.pc.0;
+        }
+        self.waiting = false;
+
+        if !self.rdy_line() || !mem.ready() {
+            self.clockticks += 1;
+            self.rdy_stalled_cycles += 1;
+            if self.rdy_hold > 0 {
+                self.rdy_hold -= 1;
+            }
+            self.bus_tick(mem, 1);
+            return self.pc.0;
+        }
+
+        // (In cycle-accurate mode, Backplane::tick() gets called by bus_read() and bus_write(),
+        // otherwise once at the end of this.)
+        let start = self.clockticks;
+        self.bus_mode = self.cycle_accurate && !self.variant.is_cmos();
+        self.poll_interrupts(mem);
+        let pc = self.pc.0;
+        if self.interrupted && stop_after_interrupt {
+            if !self.bus_mode {
+                self.bus_tick(mem, (self.clockticks - start) as u32);
+            }
+            self.bus_mode = false;
+            return pc;
+        }
+        if let Some(mut tracer) = self.tracer.take() {
+            tracer.trace(self, mem);
+            self.tracer = Some(tracer);
+        }
+
+        if self.bus_mode {
+            self.run_one_op_bus(mem);
+            self.instructions_ran += 1;
+            return pc;
+        }
+
     //        opcode = read6502(pc++);
     //        status |= FLAG_CONSTANT;
-            self.opcode = mem.read(self.pc.0);
-            self.pc = self.pc + Wrapping(1);
-            self.flagset(FLAG_CONSTANT);
+        self.opcode = mem.read(self.pc.0);
+        self.pc += Wrapping(1);
+        self.flagset(FLAG_CONSTANT);
 
     //        penaltyop = 0;
     //        penaltyaddr = 0;
-            self.penaltyop = 0;
-            self.penaltyaddr = 0;
-            self.addr_acc = false;
+        self.penaltyop = 0;
+        self.penaltyaddr = 0;
+        self.addr_acc = false;
+        self.branch_taken = false;
 
     //        (*addrtable[opcode])();
     //        (*optable[opcode])();
     //        clockticks6502 += ticktable[opcode];
     //        if (penaltyop && penaltyaddr) clockticks6502++;
-            self.clockticks += self.run_one_op(mem);
-            if self.penaltyop != 0 && self.penaltyaddr != 0 {
-                self.clockticks += 1;
-            }
+        let old_i = self.status.0 & FLAG_INTERRUPT != 0;
+        self.clockticks += u64::from(if self.variant.is_cmos() {
+            self.run_one_op_65c02(mem)
+        } else {
+            self.run_one_op(mem)
+        });
+        if self.penaltyop != 0 && self.penaltyaddr != 0 {
+            self.clockticks += 1;
+        }
+        match self.opcode {
+            // CLI, SEI, PLP
+            0x58 | 0x78 | 0x28 => { self.delayed_i = Some(old_i); }
+            _ => {}
+        };
 
     //        instructions++;
-            // TODO: If anything needed to watch out for overflows, it's this.
-            self.instructions_ran += 1;
-
-            // TODO: Figure out how a callback works. Maybe an Option<fn>?
-    //        if (callexternal) (*loopexternal)();
-            
-            if self.do_callback {
-                if !mem.each_instr(self) {
-                    break;
-                }
-            }
+        self.instructions_ran += 1;
 
-    //    }
-    //}
-        }
+        self.bus_tick(mem, (self.clockticks - start) as u32);
+        pc
     }
 
     fn run_one_op<T: Backplane>(&mut self, mem: &mut T) -> u32 {
@@ -1712,10 +3235,10 @@ impl CPU {
 
         // We return the number of cycles running the instruction took (rather than looking it up
         // anywhere.)
-        return match self.opcode {
+        match self.opcode {
             0   => { self.addr_implied(mem);           self.inst_brk(mem);   7 },
             1   => { self.addr_indirect_x(mem);        self.inst_ora(mem);   6 },
-            2   => { self.addr_implied(mem);           self.inst_nop(mem);   2 },
+            2   => { self.addr_implied(mem);           self.inst_jam(mem);   2 },
             3   => { self.addr_indirect_x(mem);        self.inst_slo(mem);   8 },
             4   => { self.addr_zeropage(mem);          self.inst_nop(mem);   3 },
             5   => { self.addr_zeropage(mem);          self.inst_ora(mem);   3 },
@@ -1724,14 +3247,14 @@ impl CPU {
             8   => { self.addr_implied(mem);           self.inst_php(mem);   3 },
             9   => { self.addr_immediate(mem);         self.inst_ora(mem);   2 },
             10  => { self.addr_accumulator(mem);       self.inst_asl(mem);   2 },
-            11  => { self.addr_immediate(mem);         self.inst_nop(mem);   2 },
+            11  => { self.addr_immediate(mem);         self.inst_anc(mem);   2 },
             12  => { self.addr_absolute(mem);          self.inst_nop(mem);   4 },
             13  => { self.addr_absolute(mem);          self.inst_ora(mem);   4 },
             14  => { self.addr_absolute(mem);          self.inst_asl(mem);   6 },
             15  => { self.addr_absolute(mem);          self.inst_slo(mem);   6 },
             16  => { self.addr_relative_branch(mem);   self.inst_bpl(mem);   2 },
             17  => { self.addr_indirect_y(mem);        self.inst_ora(mem);   5 },
-            18  => { self.addr_implied(mem);           self.inst_nop(mem);   2 },
+            18  => { self.addr_implied(mem);           self.inst_jam(mem);   2 },
             19  => { self.addr_indirect_y(mem);        self.inst_slo(mem);   8 },
             20  => { self.addr_zeropage_x(mem);        self.inst_nop(mem);   4 },
             21  => { self.addr_zeropage_x(mem);        self.inst_ora(mem);   4 },
@@ -1747,7 +3270,7 @@ impl CPU {
             31  => { self.addr_absolute_x(mem);        self.inst_slo(mem);   7 },
             32  => { self.addr_absolute(mem);          self.inst_jsr(mem);   6 },
             33  => { self.addr_indirect_x(mem);        self.inst_and(mem);   6 },
-            34  => { self.addr_implied(mem);           self.inst_nop(mem);   2 },
+            34  => { self.addr_implied(mem);           self.inst_jam(mem);   2 },
             35  => { self.addr_indirect_x(mem);        self.inst_rla(mem);   8 },
             36  => { self.addr_zeropage(mem);          self.inst_bit(mem);   3 },
             37  => { self.addr_zeropage(mem);          self.inst_and(mem);   3 },
@@ -1756,14 +3279,14 @@ impl CPU {
             40  => { self.addr_implied(mem);           self.inst_plp(mem);   4 },
             41  => { self.addr_immediate(mem);         self.inst_and(mem);   2 },
             42  => { self.addr_accumulator(mem);       self.inst_rol(mem);   2 },
-            43  => { self.addr_immediate(mem);         self.inst_nop(mem);   2 },
+            43  => { self.addr_immediate(mem);         self.inst_anc(mem);   2 },
             44  => { self.addr_absolute(mem);          self.inst_bit(mem);   4 },
             45  => { self.addr_absolute(mem);          self.inst_and(mem);   4 },
             46  => { self.addr_absolute(mem);          self.inst_rol(mem);   6 },
             47  => { self.addr_absolute(mem);          self.inst_rla(mem);   6 },
             48  => { self.addr_relative_branch(mem);   self.inst_bmi(mem);   2 },
             49  => { self.addr_indirect_y(mem);        self.inst_and(mem);   5 },
-            50  => { self.addr_implied(mem);           self.inst_nop(mem);   2 },
+            50  => { self.addr_implied(mem);           self.inst_jam(mem);   2 },
             51  => { self.addr_indirect_y(mem);        self.inst_rla(mem);   8 },
             52  => { self.addr_zeropage_x(mem);        self.inst_nop(mem);   4 },
             53  => { self.addr_zeropage_x(mem);        self.inst_and(mem);   4 },
@@ -1779,7 +3302,7 @@ impl CPU {
             63  => { self.addr_absolute_x(mem);        self.inst_rla(mem);   7 },
             64  => { self.addr_implied(mem);           self.inst_rti(mem);   6 },
             65  => { self.addr_indirect_x(mem);        self.inst_eor(mem);   6 },
-            66  => { self.addr_implied(mem);           self.inst_nop(mem);   2 },
+            66  => { self.addr_implied(mem);           self.inst_jam(mem);   2 },
             67  => { self.addr_indirect_x(mem);        self.inst_sre(mem);   8 },
             68  => { self.addr_zeropage(mem);          self.inst_nop(mem);   3 },
             69  => { self.addr_zeropage(mem);          self.inst_eor(mem);   3 },
@@ -1788,14 +3311,14 @@ impl CPU {
             72  => { self.addr_implied(mem);           self.inst_pha(mem);   3 },
             73  => { self.addr_immediate(mem);         self.inst_eor(mem);   2 },
             74  => { self.addr_accumulator(mem);       self.inst_lsr(mem);   2 },
-            75  => { self.addr_immediate(mem);         self.inst_nop(mem);   2 },
+            75  => { self.addr_immediate(mem);         self.inst_alr(mem);   2 },
             76  => { self.addr_absolute(mem);          self.inst_jmp(mem);   3 },
             77  => { self.addr_absolute(mem);          self.inst_eor(mem);   4 },
             78  => { self.addr_absolute(mem);          self.inst_lsr(mem);   6 },
             79  => { self.addr_absolute(mem);          self.inst_sre(mem);   6 },
             80  => { self.addr_relative_branch(mem);   self.inst_bvc(mem);   2 },
             81  => { self.addr_indirect_y(mem);        self.inst_eor(mem);   5 },
-            82  => { self.addr_implied(mem);           self.inst_nop(mem);   2 },
+            82  => { self.addr_implied(mem);           self.inst_jam(mem);   2 },
             83  => { self.addr_indirect_y(mem);        self.inst_sre(mem);   8 },
             84  => { self.addr_zeropage_x(mem);        self.inst_nop(mem);   4 },
             85  => { self.addr_zeropage_x(mem);        self.inst_eor(mem);   4 },
@@ -1811,7 +3334,7 @@ impl CPU {
             95  => { self.addr_absolute_x(mem);        self.inst_sre(mem);   7 },
             96  => { self.addr_implied(mem);           self.inst_rts(mem);   6 },
             97  => { self.addr_indirect_x(mem);        self.inst_adc(mem);   6 },
-            98  => { self.addr_implied(mem);           self.inst_nop(mem);   2 },
+            98  => { self.addr_implied(mem);           self.inst_jam(mem);   2 },
             99  => { self.addr_indirect_x(mem);        self.inst_rra(mem);   8 },
             100 => { self.addr_zeropage(mem);          self.inst_nop(mem);   3 },
             101 => { self.addr_zeropage(mem);          self.inst_adc(mem);   3 },
@@ -1820,14 +3343,14 @@ impl CPU {
             104 => { self.addr_implied(mem);           self.inst_pla(mem);   4 },
             105 => { self.addr_immediate(mem);         self.inst_adc(mem);   2 },
             106 => { self.addr_accumulator(mem);       self.inst_ror(mem);   2 },
-            107 => { self.addr_immediate(mem);         self.inst_nop(mem);   2 },
+            107 => { self.addr_immediate(mem);         self.inst_arr(mem);   2 },
             108 => { self.addr_indirect(mem);          self.inst_jmp(mem);   5 },
             109 => { self.addr_absolute(mem);          self.inst_adc(mem);   4 },
             110 => { self.addr_absolute(mem);          self.inst_ror(mem);   6 },
             111 => { self.addr_absolute(mem);          self.inst_rra(mem);   6 },
             112 => { self.addr_relative_branch(mem);   self.inst_bvs(mem);   2 },
             113 => { self.addr_indirect_y(mem);        self.inst_adc(mem);   5 },
-            114 => { self.addr_implied(mem);           self.inst_nop(mem);   2 },
+            114 => { self.addr_implied(mem);           self.inst_jam(mem);   2 },
             115 => { self.addr_indirect_y(mem);        self.inst_rra(mem);   8 },
             116 => { self.addr_zeropage_x(mem);        self.inst_nop(mem);   4 },
             117 => { self.addr_zeropage_x(mem);        self.inst_adc(mem);   4 },
@@ -1852,15 +3375,15 @@ impl CPU {
             136 => { self.addr_implied(mem);           self.inst_dey(mem);   2 },
             137 => { self.addr_immediate(mem);         self.inst_nop(mem);   2 },
             138 => { self.addr_implied(mem);           self.inst_txa(mem);   2 },
-            139 => { self.addr_immediate(mem);         self.inst_nop(mem);   2 },
+            139 => { self.addr_immediate(mem);         self.inst_ane(mem);   2 },
             140 => { self.addr_absolute(mem);          self.inst_sty(mem);   4 },
             141 => { self.addr_absolute(mem);          self.inst_sta(mem);   4 },
             142 => { self.addr_absolute(mem);          self.inst_stx(mem);   4 },
             143 => { self.addr_absolute(mem);          self.inst_sax(mem);   4 },
             144 => { self.addr_relative_branch(mem);   self.inst_bcc(mem);   2 },
             145 => { self.addr_indirect_y(mem);        self.inst_sta(mem);   6 },
-            146 => { self.addr_implied(mem);           self.inst_nop(mem);   2 },
-            147 => { self.addr_indirect_y(mem);        self.inst_nop(mem);   6 },
+            146 => { self.addr_implied(mem);           self.inst_jam(mem);   2 },
+            147 => { self.addr_indirect_y(mem);        self.inst_sha(mem);   6 },
             148 => { self.addr_zeropage_x(mem);        self.inst_sty(mem);   4 },
             149 => { self.addr_zeropage_x(mem);        self.inst_sta(mem);   4 },
             150 => { self.addr_zeropage_y(mem);        self.inst_stx(mem);   4 },
@@ -1868,11 +3391,11 @@ impl CPU {
             152 => { self.addr_implied(mem);           self.inst_tya(mem);   2 },
             153 => { self.addr_absolute_y(mem);        self.inst_sta(mem);   5 },
             154 => { self.addr_implied(mem);           self.inst_txs(mem);   2 },
-            155 => { self.addr_absolute_y(mem);        self.inst_nop(mem);   5 },
-            156 => { self.addr_absolute_x(mem);        self.inst_nop(mem);   5 },
+            155 => { self.addr_absolute_y(mem);        self.inst_tas(mem);   5 },
+            156 => { self.addr_absolute_x(mem);        self.inst_shy(mem);   5 },
             157 => { self.addr_absolute_x(mem);        self.inst_sta(mem);   5 },
-            158 => { self.addr_absolute_y(mem);        self.inst_nop(mem);   5 },
-            159 => { self.addr_absolute_y(mem);        self.inst_nop(mem);   5 },
+            158 => { self.addr_absolute_y(mem);        self.inst_shx(mem);   5 },
+            159 => { self.addr_absolute_y(mem);        self.inst_sha(mem);   5 },
             160 => { self.addr_immediate(mem);         self.inst_ldy(mem);   2 },
             161 => { self.addr_indirect_x(mem);        self.inst_lda(mem);   6 },
             162 => { self.addr_immediate(mem);         self.inst_ldx(mem);   2 },
@@ -1884,14 +3407,14 @@ impl CPU {
             168 => { self.addr_implied(mem);           self.inst_tay(mem);   2 },
             169 => { self.addr_immediate(mem);         self.inst_lda(mem);   2 },
             170 => { self.addr_implied(mem);           self.inst_tax(mem);   2 },
-            171 => { self.addr_immediate(mem);         self.inst_nop(mem);   2 },
+            171 => { self.addr_immediate(mem);         self.inst_lxa(mem);   2 },
             172 => { self.addr_absolute(mem);          self.inst_ldy(mem);   4 },
             173 => { self.addr_absolute(mem);          self.inst_lda(mem);   4 },
             174 => { self.addr_absolute(mem);          self.inst_ldx(mem);   4 },
             175 => { self.addr_absolute(mem);          self.inst_lax(mem);   4 },
             176 => { self.addr_relative_branch(mem);   self.inst_bcs(mem);   2 },
             177 => { self.addr_indirect_y(mem);        self.inst_lda(mem);   5 },
-            178 => { self.addr_implied(mem);           self.inst_nop(mem);   2 },
+            178 => { self.addr_implied(mem);           self.inst_jam(mem);   2 },
             179 => { self.addr_indirect_y(mem);        self.inst_lax(mem);   5 },
             180 => { self.addr_zeropage_x(mem);        self.inst_ldy(mem);   4 },
             181 => { self.addr_zeropage_x(mem);        self.inst_lda(mem);   4 },
@@ -1900,7 +3423,7 @@ impl CPU {
             184 => { self.addr_implied(mem);           self.inst_clv(mem);   2 },
             185 => { self.addr_absolute_y(mem);        self.inst_lda(mem);   4 },
             186 => { self.addr_implied(mem);           self.inst_tsx(mem);   2 },
-            187 => { self.addr_absolute_y(mem);        self.inst_lax(mem);   4 },
+            187 => { self.addr_absolute_y(mem);        self.inst_las(mem);   4 },
             188 => { self.addr_absolute_x(mem);        self.inst_ldy(mem);   4 },
             189 => { self.addr_absolute_x(mem);        self.inst_lda(mem);   4 },
             190 => { self.addr_absolute_y(mem);        self.inst_ldx(mem);   4 },
@@ -1916,14 +3439,14 @@ impl CPU {
             200 => { self.addr_implied(mem);           self.inst_iny(mem);   2 },
             201 => { self.addr_immediate(mem);         self.inst_cmp(mem);   2 },
             202 => { self.addr_implied(mem);           self.inst_dex(mem);   2 },
-            203 => { self.addr_immediate(mem);         self.inst_nop(mem);   2 },
+            203 => { self.addr_immediate(mem);         self.inst_sbx(mem);   2 },
             204 => { self.addr_absolute(mem);          self.inst_cpy(mem);   4 },
             205 => { self.addr_absolute(mem);          self.inst_cmp(mem);   4 },
             206 => { self.addr_absolute(mem);          self.inst_dec(mem);   6 },
             207 => { self.addr_absolute(mem);          self.inst_dcp(mem);   6 },
             208 => { self.addr_relative_branch(mem);   self.inst_bne(mem);   2 },
             209 => { self.addr_indirect_y(mem);        self.inst_cmp(mem);   5 },
-            210 => { self.addr_implied(mem);           self.inst_nop(mem);   2 },
+            210 => { self.addr_implied(mem);           self.inst_jam(mem);   2 },
             211 => { self.addr_indirect_y(mem);        self.inst_dcp(mem);   8 },
             212 => { self.addr_zeropage_x(mem);        self.inst_nop(mem);   4 },
             213 => { self.addr_zeropage_x(mem);        self.inst_cmp(mem);   4 },
@@ -1948,14 +3471,14 @@ impl CPU {
             232 => { self.addr_implied(mem);           self.inst_inx(mem);   2 },
             233 => { self.addr_immediate(mem);         self.inst_sbc(mem);   2 },
             234 => { self.addr_implied(mem);           self.inst_nop(mem);   2 },
-            235 => { self.addr_immediate(mem);         self.inst_sbc(mem);   2 },
+            235 => { self.addr_immediate(mem);         self.inst_usbc(mem);  2 },
             236 => { self.addr_absolute(mem);          self.inst_cpx(mem);   4 },
             237 => { self.addr_absolute(mem);          self.inst_sbc(mem);   4 },
             238 => { self.addr_absolute(mem);          self.inst_inc(mem);   6 },
             239 => { self.addr_absolute(mem);          self.inst_isb(mem);   6 },
             240 => { self.addr_relative_branch(mem);   self.inst_beq(mem);   2 },
             241 => { self.addr_indirect_y(mem);        self.inst_sbc(mem);   5 },
-            242 => { self.addr_implied(mem);           self.inst_nop(mem);   2 },
+            242 => { self.addr_implied(mem);           self.inst_jam(mem);   2 },
             243 => { self.addr_indirect_y(mem);        self.inst_isb(mem);   8 },
             244 => { self.addr_zeropage_x(mem);        self.inst_nop(mem);   4 },
             245 => { self.addr_zeropage_x(mem);        self.inst_sbc(mem);   4 },
@@ -1969,13 +3492,1421 @@ impl CPU {
             253 => { self.addr_absolute_x(mem);        self.inst_sbc(mem);   4 },
             254 => { self.addr_absolute_x(mem);        self.inst_inc(mem);   7 },
             255 => { self.addr_absolute_x(mem);        self.inst_isb(mem);   7 },
-            _   => { panic!("unimplemented/impossible instruction");           },
         }
     }
+
+    // The same thing for the 65C02, generated by running process_tables.py with "65c02" as its
+    // argument. Every opcode is defined on the 65C02; the ones that don't do anything are NOPs of
+    // various lengths and timings.
+    fn run_one_op_65c02<T: Backplane>(&mut self, mem: &mut T) -> u32 {
+        match self.opcode {
+            0   => { self.addr_implied(mem);               self.inst_brk(mem);   7 },
+            1   => { self.addr_indirect_x(mem);            self.inst_ora(mem);   6 },
+            2   => { self.addr_immediate(mem);             self.inst_nop(mem);   2 },
+            3   => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            4   => { self.addr_zeropage(mem);              self.inst_tsb(mem);   5 },
+            5   => { self.addr_zeropage(mem);              self.inst_ora(mem);   3 },
+            6   => { self.addr_zeropage(mem);              self.inst_asl(mem);   5 },
+            7   => { self.addr_zeropage(mem);              self.inst_rmb(mem);   5 },
+            8   => { self.addr_implied(mem);               self.inst_php(mem);   3 },
+            9   => { self.addr_immediate(mem);             self.inst_ora(mem);   2 },
+            10  => { self.addr_accumulator(mem);           self.inst_asl(mem);   2 },
+            11  => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            12  => { self.addr_absolute(mem);              self.inst_tsb(mem);   6 },
+            13  => { self.addr_absolute(mem);              self.inst_ora(mem);   4 },
+            14  => { self.addr_absolute(mem);              self.inst_asl(mem);   6 },
+            15  => { self.addr_zeropage_relative(mem);     self.inst_bbr(mem);   5 },
+            16  => { self.addr_relative_branch(mem);       self.inst_bpl(mem);   2 },
+            17  => { self.addr_indirect_y(mem);            self.inst_ora(mem);   5 },
+            18  => { self.addr_zeropage_indirect(mem);     self.inst_ora(mem);   5 },
+            19  => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            20  => { self.addr_zeropage(mem);              self.inst_trb(mem);   5 },
+            21  => { self.addr_zeropage_x(mem);            self.inst_ora(mem);   4 },
+            22  => { self.addr_zeropage_x(mem);            self.inst_asl(mem);   6 },
+            23  => { self.addr_zeropage(mem);              self.inst_rmb(mem);   5 },
+            24  => { self.addr_implied(mem);               self.inst_clc(mem);   2 },
+            25  => { self.addr_absolute_y(mem);            self.inst_ora(mem);   4 },
+            26  => { self.addr_accumulator(mem);           self.inst_inc(mem);   2 },
+            27  => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            28  => { self.addr_absolute(mem);              self.inst_trb(mem);   6 },
+            29  => { self.addr_absolute_x(mem);            self.inst_ora(mem);   4 },
+            30  => { self.addr_absolute_x(mem);            self.inst_asl(mem);   6 },
+            31  => { self.addr_zeropage_relative(mem);     self.inst_bbr(mem);   5 },
+            32  => { self.addr_absolute(mem);              self.inst_jsr(mem);   6 },
+            33  => { self.addr_indirect_x(mem);            self.inst_and(mem);   6 },
+            34  => { self.addr_immediate(mem);             self.inst_nop(mem);   2 },
+            35  => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            36  => { self.addr_zeropage(mem);              self.inst_bit(mem);   3 },
+            37  => { self.addr_zeropage(mem);              self.inst_and(mem);   3 },
+            38  => { self.addr_zeropage(mem);              self.inst_rol(mem);   5 },
+            39  => { self.addr_zeropage(mem);              self.inst_rmb(mem);   5 },
+            40  => { self.addr_implied(mem);               self.inst_plp(mem);   4 },
+            41  => { self.addr_immediate(mem);             self.inst_and(mem);   2 },
+            42  => { self.addr_accumulator(mem);           self.inst_rol(mem);   2 },
+            43  => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            44  => { self.addr_absolute(mem);              self.inst_bit(mem);   4 },
+            45  => { self.addr_absolute(mem);              self.inst_and(mem);   4 },
+            46  => { self.addr_absolute(mem);              self.inst_rol(mem);   6 },
+            47  => { self.addr_zeropage_relative(mem);     self.inst_bbr(mem);   5 },
+            48  => { self.addr_relative_branch(mem);       self.inst_bmi(mem);   2 },
+            49  => { self.addr_indirect_y(mem);            self.inst_and(mem);   5 },
+            50  => { self.addr_zeropage_indirect(mem);     self.inst_and(mem);   5 },
+            51  => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            52  => { self.addr_zeropage_x(mem);            self.inst_bit(mem);   4 },
+            53  => { self.addr_zeropage_x(mem);            self.inst_and(mem);   4 },
+            54  => { self.addr_zeropage_x(mem);            self.inst_rol(mem);   6 },
+            55  => { self.addr_zeropage(mem);              self.inst_rmb(mem);   5 },
+            56  => { self.addr_implied(mem);               self.inst_sec(mem);   2 },
+            57  => { self.addr_absolute_y(mem);            self.inst_and(mem);   4 },
+            58  => { self.addr_accumulator(mem);           self.inst_dec(mem);   2 },
+            59  => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            60  => { self.addr_absolute_x(mem);            self.inst_bit(mem);   4 },
+            61  => { self.addr_absolute_x(mem);            self.inst_and(mem);   4 },
+            62  => { self.addr_absolute_x(mem);            self.inst_rol(mem);   6 },
+            63  => { self.addr_zeropage_relative(mem);     self.inst_bbr(mem);   5 },
+            64  => { self.addr_implied(mem);               self.inst_rti(mem);   6 },
+            65  => { self.addr_indirect_x(mem);            self.inst_eor(mem);   6 },
+            66  => { self.addr_immediate(mem);             self.inst_nop(mem);   2 },
+            67  => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            68  => { self.addr_zeropage(mem);              self.inst_nop(mem);   3 },
+            69  => { self.addr_zeropage(mem);              self.inst_eor(mem);   3 },
+            70  => { self.addr_zeropage(mem);              self.inst_lsr(mem);   5 },
+            71  => { self.addr_zeropage(mem);              self.inst_rmb(mem);   5 },
+            72  => { self.addr_implied(mem);               self.inst_pha(mem);   3 },
+            73  => { self.addr_immediate(mem);             self.inst_eor(mem);   2 },
+            74  => { self.addr_accumulator(mem);           self.inst_lsr(mem);   2 },
+            75  => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            76  => { self.addr_absolute(mem);              self.inst_jmp(mem);   3 },
+            77  => { self.addr_absolute(mem);              self.inst_eor(mem);   4 },
+            78  => { self.addr_absolute(mem);              self.inst_lsr(mem);   6 },
+            79  => { self.addr_zeropage_relative(mem);     self.inst_bbr(mem);   5 },
+            80  => { self.addr_relative_branch(mem);       self.inst_bvc(mem);   2 },
+            81  => { self.addr_indirect_y(mem);            self.inst_eor(mem);   5 },
+            82  => { self.addr_zeropage_indirect(mem);     self.inst_eor(mem);   5 },
+            83  => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            84  => { self.addr_zeropage_x(mem);            self.inst_nop(mem);   4 },
+            85  => { self.addr_zeropage_x(mem);            self.inst_eor(mem);   4 },
+            86  => { self.addr_zeropage_x(mem);            self.inst_lsr(mem);   6 },
+            87  => { self.addr_zeropage(mem);              self.inst_rmb(mem);   5 },
+            88  => { self.addr_implied(mem);               self.inst_cli(mem);   2 },
+            89  => { self.addr_absolute_y(mem);            self.inst_eor(mem);   4 },
+            90  => { self.addr_implied(mem);               self.inst_phy(mem);   3 },
+            91  => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            92  => { self.addr_absolute(mem);              self.inst_nop(mem);   8 },
+            93  => { self.addr_absolute_x(mem);            self.inst_eor(mem);   4 },
+            94  => { self.addr_absolute_x(mem);            self.inst_lsr(mem);   6 },
+            95  => { self.addr_zeropage_relative(mem);     self.inst_bbr(mem);   5 },
+            96  => { self.addr_implied(mem);               self.inst_rts(mem);   6 },
+            97  => { self.addr_indirect_x(mem);            self.inst_adc(mem);   6 },
+            98  => { self.addr_immediate(mem);             self.inst_nop(mem);   2 },
+            99  => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            100 => { self.addr_zeropage(mem);              self.inst_stz(mem);   3 },
+            101 => { self.addr_zeropage(mem);              self.inst_adc(mem);   3 },
+            102 => { self.addr_zeropage(mem);              self.inst_ror(mem);   5 },
+            103 => { self.addr_zeropage(mem);              self.inst_rmb(mem);   5 },
+            104 => { self.addr_implied(mem);               self.inst_pla(mem);   4 },
+            105 => { self.addr_immediate(mem);             self.inst_adc(mem);   2 },
+            106 => { self.addr_accumulator(mem);           self.inst_ror(mem);   2 },
+            107 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            108 => { self.addr_indirect(mem);              self.inst_jmp(mem);   6 },
+            109 => { self.addr_absolute(mem);              self.inst_adc(mem);   4 },
+            110 => { self.addr_absolute(mem);              self.inst_ror(mem);   6 },
+            111 => { self.addr_zeropage_relative(mem);     self.inst_bbr(mem);   5 },
+            112 => { self.addr_relative_branch(mem);       self.inst_bvs(mem);   2 },
+            113 => { self.addr_indirect_y(mem);            self.inst_adc(mem);   5 },
+            114 => { self.addr_zeropage_indirect(mem);     self.inst_adc(mem);   5 },
+            115 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            116 => { self.addr_zeropage_x(mem);            self.inst_stz(mem);   4 },
+            117 => { self.addr_zeropage_x(mem);            self.inst_adc(mem);   4 },
+            118 => { self.addr_zeropage_x(mem);            self.inst_ror(mem);   6 },
+            119 => { self.addr_zeropage(mem);              self.inst_rmb(mem);   5 },
+            120 => { self.addr_implied(mem);               self.inst_sei(mem);   2 },
+            121 => { self.addr_absolute_y(mem);            self.inst_adc(mem);   4 },
+            122 => { self.addr_implied(mem);               self.inst_ply(mem);   4 },
+            123 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            124 => { self.addr_absolute_x_indirect(mem);   self.inst_jmp(mem);   6 },
+            125 => { self.addr_absolute_x(mem);            self.inst_adc(mem);   4 },
+            126 => { self.addr_absolute_x(mem);            self.inst_ror(mem);   6 },
+            127 => { self.addr_zeropage_relative(mem);     self.inst_bbr(mem);   5 },
+            128 => { self.addr_relative_branch(mem);       self.inst_bra(mem);   2 },
+            129 => { self.addr_indirect_x(mem);            self.inst_sta(mem);   6 },
+            130 => { self.addr_immediate(mem);             self.inst_nop(mem);   2 },
+            131 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            132 => { self.addr_zeropage(mem);              self.inst_sty(mem);   3 },
+            133 => { self.addr_zeropage(mem);              self.inst_sta(mem);   3 },
+            134 => { self.addr_zeropage(mem);              self.inst_stx(mem);   3 },
+            135 => { self.addr_zeropage(mem);              self.inst_smb(mem);   5 },
+            136 => { self.addr_implied(mem);               self.inst_dey(mem);   2 },
+            137 => { self.addr_immediate(mem);             self.inst_bit(mem);   2 },
+            138 => { self.addr_implied(mem);               self.inst_txa(mem);   2 },
+            139 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            140 => { self.addr_absolute(mem);              self.inst_sty(mem);   4 },
+            141 => { self.addr_absolute(mem);              self.inst_sta(mem);   4 },
+            142 => { self.addr_absolute(mem);              self.inst_stx(mem);   4 },
+            143 => { self.addr_zeropage_relative(mem);     self.inst_bbs(mem);   5 },
+            144 => { self.addr_relative_branch(mem);       self.inst_bcc(mem);   2 },
+            145 => { self.addr_indirect_y(mem);            self.inst_sta(mem);   6 },
+            146 => { self.addr_zeropage_indirect(mem);     self.inst_sta(mem);   5 },
+            147 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            148 => { self.addr_zeropage_x(mem);            self.inst_sty(mem);   4 },
+            149 => { self.addr_zeropage_x(mem);            self.inst_sta(mem);   4 },
+            150 => { self.addr_zeropage_y(mem);            self.inst_stx(mem);   4 },
+            151 => { self.addr_zeropage(mem);              self.inst_smb(mem);   5 },
+            152 => { self.addr_implied(mem);               self.inst_tya(mem);   2 },
+            153 => { self.addr_absolute_y(mem);            self.inst_sta(mem);   5 },
+            154 => { self.addr_implied(mem);               self.inst_txs(mem);   2 },
+            155 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            156 => { self.addr_absolute(mem);              self.inst_stz(mem);   4 },
+            157 => { self.addr_absolute_x(mem);            self.inst_sta(mem);   5 },
+            158 => { self.addr_absolute_x(mem);            self.inst_stz(mem);   5 },
+            159 => { self.addr_zeropage_relative(mem);     self.inst_bbs(mem);   5 },
+            160 => { self.addr_immediate(mem);             self.inst_ldy(mem);   2 },
+            161 => { self.addr_indirect_x(mem);            self.inst_lda(mem);   6 },
+            162 => { self.addr_immediate(mem);             self.inst_ldx(mem);   2 },
+            163 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            164 => { self.addr_zeropage(mem);              self.inst_ldy(mem);   3 },
+            165 => { self.addr_zeropage(mem);              self.inst_lda(mem);   3 },
+            166 => { self.addr_zeropage(mem);              self.inst_ldx(mem);   3 },
+            167 => { self.addr_zeropage(mem);              self.inst_smb(mem);   5 },
+            168 => { self.addr_implied(mem);               self.inst_tay(mem);   2 },
+            169 => { self.addr_immediate(mem);             self.inst_lda(mem);   2 },
+            170 => { self.addr_implied(mem);               self.inst_tax(mem);   2 },
+            171 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            172 => { self.addr_absolute(mem);              self.inst_ldy(mem);   4 },
+            173 => { self.addr_absolute(mem);              self.inst_lda(mem);   4 },
+            174 => { self.addr_absolute(mem);              self.inst_ldx(mem);   4 },
+            175 => { self.addr_zeropage_relative(mem);     self.inst_bbs(mem);   5 },
+            176 => { self.addr_relative_branch(mem);       self.inst_bcs(mem);   2 },
+            177 => { self.addr_indirect_y(mem);            self.inst_lda(mem);   5 },
+            178 => { self.addr_zeropage_indirect(mem);     self.inst_lda(mem);   5 },
+            179 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            180 => { self.addr_zeropage_x(mem);            self.inst_ldy(mem);   4 },
+            181 => { self.addr_zeropage_x(mem);            self.inst_lda(mem);   4 },
+            182 => { self.addr_zeropage_y(mem);            self.inst_ldx(mem);   4 },
+            183 => { self.addr_zeropage(mem);              self.inst_smb(mem);   5 },
+            184 => { self.addr_implied(mem);               self.inst_clv(mem);   2 },
+            185 => { self.addr_absolute_y(mem);            self.inst_lda(mem);   4 },
+            186 => { self.addr_implied(mem);               self.inst_tsx(mem);   2 },
+            187 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            188 => { self.addr_absolute_x(mem);            self.inst_ldy(mem);   4 },
+            189 => { self.addr_absolute_x(mem);            self.inst_lda(mem);   4 },
+            190 => { self.addr_absolute_y(mem);            self.inst_ldx(mem);   4 },
+            191 => { self.addr_zeropage_relative(mem);     self.inst_bbs(mem);   5 },
+            192 => { self.addr_immediate(mem);             self.inst_cpy(mem);   2 },
+            193 => { self.addr_indirect_x(mem);            self.inst_cmp(mem);   6 },
+            194 => { self.addr_immediate(mem);             self.inst_nop(mem);   2 },
+            195 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            196 => { self.addr_zeropage(mem);              self.inst_cpy(mem);   3 },
+            197 => { self.addr_zeropage(mem);              self.inst_cmp(mem);   3 },
+            198 => { self.addr_zeropage(mem);              self.inst_dec(mem);   5 },
+            199 => { self.addr_zeropage(mem);              self.inst_smb(mem);   5 },
+            200 => { self.addr_implied(mem);               self.inst_iny(mem);   2 },
+            201 => { self.addr_immediate(mem);             self.inst_cmp(mem);   2 },
+            202 => { self.addr_implied(mem);               self.inst_dex(mem);   2 },
+            203 => { self.addr_implied(mem);               self.inst_wai(mem);   3 },
+            204 => { self.addr_absolute(mem);              self.inst_cpy(mem);   4 },
+            205 => { self.addr_absolute(mem);              self.inst_cmp(mem);   4 },
+            206 => { self.addr_absolute(mem);              self.inst_dec(mem);   6 },
+            207 => { self.addr_zeropage_relative(mem);     self.inst_bbs(mem);   5 },
+            208 => { self.addr_relative_branch(mem);       self.inst_bne(mem);   2 },
+            209 => { self.addr_indirect_y(mem);            self.inst_cmp(mem);   5 },
+            210 => { self.addr_zeropage_indirect(mem);     self.inst_cmp(mem);   5 },
+            211 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            212 => { self.addr_zeropage_x(mem);            self.inst_nop(mem);   4 },
+            213 => { self.addr_zeropage_x(mem);            self.inst_cmp(mem);   4 },
+            214 => { self.addr_zeropage_x(mem);            self.inst_dec(mem);   6 },
+            215 => { self.addr_zeropage(mem);              self.inst_smb(mem);   5 },
+            216 => { self.addr_implied(mem);               self.inst_cld(mem);   2 },
+            217 => { self.addr_absolute_y(mem);            self.inst_cmp(mem);   4 },
+            218 => { self.addr_implied(mem);               self.inst_phx(mem);   3 },
+            219 => { self.addr_implied(mem);               self.inst_stp(mem);   3 },
+            220 => { self.addr_absolute(mem);              self.inst_nop(mem);   4 },
+            221 => { self.addr_absolute_x(mem);            self.inst_cmp(mem);   4 },
+            222 => { self.addr_absolute_x(mem);            self.inst_dec(mem);   7 },
+            223 => { self.addr_zeropage_relative(mem);     self.inst_bbs(mem);   5 },
+            224 => { self.addr_immediate(mem);             self.inst_cpx(mem);   2 },
+            225 => { self.addr_indirect_x(mem);            self.inst_sbc(mem);   6 },
+            226 => { self.addr_immediate(mem);             self.inst_nop(mem);   2 },
+            227 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            228 => { self.addr_zeropage(mem);              self.inst_cpx(mem);   3 },
+            229 => { self.addr_zeropage(mem);              self.inst_sbc(mem);   3 },
+            230 => { self.addr_zeropage(mem);              self.inst_inc(mem);   5 },
+            231 => { self.addr_zeropage(mem);              self.inst_smb(mem);   5 },
+            232 => { self.addr_implied(mem);               self.inst_inx(mem);   2 },
+            233 => { self.addr_immediate(mem);             self.inst_sbc(mem);   2 },
+            234 => { self.addr_implied(mem);               self.inst_nop(mem);   2 },
+            235 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            236 => { self.addr_absolute(mem);              self.inst_cpx(mem);   4 },
+            237 => { self.addr_absolute(mem);              self.inst_sbc(mem);   4 },
+            238 => { self.addr_absolute(mem);              self.inst_inc(mem);   6 },
+            239 => { self.addr_zeropage_relative(mem);     self.inst_bbs(mem);   5 },
+            240 => { self.addr_relative_branch(mem);       self.inst_beq(mem);   2 },
+            241 => { self.addr_indirect_y(mem);            self.inst_sbc(mem);   5 },
+            242 => { self.addr_zeropage_indirect(mem);     self.inst_sbc(mem);   5 },
+            243 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            244 => { self.addr_zeropage_x(mem);            self.inst_nop(mem);   4 },
+            245 => { self.addr_zeropage_x(mem);            self.inst_sbc(mem);   4 },
+            246 => { self.addr_zeropage_x(mem);            self.inst_inc(mem);   6 },
+            247 => { self.addr_zeropage(mem);              self.inst_smb(mem);   5 },
+            248 => { self.addr_implied(mem);               self.inst_sed(mem);   2 },
+            249 => { self.addr_absolute_y(mem);            self.inst_sbc(mem);   4 },
+            250 => { self.addr_implied(mem);               self.inst_plx(mem);   4 },
+            251 => { self.addr_implied(mem);               self.inst_nop(mem);   1 },
+            252 => { self.addr_absolute(mem);              self.inst_nop(mem);   4 },
+            253 => { self.addr_absolute_x(mem);            self.inst_sbc(mem);   4 },
+            254 => { self.addr_absolute_x(mem);            self.inst_inc(mem);   7 },
+            255 => { self.addr_zeropage_relative(mem);     self.inst_bbs(mem);   5 },
+        }
+    }
+}
+
+
+
+
+
+// What kind of memory access an instruction makes through its addressing mode; cycle-accurate
+// mode needs to know this before it runs the instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BusAccess {
+    // Implied, accumulator, branches, jumps: nothing (beyond the addressing itself).
+    None,
+    Read,
+    Write,
+    ReadModifyWrite,
+}
+
+// Works this out for the NMOS opcodes from the layout of the opcode matrix: the low two bits pick
+// the group and the top three the operation, and the stores and read-modify-writes all fall in
+// predictable places.
+fn bus_access(opcode: u8, mode: AddrMode) -> BusAccess {
+    match mode {
+        AddrMode::Implied | AddrMode::Accumulator | AddrMode::Relative => return BusAccess::None,
+        AddrMode::Immediate => return BusAccess::Read,
+        _ => {}
+    };
+    match opcode {
+        // JMP abs, JMP (ind), JSR
+        0x4C | 0x6C | 0x20 => return BusAccess::None,
+        _ => {}
+    };
+    match (opcode & 0x03, opcode >> 5) {
+        // STA, STX, STY, SAX, SHA, SHX, SHY, TAS
+        (_, 4) => BusAccess::Write,
+        // ASL, ROL, LSR, ROR, DEC, INC, and their undocumented combinations
+        (2, 0..=3) | (2, 6..=7) | (3, 0..=3) | (3, 6..=7) => BusAccess::ReadModifyWrite,
+        _ => BusAccess::Read,
+    }
+}
+
+// Turning bytes back into assembly language and back again, from the tables below, and the
+// debugging aids built on that. (The path attributes keep these next to this file however this
+// file itself gets pulled in.)
+#[path = "disasm.rs"]
+pub mod disasm;
+#[path = "asm.rs"]
+pub mod asm;
+#[path = "trace.rs"]
+pub mod trace;
+#[path = "gdb.rs"]
+pub mod gdb;
+#[path = "breakpoints.rs"]
+pub mod breakpoints;
+#[path = "history.rs"]
+pub mod history;
+
+impl CPU {
+    // Write a nestest.log style line (see trace.rs) for every instruction from here on, just before
+    // it runs. Interrupts don't get a line of their own; the first instruction of the handler does.
+    pub fn trace_to(&mut self, out: Box<dyn std::io::Write>, options: trace::TraceOptions) {
+        self.tracer = Some(trace::Tracer::new(out, options));
+    }
+
+    pub fn stop_tracing(&mut self) {
+        self.tracer = None;
+    }
+
+    pub fn is_tracing(&self) -> bool {
+        self.tracer.is_some()
+    }
+
+    // run_instruction() for when there are breakpoints to look out for. The breakpoints come out
+    // of self while they're checked, since conditions need to look at the CPU.
+    fn run_instruction_checked<T: Backplane>(&mut self, mem: &mut T) -> Result<u16, StopReason> {
+        let mut bps = std::mem::take(&mut self.breakpoints);
+        let result = self.run_instruction_with(&mut bps, mem);
+        self.breakpoints = bps;
+        result
+    }
+
+    fn run_instruction_with<T: Backplane>(&mut self, bps: &mut breakpoints::Breakpoints, mem: &mut T) -> Result<u16, StopReason> {
+        // Taking an interrupt moves the pc on to the handler before anything runs, so that needs
+        // checking for execute breakpoints too, before going on to the handler's first instruction.
+        loop {
+            if let Some(hit) = bps.check_execute(self, mem) {
+                return Err(StopReason::Breakpoint(hit.pc));
+            }
+
+            let ran = self.instructions_ran;
+            let watching = bps.has_watchpoints();
+            let pc = if watching || self.history.is_some() {
+                let (pc, mut accesses) = self.run_instruction_logged(mem, watching, bps.take_scratch(), true);
+                let mut hit = None;
+                if watching {
+                    // The instruction fetching itself isn't a read anyone's watching for.
+                    let len = if self.instructions_ran != ran { 1 + self.mode.operand_len() } else { 0 };
+                    accesses.retain(|a| a.write || a.address.wrapping_sub(pc) >= len);
+                    hit = bps.check_accesses(self, mem, pc, &accesses);
+                }
+                bps.put_scratch(accesses);
+                if let Some(hit) = hit {
+                    if self.instructions_ran != ran {
+                        bps.ran_instruction();
+                    }
+                    return Err(StopReason::Watchpoint(hit));
+                }
+                pc
+            } else {
+                self.execute_instruction(mem, true)
+            };
+
+            if self.instructions_ran != ran {
+                bps.ran_instruction();
+                return Ok(pc);
+            }
+            if !self.interrupted {
+                return Ok(pc);
+            }
+        }
+    }
+
+    // Start keeping a history of up to about max_bytes (see history.rs), dropping any there was.
+    // Every instruction from then on allocates a whole save_state() snapshot (a couple of hundred
+    // bytes) before it runs, so expect exec() to go noticeably slower while this is on.
+    pub fn record_history(&mut self, max_bytes: usize) {
+        self.history = Some(history::History::new(max_bytes));
+    }
+
+    pub fn stop_recording_history(&mut self) {
+        self.history = None;
+    }
+
+    pub fn history(&self) -> Option<&history::History> {
+        self.history.as_ref()
+    }
+
+    // execute_instruction() with a Recorder in the way, noting the writes (and, if 'reads' is set,
+    // the reads) in 'accesses', which is handed back. If there's a history, the state beforehand
+    // and the old value under each write go into it.
+    fn run_instruction_logged<T: Backplane>(&mut self, mem: &mut T, reads: bool, accesses: Vec<Access>, stop_after_interrupt: bool) -> (u16, Vec<Access>) {
+        let entry = self.history.as_ref().map(|_| {
+            let mut out = SnapshotWriter::new();
+            self.save_state(&mut out);
+            history::Entry::new(out.finish(), self.clockticks)
+        });
+
+        let ran = self.instructions_ran;
+        let mut recorder = Recorder { mem, reads, old_values: entry.is_some(), accesses: std::cell::RefCell::new(accesses) };
+        let pc = self.execute_instruction(&mut recorder, stop_after_interrupt);
+        let accesses = recorder.accesses.into_inner();
+
+        if let (Some(mut entry), Some(h)) = (entry, self.history.as_mut()) {
+            entry.pc = pc;
+            entry.writes = accesses.iter().filter(|a| a.write).map(|a| (a.address, a.value)).collect();
+            // Stalled on RDY or sitting in WAI: the history holds on to the state from before the
+            // stall, to go in with the instruction that ends it.
+            if self.instructions_ran != ran || !entry.writes.is_empty() {
+                h.push(entry);
+            } else {
+                h.stalled(entry);
+            }
+        }
+        (pc, accesses)
+    }
+
+    // Puts memory and the CPU back the way they were before the last 'count' entries in the
+    // history.
+    fn undo<T: Backplane>(&mut self, mem: &mut T, count: usize) {
+        let mut h = match self.history.take() {
+            Some(h) => h,
+            None => return,
+        };
+        let mut oldest = None;
+        for _ in 0..count {
+            let entry = match h.pop() {
+                Some(e) => e,
+                None => break,
+            };
+            for &(address, old) in entry.writes.iter().rev() {
+                mem.write(address, old);
+            }
+            oldest = Some(entry);
+        }
+        if let Some(entry) = oldest {
+            let snapshot = SnapshotReader::new(entry.state()).expect("history entry isn't a snapshot");
+            self.load_state(&snapshot).expect("history entry doesn't load");
+        }
+        self.history = Some(h);
+    }
+
+    // Undo the last instruction. False if there's no history to do it with.
+    pub fn step_back<T: Backplane>(&mut self, mem: &mut T) -> bool {
+        match self.history {
+            Some(ref h) if !h.is_empty() => {}
+            _ => return false,
+        }
+        self.undo(mem, 1);
+        true
+    }
+
+    // Go back at least 'cycles' cycles, to an instruction boundary, or as far as the history
+    // goes. Returns how many cycles it actually went back.
+    pub fn rewind_cycles<T: Backplane>(&mut self, mem: &mut T, cycles: u64) -> u64 {
+        let now = self.clockticks;
+        let count = match self.history {
+            Some(ref h) if cycles > 0 => h.back_to_cycle(now.saturating_sub(cycles)),
+            _ => 0,
+        };
+        self.undo(mem, count);
+        now - self.clockticks
+    }
+
+    // Go back to just before the most recent instruction that wrote to 'address', and return its
+    // pc. None (with nothing changed) if the history doesn't go back that far.
+    pub fn rewind_to_write<T: Backplane>(&mut self, mem: &mut T, address: u16) -> Option<u16> {
+        let count = self.history.as_ref()?.back_to_write(address)?;
+        self.undo(mem, count);
+        Some(self.pc.0)
+    }
+}
+
+// One bus access, as noted by a Recorder. For a write, 'value' is what was there before (if the
+// Recorder was asked to find out).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Access {
+    pub address: u16,
+    pub write: bool,
+    pub value: u8,
+}
+
+// Passes everything through to the real Backplane, noting the accesses the history and the
+// watchpoints need to know about. read() only gets &self, hence the RefCell.
+pub(crate) struct Recorder<'a, T: Backplane> {
+    pub mem: &'a mut T,
+    // Note reads as well as writes.
+    pub reads: bool,
+    // Read each address just before it's written, to keep the old value.
+    pub old_values: bool,
+    pub accesses: std::cell::RefCell<Vec<Access>>,
+}
+
+impl<'a, T: Backplane> Backplane for Recorder<'a, T> {
+    fn read(&self, address: u16) -> u8 {
+        let value = self.mem.read(address);
+        if self.reads {
+            self.accesses.borrow_mut().push(Access { address, write: false, value });
+        }
+        value
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        let old = if self.old_values { self.mem.read(address) } else { 0 };
+        self.accesses.get_mut().push(Access { address, write: true, value: old });
+        self.mem.write(address, value)
+    }
+
+    fn each_instr(&mut self, cpu: &mut CPU) -> Control {
+        self.mem.each_instr(cpu)
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        self.mem.tick(cycles)
+    }
+
+    fn ready(&mut self) -> bool {
+        self.mem.ready()
+    }
+
+    fn nmi(&mut self) -> bool {
+        self.mem.nmi()
+    }
+}
+
+// The opcode tables as data, generated by process_tables.py ("table", and "table 65c02") so they
+// can't disagree with run_one_op() and run_one_op_65c02().
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpInfo {
+    pub mnemonic: &'static str,
+    pub mode: AddrMode,
+    // Base cycle count, without page-crossing or branch penalties.
+    pub cycles: u8,
+}
+
+pub const NMOS_OPCODES: [OpInfo; 256] = [
+    /* 00 */ OpInfo { mnemonic: "brk",   mode: AddrMode::Implied,      cycles: 7 },
+    /* 01 */ OpInfo { mnemonic: "ora",   mode: AddrMode::IndirectX,    cycles: 6 },
+    /* 02 */ OpInfo { mnemonic: "jam",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 03 */ OpInfo { mnemonic: "slo",   mode: AddrMode::IndirectX,    cycles: 8 },
+    /* 04 */ OpInfo { mnemonic: "nop",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* 05 */ OpInfo { mnemonic: "ora",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* 06 */ OpInfo { mnemonic: "asl",   mode: AddrMode::ZeroPage,     cycles: 5 },
+    /* 07 */ OpInfo { mnemonic: "slo",   mode: AddrMode::ZeroPage,     cycles: 5 },
+    /* 08 */ OpInfo { mnemonic: "php",   mode: AddrMode::Implied,      cycles: 3 },
+    /* 09 */ OpInfo { mnemonic: "ora",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* 0A */ OpInfo { mnemonic: "asl",   mode: AddrMode::Accumulator,  cycles: 2 },
+    /* 0B */ OpInfo { mnemonic: "anc",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* 0C */ OpInfo { mnemonic: "nop",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* 0D */ OpInfo { mnemonic: "ora",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* 0E */ OpInfo { mnemonic: "asl",   mode: AddrMode::Absolute,     cycles: 6 },
+    /* 0F */ OpInfo { mnemonic: "slo",   mode: AddrMode::Absolute,     cycles: 6 },
+    /* 10 */ OpInfo { mnemonic: "bpl",   mode: AddrMode::Relative,     cycles: 2 },
+    /* 11 */ OpInfo { mnemonic: "ora",   mode: AddrMode::IndirectY,    cycles: 5 },
+    /* 12 */ OpInfo { mnemonic: "jam",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 13 */ OpInfo { mnemonic: "slo",   mode: AddrMode::IndirectY,    cycles: 8 },
+    /* 14 */ OpInfo { mnemonic: "nop",   mode: AddrMode::ZeroPageX,    cycles: 4 },
+    /* 15 */ OpInfo { mnemonic: "ora",   mode: AddrMode::ZeroPageX,    cycles: 4 },
+    /* 16 */ OpInfo { mnemonic: "asl",   mode: AddrMode::ZeroPageX,    cycles: 6 },
+    /* 17 */ OpInfo { mnemonic: "slo",   mode: AddrMode::ZeroPageX,    cycles: 6 },
+    /* 18 */ OpInfo { mnemonic: "clc",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 19 */ OpInfo { mnemonic: "ora",   mode: AddrMode::AbsoluteY,    cycles: 4 },
+    /* 1A */ OpInfo { mnemonic: "nop",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 1B */ OpInfo { mnemonic: "slo",   mode: AddrMode::AbsoluteY,    cycles: 7 },
+    /* 1C */ OpInfo { mnemonic: "nop",   mode: AddrMode::AbsoluteX,    cycles: 4 },
+    /* 1D */ OpInfo { mnemonic: "ora",   mode: AddrMode::AbsoluteX,    cycles: 4 },
+    /* 1E */ OpInfo { mnemonic: "asl",   mode: AddrMode::AbsoluteX,    cycles: 7 },
+    /* 1F */ OpInfo { mnemonic: "slo",   mode: AddrMode::AbsoluteX,    cycles: 7 },
+    /* 20 */ OpInfo { mnemonic: "jsr",   mode: AddrMode::Absolute,     cycles: 6 },
+    /* 21 */ OpInfo { mnemonic: "and",   mode: AddrMode::IndirectX,    cycles: 6 },
+    /* 22 */ OpInfo { mnemonic: "jam",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 23 */ OpInfo { mnemonic: "rla",   mode: AddrMode::IndirectX,    cycles: 8 },
+    /* 24 */ OpInfo { mnemonic: "bit",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* 25 */ OpInfo { mnemonic: "and",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* 26 */ OpInfo { mnemonic: "rol",   mode: AddrMode::ZeroPage,     cycles: 5 },
+    /* 27 */ OpInfo { mnemonic: "rla",   mode: AddrMode::ZeroPage,     cycles: 5 },
+    /* 28 */ OpInfo { mnemonic: "plp",   mode: AddrMode::Implied,      cycles: 4 },
+    /* 29 */ OpInfo { mnemonic: "and",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* 2A */ OpInfo { mnemonic: "rol",   mode: AddrMode::Accumulator,  cycles: 2 },
+    /* 2B */ OpInfo { mnemonic: "anc",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* 2C */ OpInfo { mnemonic: "bit",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* 2D */ OpInfo { mnemonic: "and",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* 2E */ OpInfo { mnemonic: "rol",   mode: AddrMode::Absolute,     cycles: 6 },
+    /* 2F */ OpInfo { mnemonic: "rla",   mode: AddrMode::Absolute,     cycles: 6 },
+    /* 30 */ OpInfo { mnemonic: "bmi",   mode: AddrMode::Relative,     cycles: 2 },
+    /* 31 */ OpInfo { mnemonic: "and",   mode: AddrMode::IndirectY,    cycles: 5 },
+    /* 32 */ OpInfo { mnemonic: "jam",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 33 */ OpInfo { mnemonic: "rla",   mode: AddrMode::IndirectY,    cycles: 8 },
+    /* 34 */ OpInfo { mnemonic: "nop",   mode: AddrMode::ZeroPageX,    cycles: 4 },
+    /* 35 */ OpInfo { mnemonic: "and",   mode: AddrMode::ZeroPageX,    cycles: 4 },
+    /* 36 */ OpInfo { mnemonic: "rol",   mode: AddrMode::ZeroPageX,    cycles: 6 },
+    /* 37 */ OpInfo { mnemonic: "rla",   mode: AddrMode::ZeroPageX,    cycles: 6 },
+    /* 38 */ OpInfo { mnemonic: "sec",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 39 */ OpInfo { mnemonic: "and",   mode: AddrMode::AbsoluteY,    cycles: 4 },
+    /* 3A */ OpInfo { mnemonic: "nop",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 3B */ OpInfo { mnemonic: "rla",   mode: AddrMode::AbsoluteY,    cycles: 7 },
+    /* 3C */ OpInfo { mnemonic: "nop",   mode: AddrMode::AbsoluteX,    cycles: 4 },
+    /* 3D */ OpInfo { mnemonic: "and",   mode: AddrMode::AbsoluteX,    cycles: 4 },
+    /* 3E */ OpInfo { mnemonic: "rol",   mode: AddrMode::AbsoluteX,    cycles: 7 },
+    /* 3F */ OpInfo { mnemonic: "rla",   mode: AddrMode::AbsoluteX,    cycles: 7 },
+    /* 40 */ OpInfo { mnemonic: "rti",   mode: AddrMode::Implied,      cycles: 6 },
+    /* 41 */ OpInfo { mnemonic: "eor",   mode: AddrMode::IndirectX,    cycles: 6 },
+    /* 42 */ OpInfo { mnemonic: "jam",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 43 */ OpInfo { mnemonic: "sre",   mode: AddrMode::IndirectX,    cycles: 8 },
+    /* 44 */ OpInfo { mnemonic: "nop",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* 45 */ OpInfo { mnemonic: "eor",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* 46 */ OpInfo { mnemonic: "lsr",   mode: AddrMode::ZeroPage,     cycles: 5 },
+    /* 47 */ OpInfo { mnemonic: "sre",   mode: AddrMode::ZeroPage,     cycles: 5 },
+    /* 48 */ OpInfo { mnemonic: "pha",   mode: AddrMode::Implied,      cycles: 3 },
+    /* 49 */ OpInfo { mnemonic: "eor",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* 4A */ OpInfo { mnemonic: "lsr",   mode: AddrMode::Accumulator,  cycles: 2 },
+    /* 4B */ OpInfo { mnemonic: "alr",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* 4C */ OpInfo { mnemonic: "jmp",   mode: AddrMode::Absolute,     cycles: 3 },
+    /* 4D */ OpInfo { mnemonic: "eor",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* 4E */ OpInfo { mnemonic: "lsr",   mode: AddrMode::Absolute,     cycles: 6 },
+    /* 4F */ OpInfo { mnemonic: "sre",   mode: AddrMode::Absolute,     cycles: 6 },
+    /* 50 */ OpInfo { mnemonic: "bvc",   mode: AddrMode::Relative,     cycles: 2 },
+    /* 51 */ OpInfo { mnemonic: "eor",   mode: AddrMode::IndirectY,    cycles: 5 },
+    /* 52 */ OpInfo { mnemonic: "jam",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 53 */ OpInfo { mnemonic: "sre",   mode: AddrMode::IndirectY,    cycles: 8 },
+    /* 54 */ OpInfo { mnemonic: "nop",   mode: AddrMode::ZeroPageX,    cycles: 4 },
+    /* 55 */ OpInfo { mnemonic: "eor",   mode: AddrMode::ZeroPageX,    cycles: 4 },
+    /* 56 */ OpInfo { mnemonic: "lsr",   mode: AddrMode::ZeroPageX,    cycles: 6 },
+    /* 57 */ OpInfo { mnemonic: "sre",   mode: AddrMode::ZeroPageX,    cycles: 6 },
+    /* 58 */ OpInfo { mnemonic: "cli",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 59 */ OpInfo { mnemonic: "eor",   mode: AddrMode::AbsoluteY,    cycles: 4 },
+    /* 5A */ OpInfo { mnemonic: "nop",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 5B */ OpInfo { mnemonic: "sre",   mode: AddrMode::AbsoluteY,    cycles: 7 },
+    /* 5C */ OpInfo { mnemonic: "nop",   mode: AddrMode::AbsoluteX,    cycles: 4 },
+    /* 5D */ OpInfo { mnemonic: "eor",   mode: AddrMode::AbsoluteX,    cycles: 4 },
+    /* 5E */ OpInfo { mnemonic: "lsr",   mode: AddrMode::AbsoluteX,    cycles: 7 },
+    /* 5F */ OpInfo { mnemonic: "sre",   mode: AddrMode::AbsoluteX,    cycles: 7 },
+    /* 60 */ OpInfo { mnemonic: "rts",   mode: AddrMode::Implied,      cycles: 6 },
+    /* 61 */ OpInfo { mnemonic: "adc",   mode: AddrMode::IndirectX,    cycles: 6 },
+    /* 62 */ OpInfo { mnemonic: "jam",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 63 */ OpInfo { mnemonic: "rra",   mode: AddrMode::IndirectX,    cycles: 8 },
+    /* 64 */ OpInfo { mnemonic: "nop",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* 65 */ OpInfo { mnemonic: "adc",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* 66 */ OpInfo { mnemonic: "ror",   mode: AddrMode::ZeroPage,     cycles: 5 },
+    /* 67 */ OpInfo { mnemonic: "rra",   mode: AddrMode::ZeroPage,     cycles: 5 },
+    /* 68 */ OpInfo { mnemonic: "pla",   mode: AddrMode::Implied,      cycles: 4 },
+    /* 69 */ OpInfo { mnemonic: "adc",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* 6A */ OpInfo { mnemonic: "ror",   mode: AddrMode::Accumulator,  cycles: 2 },
+    /* 6B */ OpInfo { mnemonic: "arr",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* 6C */ OpInfo { mnemonic: "jmp",   mode: AddrMode::Indirect,     cycles: 5 },
+    /* 6D */ OpInfo { mnemonic: "adc",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* 6E */ OpInfo { mnemonic: "ror",   mode: AddrMode::Absolute,     cycles: 6 },
+    /* 6F */ OpInfo { mnemonic: "rra",   mode: AddrMode::Absolute,     cycles: 6 },
+    /* 70 */ OpInfo { mnemonic: "bvs",   mode: AddrMode::Relative,     cycles: 2 },
+    /* 71 */ OpInfo { mnemonic: "adc",   mode: AddrMode::IndirectY,    cycles: 5 },
+    /* 72 */ OpInfo { mnemonic: "jam",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 73 */ OpInfo { mnemonic: "rra",   mode: AddrMode::IndirectY,    cycles: 8 },
+    /* 74 */ OpInfo { mnemonic: "nop",   mode: AddrMode::ZeroPageX,    cycles: 4 },
+    /* 75 */ OpInfo { mnemonic: "adc",   mode: AddrMode::ZeroPageX,    cycles: 4 },
+    /* 76 */ OpInfo { mnemonic: "ror",   mode: AddrMode::ZeroPageX,    cycles: 6 },
+    /* 77 */ OpInfo { mnemonic: "rra",   mode: AddrMode::ZeroPageX,    cycles: 6 },
+    /* 78 */ OpInfo { mnemonic: "sei",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 79 */ OpInfo { mnemonic: "adc",   mode: AddrMode::AbsoluteY,    cycles: 4 },
+    /* 7A */ OpInfo { mnemonic: "nop",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 7B */ OpInfo { mnemonic: "rra",   mode: AddrMode::AbsoluteY,    cycles: 7 },
+    /* 7C */ OpInfo { mnemonic: "nop",   mode: AddrMode::AbsoluteX,    cycles: 4 },
+    /* 7D */ OpInfo { mnemonic: "adc",   mode: AddrMode::AbsoluteX,    cycles: 4 },
+    /* 7E */ OpInfo { mnemonic: "ror",   mode: AddrMode::AbsoluteX,    cycles: 7 },
+    /* 7F */ OpInfo { mnemonic: "rra",   mode: AddrMode::AbsoluteX,    cycles: 7 },
+    /* 80 */ OpInfo { mnemonic: "nop",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* 81 */ OpInfo { mnemonic: "sta",   mode: AddrMode::IndirectX,    cycles: 6 },
+    /* 82 */ OpInfo { mnemonic: "nop",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* 83 */ OpInfo { mnemonic: "sax",   mode: AddrMode::IndirectX,    cycles: 6 },
+    /* 84 */ OpInfo { mnemonic: "sty",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* 85 */ OpInfo { mnemonic: "sta",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* 86 */ OpInfo { mnemonic: "stx",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* 87 */ OpInfo { mnemonic: "sax",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* 88 */ OpInfo { mnemonic: "dey",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 89 */ OpInfo { mnemonic: "nop",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* 8A */ OpInfo { mnemonic: "txa",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 8B */ OpInfo { mnemonic: "ane",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* 8C */ OpInfo { mnemonic: "sty",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* 8D */ OpInfo { mnemonic: "sta",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* 8E */ OpInfo { mnemonic: "stx",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* 8F */ OpInfo { mnemonic: "sax",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* 90 */ OpInfo { mnemonic: "bcc",   mode: AddrMode::Relative,     cycles: 2 },
+    /* 91 */ OpInfo { mnemonic: "sta",   mode: AddrMode::IndirectY,    cycles: 6 },
+    /* 92 */ OpInfo { mnemonic: "jam",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 93 */ OpInfo { mnemonic: "sha",   mode: AddrMode::IndirectY,    cycles: 6 },
+    /* 94 */ OpInfo { mnemonic: "sty",   mode: AddrMode::ZeroPageX,    cycles: 4 },
+    /* 95 */ OpInfo { mnemonic: "sta",   mode: AddrMode::ZeroPageX,    cycles: 4 },
+    /* 96 */ OpInfo { mnemonic: "stx",   mode: AddrMode::ZeroPageY,    cycles: 4 },
+    /* 97 */ OpInfo { mnemonic: "sax",   mode: AddrMode::ZeroPageY,    cycles: 4 },
+    /* 98 */ OpInfo { mnemonic: "tya",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 99 */ OpInfo { mnemonic: "sta",   mode: AddrMode::AbsoluteY,    cycles: 5 },
+    /* 9A */ OpInfo { mnemonic: "txs",   mode: AddrMode::Implied,      cycles: 2 },
+    /* 9B */ OpInfo { mnemonic: "tas",   mode: AddrMode::AbsoluteY,    cycles: 5 },
+    /* 9C */ OpInfo { mnemonic: "shy",   mode: AddrMode::AbsoluteX,    cycles: 5 },
+    /* 9D */ OpInfo { mnemonic: "sta",   mode: AddrMode::AbsoluteX,    cycles: 5 },
+    /* 9E */ OpInfo { mnemonic: "shx",   mode: AddrMode::AbsoluteY,    cycles: 5 },
+    /* 9F */ OpInfo { mnemonic: "sha",   mode: AddrMode::AbsoluteY,    cycles: 5 },
+    /* A0 */ OpInfo { mnemonic: "ldy",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* A1 */ OpInfo { mnemonic: "lda",   mode: AddrMode::IndirectX,    cycles: 6 },
+    /* A2 */ OpInfo { mnemonic: "ldx",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* A3 */ OpInfo { mnemonic: "lax",   mode: AddrMode::IndirectX,    cycles: 6 },
+    /* A4 */ OpInfo { mnemonic: "ldy",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* A5 */ OpInfo { mnemonic: "lda",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* A6 */ OpInfo { mnemonic: "ldx",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* A7 */ OpInfo { mnemonic: "lax",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* A8 */ OpInfo { mnemonic: "tay",   mode: AddrMode::Implied,      cycles: 2 },
+    /* A9 */ OpInfo { mnemonic: "lda",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* AA */ OpInfo { mnemonic: "tax",   mode: AddrMode::Implied,      cycles: 2 },
+    /* AB */ OpInfo { mnemonic: "lxa",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* AC */ OpInfo { mnemonic: "ldy",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* AD */ OpInfo { mnemonic: "lda",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* AE */ OpInfo { mnemonic: "ldx",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* AF */ OpInfo { mnemonic: "lax",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* B0 */ OpInfo { mnemonic: "bcs",   mode: AddrMode::Relative,     cycles: 2 },
+    /* B1 */ OpInfo { mnemonic: "lda",   mode: AddrMode::IndirectY,    cycles: 5 },
+    /* B2 */ OpInfo { mnemonic: "jam",   mode: AddrMode::Implied,      cycles: 2 },
+    /* B3 */ OpInfo { mnemonic: "lax",   mode: AddrMode::IndirectY,    cycles: 5 },
+    /* B4 */ OpInfo { mnemonic: "ldy",   mode: AddrMode::ZeroPageX,    cycles: 4 },
+    /* B5 */ OpInfo { mnemonic: "lda",   mode: AddrMode::ZeroPageX,    cycles: 4 },
+    /* B6 */ OpInfo { mnemonic: "ldx",   mode: AddrMode::ZeroPageY,    cycles: 4 },
+    /* B7 */ OpInfo { mnemonic: "lax",   mode: AddrMode::ZeroPageY,    cycles: 4 },
+    /* B8 */ OpInfo { mnemonic: "clv",   mode: AddrMode::Implied,      cycles: 2 },
+    /* B9 */ OpInfo { mnemonic: "lda",   mode: AddrMode::AbsoluteY,    cycles: 4 },
+    /* BA */ OpInfo { mnemonic: "tsx",   mode: AddrMode::Implied,      cycles: 2 },
+    /* BB */ OpInfo { mnemonic: "las",   mode: AddrMode::AbsoluteY,    cycles: 4 },
+    /* BC */ OpInfo { mnemonic: "ldy",   mode: AddrMode::AbsoluteX,    cycles: 4 },
+    /* BD */ OpInfo { mnemonic: "lda",   mode: AddrMode::AbsoluteX,    cycles: 4 },
+    /* BE */ OpInfo { mnemonic: "ldx",   mode: AddrMode::AbsoluteY,    cycles: 4 },
+    /* BF */ OpInfo { mnemonic: "lax",   mode: AddrMode::AbsoluteY,    cycles: 4 },
+    /* C0 */ OpInfo { mnemonic: "cpy",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* C1 */ OpInfo { mnemonic: "cmp",   mode: AddrMode::IndirectX,    cycles: 6 },
+    /* C2 */ OpInfo { mnemonic: "nop",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* C3 */ OpInfo { mnemonic: "dcp",   mode: AddrMode::IndirectX,    cycles: 8 },
+    /* C4 */ OpInfo { mnemonic: "cpy",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* C5 */ OpInfo { mnemonic: "cmp",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* C6 */ OpInfo { mnemonic: "dec",   mode: AddrMode::ZeroPage,     cycles: 5 },
+    /* C7 */ OpInfo { mnemonic: "dcp",   mode: AddrMode::ZeroPage,     cycles: 5 },
+    /* C8 */ OpInfo { mnemonic: "iny",   mode: AddrMode::Implied,      cycles: 2 },
+    /* C9 */ OpInfo { mnemonic: "cmp",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* CA */ OpInfo { mnemonic: "dex",   mode: AddrMode::Implied,      cycles: 2 },
+    /* CB */ OpInfo { mnemonic: "sbx",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* CC */ OpInfo { mnemonic: "cpy",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* CD */ OpInfo { mnemonic: "cmp",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* CE */ OpInfo { mnemonic: "dec",   mode: AddrMode::Absolute,     cycles: 6 },
+    /* CF */ OpInfo { mnemonic: "dcp",   mode: AddrMode::Absolute,     cycles: 6 },
+    /* D0 */ OpInfo { mnemonic: "bne",   mode: AddrMode::Relative,     cycles: 2 },
+    /* D1 */ OpInfo { mnemonic: "cmp",   mode: AddrMode::IndirectY,    cycles: 5 },
+    /* D2 */ OpInfo { mnemonic: "jam",   mode: AddrMode::Implied,      cycles: 2 },
+    /* D3 */ OpInfo { mnemonic: "dcp",   mode: AddrMode::IndirectY,    cycles: 8 },
+    /* D4 */ OpInfo { mnemonic: "nop",   mode: AddrMode::ZeroPageX,    cycles: 4 },
+    /* D5 */ OpInfo { mnemonic: "cmp",   mode: AddrMode::ZeroPageX,    cycles: 4 },
+    /* D6 */ OpInfo { mnemonic: "dec",   mode: AddrMode::ZeroPageX,    cycles: 6 },
+    /* D7 */ OpInfo { mnemonic: "dcp",   mode: AddrMode::ZeroPageX,    cycles: 6 },
+    /* D8 */ OpInfo { mnemonic: "cld",   mode: AddrMode::Implied,      cycles: 2 },
+    /* D9 */ OpInfo { mnemonic: "cmp",   mode: AddrMode::AbsoluteY,    cycles: 4 },
+    /* DA */ OpInfo { mnemonic: "nop",   mode: AddrMode::Implied,      cycles: 2 },
+    /* DB */ OpInfo { mnemonic: "dcp",   mode: AddrMode::AbsoluteY,    cycles: 7 },
+    /* DC */ OpInfo { mnemonic: "nop",   mode: AddrMode::AbsoluteX,    cycles: 4 },
+    /* DD */ OpInfo { mnemonic: "cmp",   mode: AddrMode::AbsoluteX,    cycles: 4 },
+    /* DE */ OpInfo { mnemonic: "dec",   mode: AddrMode::AbsoluteX,    cycles: 7 },
+    /* DF */ OpInfo { mnemonic: "dcp",   mode: AddrMode::AbsoluteX,    cycles: 7 },
+    /* E0 */ OpInfo { mnemonic: "cpx",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* E1 */ OpInfo { mnemonic: "sbc",   mode: AddrMode::IndirectX,    cycles: 6 },
+    /* E2 */ OpInfo { mnemonic: "nop",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* E3 */ OpInfo { mnemonic: "isb",   mode: AddrMode::IndirectX,    cycles: 8 },
+    /* E4 */ OpInfo { mnemonic: "cpx",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* E5 */ OpInfo { mnemonic: "sbc",   mode: AddrMode::ZeroPage,     cycles: 3 },
+    /* E6 */ OpInfo { mnemonic: "inc",   mode: AddrMode::ZeroPage,     cycles: 5 },
+    /* E7 */ OpInfo { mnemonic: "isb",   mode: AddrMode::ZeroPage,     cycles: 5 },
+    /* E8 */ OpInfo { mnemonic: "inx",   mode: AddrMode::Implied,      cycles: 2 },
+    /* E9 */ OpInfo { mnemonic: "sbc",   mode: AddrMode::Immediate,    cycles: 2 },
+    /* EA */ OpInfo { mnemonic: "nop",   mode: AddrMode::Implied,      cycles: 2 },
+    /* EB */ OpInfo { mnemonic: "usbc",  mode: AddrMode::Immediate,    cycles: 2 },
+    /* EC */ OpInfo { mnemonic: "cpx",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* ED */ OpInfo { mnemonic: "sbc",   mode: AddrMode::Absolute,     cycles: 4 },
+    /* EE */ OpInfo { mnemonic: "inc",   mode: AddrMode::Absolute,     cycles: 6 },
+    /* EF */ OpInfo { mnemonic: "isb",   mode: AddrMode::Absolute,     cycles: 6 },
+    /* F0 */ OpInfo { mnemonic: "beq",   mode: AddrMode::Relative,     cycles: 2 },
+    /* F1 */ OpInfo { mnemonic: "sbc",   mode: AddrMode::IndirectY,    cycles: 5 },
+    /* F2 */ OpInfo { mnemonic: "jam",   mode: AddrMode::Implied,      cycles: 2 },
+    /* F3 */ OpInfo { mnemonic: "isb",   mode: AddrMode::IndirectY,    cycles: 8 },
+    /* F4 */ OpInfo { mnemonic: "nop",   mode: AddrMode::ZeroPageX,    cycles: 4 },
+    /* F5 */ OpInfo { mnemonic: "sbc",   mode: AddrMode::ZeroPageX,    cycles: 4 },
+    /* F6 */ OpInfo { mnemonic: "inc",   mode: AddrMode::ZeroPageX,    cycles: 6 },
+    /* F7 */ OpInfo { mnemonic: "isb",   mode: AddrMode::ZeroPageX,    cycles: 6 },
+    /* F8 */ OpInfo { mnemonic: "sed",   mode: AddrMode::Implied,      cycles: 2 },
+    /* F9 */ OpInfo { mnemonic: "sbc",   mode: AddrMode::AbsoluteY,    cycles: 4 },
+    /* FA */ OpInfo { mnemonic: "nop",   mode: AddrMode::Implied,      cycles: 2 },
+    /* FB */ OpInfo { mnemonic: "isb",   mode: AddrMode::AbsoluteY,    cycles: 7 },
+    /* FC */ OpInfo { mnemonic: "nop",   mode: AddrMode::AbsoluteX,    cycles: 4 },
+    /* FD */ OpInfo { mnemonic: "sbc",   mode: AddrMode::AbsoluteX,    cycles: 4 },
+    /* FE */ OpInfo { mnemonic: "inc",   mode: AddrMode::AbsoluteX,    cycles: 7 },
+    /* FF */ OpInfo { mnemonic: "isb",   mode: AddrMode::AbsoluteX,    cycles: 7 },
+];
+
+pub const CMOS_OPCODES: [OpInfo; 256] = [
+    /* 00 */ OpInfo { mnemonic: "brk",  mode: AddrMode::Implied,            cycles: 7 },
+    /* 01 */ OpInfo { mnemonic: "ora",  mode: AddrMode::IndirectX,          cycles: 6 },
+    /* 02 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* 03 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 04 */ OpInfo { mnemonic: "tsb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* 05 */ OpInfo { mnemonic: "ora",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* 06 */ OpInfo { mnemonic: "asl",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* 07 */ OpInfo { mnemonic: "rmb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* 08 */ OpInfo { mnemonic: "php",  mode: AddrMode::Implied,            cycles: 3 },
+    /* 09 */ OpInfo { mnemonic: "ora",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* 0A */ OpInfo { mnemonic: "asl",  mode: AddrMode::Accumulator,        cycles: 2 },
+    /* 0B */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 0C */ OpInfo { mnemonic: "tsb",  mode: AddrMode::Absolute,           cycles: 6 },
+    /* 0D */ OpInfo { mnemonic: "ora",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* 0E */ OpInfo { mnemonic: "asl",  mode: AddrMode::Absolute,           cycles: 6 },
+    /* 0F */ OpInfo { mnemonic: "bbr",  mode: AddrMode::ZeroPageRelative,   cycles: 5 },
+    /* 10 */ OpInfo { mnemonic: "bpl",  mode: AddrMode::Relative,           cycles: 2 },
+    /* 11 */ OpInfo { mnemonic: "ora",  mode: AddrMode::IndirectY,          cycles: 5 },
+    /* 12 */ OpInfo { mnemonic: "ora",  mode: AddrMode::ZeroPageIndirect,   cycles: 5 },
+    /* 13 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 14 */ OpInfo { mnemonic: "trb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* 15 */ OpInfo { mnemonic: "ora",  mode: AddrMode::ZeroPageX,          cycles: 4 },
+    /* 16 */ OpInfo { mnemonic: "asl",  mode: AddrMode::ZeroPageX,          cycles: 6 },
+    /* 17 */ OpInfo { mnemonic: "rmb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* 18 */ OpInfo { mnemonic: "clc",  mode: AddrMode::Implied,            cycles: 2 },
+    /* 19 */ OpInfo { mnemonic: "ora",  mode: AddrMode::AbsoluteY,          cycles: 4 },
+    /* 1A */ OpInfo { mnemonic: "inc",  mode: AddrMode::Accumulator,        cycles: 2 },
+    /* 1B */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 1C */ OpInfo { mnemonic: "trb",  mode: AddrMode::Absolute,           cycles: 6 },
+    /* 1D */ OpInfo { mnemonic: "ora",  mode: AddrMode::AbsoluteX,          cycles: 4 },
+    /* 1E */ OpInfo { mnemonic: "asl",  mode: AddrMode::AbsoluteX,          cycles: 6 },
+    /* 1F */ OpInfo { mnemonic: "bbr",  mode: AddrMode::ZeroPageRelative,   cycles: 5 },
+    /* 20 */ OpInfo { mnemonic: "jsr",  mode: AddrMode::Absolute,           cycles: 6 },
+    /* 21 */ OpInfo { mnemonic: "and",  mode: AddrMode::IndirectX,          cycles: 6 },
+    /* 22 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* 23 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 24 */ OpInfo { mnemonic: "bit",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* 25 */ OpInfo { mnemonic: "and",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* 26 */ OpInfo { mnemonic: "rol",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* 27 */ OpInfo { mnemonic: "rmb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* 28 */ OpInfo { mnemonic: "plp",  mode: AddrMode::Implied,            cycles: 4 },
+    /* 29 */ OpInfo { mnemonic: "and",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* 2A */ OpInfo { mnemonic: "rol",  mode: AddrMode::Accumulator,        cycles: 2 },
+    /* 2B */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 2C */ OpInfo { mnemonic: "bit",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* 2D */ OpInfo { mnemonic: "and",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* 2E */ OpInfo { mnemonic: "rol",  mode: AddrMode::Absolute,           cycles: 6 },
+    /* 2F */ OpInfo { mnemonic: "bbr",  mode: AddrMode::ZeroPageRelative,   cycles: 5 },
+    /* 30 */ OpInfo { mnemonic: "bmi",  mode: AddrMode::Relative,           cycles: 2 },
+    /* 31 */ OpInfo { mnemonic: "and",  mode: AddrMode::IndirectY,          cycles: 5 },
+    /* 32 */ OpInfo { mnemonic: "and",  mode: AddrMode::ZeroPageIndirect,   cycles: 5 },
+    /* 33 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 34 */ OpInfo { mnemonic: "bit",  mode: AddrMode::ZeroPageX,          cycles: 4 },
+    /* 35 */ OpInfo { mnemonic: "and",  mode: AddrMode::ZeroPageX,          cycles: 4 },
+    /* 36 */ OpInfo { mnemonic: "rol",  mode: AddrMode::ZeroPageX,          cycles: 6 },
+    /* 37 */ OpInfo { mnemonic: "rmb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* 38 */ OpInfo { mnemonic: "sec",  mode: AddrMode::Implied,            cycles: 2 },
+    /* 39 */ OpInfo { mnemonic: "and",  mode: AddrMode::AbsoluteY,          cycles: 4 },
+    /* 3A */ OpInfo { mnemonic: "dec",  mode: AddrMode::Accumulator,        cycles: 2 },
+    /* 3B */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 3C */ OpInfo { mnemonic: "bit",  mode: AddrMode::AbsoluteX,          cycles: 4 },
+    /* 3D */ OpInfo { mnemonic: "and",  mode: AddrMode::AbsoluteX,          cycles: 4 },
+    /* 3E */ OpInfo { mnemonic: "rol",  mode: AddrMode::AbsoluteX,          cycles: 6 },
+    /* 3F */ OpInfo { mnemonic: "bbr",  mode: AddrMode::ZeroPageRelative,   cycles: 5 },
+    /* 40 */ OpInfo { mnemonic: "rti",  mode: AddrMode::Implied,            cycles: 6 },
+    /* 41 */ OpInfo { mnemonic: "eor",  mode: AddrMode::IndirectX,          cycles: 6 },
+    /* 42 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* 43 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 44 */ OpInfo { mnemonic: "nop",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* 45 */ OpInfo { mnemonic: "eor",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* 46 */ OpInfo { mnemonic: "lsr",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* 47 */ OpInfo { mnemonic: "rmb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* 48 */ OpInfo { mnemonic: "pha",  mode: AddrMode::Implied,            cycles: 3 },
+    /* 49 */ OpInfo { mnemonic: "eor",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* 4A */ OpInfo { mnemonic: "lsr",  mode: AddrMode::Accumulator,        cycles: 2 },
+    /* 4B */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 4C */ OpInfo { mnemonic: "jmp",  mode: AddrMode::Absolute,           cycles: 3 },
+    /* 4D */ OpInfo { mnemonic: "eor",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* 4E */ OpInfo { mnemonic: "lsr",  mode: AddrMode::Absolute,           cycles: 6 },
+    /* 4F */ OpInfo { mnemonic: "bbr",  mode: AddrMode::ZeroPageRelative,   cycles: 5 },
+    /* 50 */ OpInfo { mnemonic: "bvc",  mode: AddrMode::Relative,           cycles: 2 },
+    /* 51 */ OpInfo { mnemonic: "eor",  mode: AddrMode::IndirectY,          cycles: 5 },
+    /* 52 */ OpInfo { mnemonic: "eor",  mode: AddrMode::ZeroPageIndirect,   cycles: 5 },
+    /* 53 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 54 */ OpInfo { mnemonic: "nop",  mode: AddrMode::ZeroPageX,          cycles: 4 },
+    /* 55 */ OpInfo { mnemonic: "eor",  mode: AddrMode::ZeroPageX,          cycles: 4 },
+    /* 56 */ OpInfo { mnemonic: "lsr",  mode: AddrMode::ZeroPageX,          cycles: 6 },
+    /* 57 */ OpInfo { mnemonic: "rmb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* 58 */ OpInfo { mnemonic: "cli",  mode: AddrMode::Implied,            cycles: 2 },
+    /* 59 */ OpInfo { mnemonic: "eor",  mode: AddrMode::AbsoluteY,          cycles: 4 },
+    /* 5A */ OpInfo { mnemonic: "phy",  mode: AddrMode::Implied,            cycles: 3 },
+    /* 5B */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 5C */ OpInfo { mnemonic: "nop",  mode: AddrMode::Absolute,           cycles: 8 },
+    /* 5D */ OpInfo { mnemonic: "eor",  mode: AddrMode::AbsoluteX,          cycles: 4 },
+    /* 5E */ OpInfo { mnemonic: "lsr",  mode: AddrMode::AbsoluteX,          cycles: 6 },
+    /* 5F */ OpInfo { mnemonic: "bbr",  mode: AddrMode::ZeroPageRelative,   cycles: 5 },
+    /* 60 */ OpInfo { mnemonic: "rts",  mode: AddrMode::Implied,            cycles: 6 },
+    /* 61 */ OpInfo { mnemonic: "adc",  mode: AddrMode::IndirectX,          cycles: 6 },
+    /* 62 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* 63 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 64 */ OpInfo { mnemonic: "stz",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* 65 */ OpInfo { mnemonic: "adc",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* 66 */ OpInfo { mnemonic: "ror",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* 67 */ OpInfo { mnemonic: "rmb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* 68 */ OpInfo { mnemonic: "pla",  mode: AddrMode::Implied,            cycles: 4 },
+    /* 69 */ OpInfo { mnemonic: "adc",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* 6A */ OpInfo { mnemonic: "ror",  mode: AddrMode::Accumulator,        cycles: 2 },
+    /* 6B */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 6C */ OpInfo { mnemonic: "jmp",  mode: AddrMode::Indirect,           cycles: 6 },
+    /* 6D */ OpInfo { mnemonic: "adc",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* 6E */ OpInfo { mnemonic: "ror",  mode: AddrMode::Absolute,           cycles: 6 },
+    /* 6F */ OpInfo { mnemonic: "bbr",  mode: AddrMode::ZeroPageRelative,   cycles: 5 },
+    /* 70 */ OpInfo { mnemonic: "bvs",  mode: AddrMode::Relative,           cycles: 2 },
+    /* 71 */ OpInfo { mnemonic: "adc",  mode: AddrMode::IndirectY,          cycles: 5 },
+    /* 72 */ OpInfo { mnemonic: "adc",  mode: AddrMode::ZeroPageIndirect,   cycles: 5 },
+    /* 73 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 74 */ OpInfo { mnemonic: "stz",  mode: AddrMode::ZeroPageX,          cycles: 4 },
+    /* 75 */ OpInfo { mnemonic: "adc",  mode: AddrMode::ZeroPageX,          cycles: 4 },
+    /* 76 */ OpInfo { mnemonic: "ror",  mode: AddrMode::ZeroPageX,          cycles: 6 },
+    /* 77 */ OpInfo { mnemonic: "rmb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* 78 */ OpInfo { mnemonic: "sei",  mode: AddrMode::Implied,            cycles: 2 },
+    /* 79 */ OpInfo { mnemonic: "adc",  mode: AddrMode::AbsoluteY,          cycles: 4 },
+    /* 7A */ OpInfo { mnemonic: "ply",  mode: AddrMode::Implied,            cycles: 4 },
+    /* 7B */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 7C */ OpInfo { mnemonic: "jmp",  mode: AddrMode::AbsoluteXIndirect,  cycles: 6 },
+    /* 7D */ OpInfo { mnemonic: "adc",  mode: AddrMode::AbsoluteX,          cycles: 4 },
+    /* 7E */ OpInfo { mnemonic: "ror",  mode: AddrMode::AbsoluteX,          cycles: 6 },
+    /* 7F */ OpInfo { mnemonic: "bbr",  mode: AddrMode::ZeroPageRelative,   cycles: 5 },
+    /* 80 */ OpInfo { mnemonic: "bra",  mode: AddrMode::Relative,           cycles: 2 },
+    /* 81 */ OpInfo { mnemonic: "sta",  mode: AddrMode::IndirectX,          cycles: 6 },
+    /* 82 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* 83 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 84 */ OpInfo { mnemonic: "sty",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* 85 */ OpInfo { mnemonic: "sta",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* 86 */ OpInfo { mnemonic: "stx",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* 87 */ OpInfo { mnemonic: "smb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* 88 */ OpInfo { mnemonic: "dey",  mode: AddrMode::Implied,            cycles: 2 },
+    /* 89 */ OpInfo { mnemonic: "bit",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* 8A */ OpInfo { mnemonic: "txa",  mode: AddrMode::Implied,            cycles: 2 },
+    /* 8B */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 8C */ OpInfo { mnemonic: "sty",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* 8D */ OpInfo { mnemonic: "sta",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* 8E */ OpInfo { mnemonic: "stx",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* 8F */ OpInfo { mnemonic: "bbs",  mode: AddrMode::ZeroPageRelative,   cycles: 5 },
+    /* 90 */ OpInfo { mnemonic: "bcc",  mode: AddrMode::Relative,           cycles: 2 },
+    /* 91 */ OpInfo { mnemonic: "sta",  mode: AddrMode::IndirectY,          cycles: 6 },
+    /* 92 */ OpInfo { mnemonic: "sta",  mode: AddrMode::ZeroPageIndirect,   cycles: 5 },
+    /* 93 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 94 */ OpInfo { mnemonic: "sty",  mode: AddrMode::ZeroPageX,          cycles: 4 },
+    /* 95 */ OpInfo { mnemonic: "sta",  mode: AddrMode::ZeroPageX,          cycles: 4 },
+    /* 96 */ OpInfo { mnemonic: "stx",  mode: AddrMode::ZeroPageY,          cycles: 4 },
+    /* 97 */ OpInfo { mnemonic: "smb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* 98 */ OpInfo { mnemonic: "tya",  mode: AddrMode::Implied,            cycles: 2 },
+    /* 99 */ OpInfo { mnemonic: "sta",  mode: AddrMode::AbsoluteY,          cycles: 5 },
+    /* 9A */ OpInfo { mnemonic: "txs",  mode: AddrMode::Implied,            cycles: 2 },
+    /* 9B */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* 9C */ OpInfo { mnemonic: "stz",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* 9D */ OpInfo { mnemonic: "sta",  mode: AddrMode::AbsoluteX,          cycles: 5 },
+    /* 9E */ OpInfo { mnemonic: "stz",  mode: AddrMode::AbsoluteX,          cycles: 5 },
+    /* 9F */ OpInfo { mnemonic: "bbs",  mode: AddrMode::ZeroPageRelative,   cycles: 5 },
+    /* A0 */ OpInfo { mnemonic: "ldy",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* A1 */ OpInfo { mnemonic: "lda",  mode: AddrMode::IndirectX,          cycles: 6 },
+    /* A2 */ OpInfo { mnemonic: "ldx",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* A3 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* A4 */ OpInfo { mnemonic: "ldy",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* A5 */ OpInfo { mnemonic: "lda",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* A6 */ OpInfo { mnemonic: "ldx",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* A7 */ OpInfo { mnemonic: "smb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* A8 */ OpInfo { mnemonic: "tay",  mode: AddrMode::Implied,            cycles: 2 },
+    /* A9 */ OpInfo { mnemonic: "lda",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* AA */ OpInfo { mnemonic: "tax",  mode: AddrMode::Implied,            cycles: 2 },
+    /* AB */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* AC */ OpInfo { mnemonic: "ldy",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* AD */ OpInfo { mnemonic: "lda",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* AE */ OpInfo { mnemonic: "ldx",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* AF */ OpInfo { mnemonic: "bbs",  mode: AddrMode::ZeroPageRelative,   cycles: 5 },
+    /* B0 */ OpInfo { mnemonic: "bcs",  mode: AddrMode::Relative,           cycles: 2 },
+    /* B1 */ OpInfo { mnemonic: "lda",  mode: AddrMode::IndirectY,          cycles: 5 },
+    /* B2 */ OpInfo { mnemonic: "lda",  mode: AddrMode::ZeroPageIndirect,   cycles: 5 },
+    /* B3 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* B4 */ OpInfo { mnemonic: "ldy",  mode: AddrMode::ZeroPageX,          cycles: 4 },
+    /* B5 */ OpInfo { mnemonic: "lda",  mode: AddrMode::ZeroPageX,          cycles: 4 },
+    /* B6 */ OpInfo { mnemonic: "ldx",  mode: AddrMode::ZeroPageY,          cycles: 4 },
+    /* B7 */ OpInfo { mnemonic: "smb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* B8 */ OpInfo { mnemonic: "clv",  mode: AddrMode::Implied,            cycles: 2 },
+    /* B9 */ OpInfo { mnemonic: "lda",  mode: AddrMode::AbsoluteY,          cycles: 4 },
+    /* BA */ OpInfo { mnemonic: "tsx",  mode: AddrMode::Implied,            cycles: 2 },
+    /* BB */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* BC */ OpInfo { mnemonic: "ldy",  mode: AddrMode::AbsoluteX,          cycles: 4 },
+    /* BD */ OpInfo { mnemonic: "lda",  mode: AddrMode::AbsoluteX,          cycles: 4 },
+    /* BE */ OpInfo { mnemonic: "ldx",  mode: AddrMode::AbsoluteY,          cycles: 4 },
+    /* BF */ OpInfo { mnemonic: "bbs",  mode: AddrMode::ZeroPageRelative,   cycles: 5 },
+    /* C0 */ OpInfo { mnemonic: "cpy",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* C1 */ OpInfo { mnemonic: "cmp",  mode: AddrMode::IndirectX,          cycles: 6 },
+    /* C2 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* C3 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* C4 */ OpInfo { mnemonic: "cpy",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* C5 */ OpInfo { mnemonic: "cmp",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* C6 */ OpInfo { mnemonic: "dec",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* C7 */ OpInfo { mnemonic: "smb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* C8 */ OpInfo { mnemonic: "iny",  mode: AddrMode::Implied,            cycles: 2 },
+    /* C9 */ OpInfo { mnemonic: "cmp",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* CA */ OpInfo { mnemonic: "dex",  mode: AddrMode::Implied,            cycles: 2 },
+    /* CB */ OpInfo { mnemonic: "wai",  mode: AddrMode::Implied,            cycles: 3 },
+    /* CC */ OpInfo { mnemonic: "cpy",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* CD */ OpInfo { mnemonic: "cmp",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* CE */ OpInfo { mnemonic: "dec",  mode: AddrMode::Absolute,           cycles: 6 },
+    /* CF */ OpInfo { mnemonic: "bbs",  mode: AddrMode::ZeroPageRelative,   cycles: 5 },
+    /* D0 */ OpInfo { mnemonic: "bne",  mode: AddrMode::Relative,           cycles: 2 },
+    /* D1 */ OpInfo { mnemonic: "cmp",  mode: AddrMode::IndirectY,          cycles: 5 },
+    /* D2 */ OpInfo { mnemonic: "cmp",  mode: AddrMode::ZeroPageIndirect,   cycles: 5 },
+    /* D3 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* D4 */ OpInfo { mnemonic: "nop",  mode: AddrMode::ZeroPageX,          cycles: 4 },
+    /* D5 */ OpInfo { mnemonic: "cmp",  mode: AddrMode::ZeroPageX,          cycles: 4 },
+    /* D6 */ OpInfo { mnemonic: "dec",  mode: AddrMode::ZeroPageX,          cycles: 6 },
+    /* D7 */ OpInfo { mnemonic: "smb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* D8 */ OpInfo { mnemonic: "cld",  mode: AddrMode::Implied,            cycles: 2 },
+    /* D9 */ OpInfo { mnemonic: "cmp",  mode: AddrMode::AbsoluteY,          cycles: 4 },
+    /* DA */ OpInfo { mnemonic: "phx",  mode: AddrMode::Implied,            cycles: 3 },
+    /* DB */ OpInfo { mnemonic: "stp",  mode: AddrMode::Implied,            cycles: 3 },
+    /* DC */ OpInfo { mnemonic: "nop",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* DD */ OpInfo { mnemonic: "cmp",  mode: AddrMode::AbsoluteX,          cycles: 4 },
+    /* DE */ OpInfo { mnemonic: "dec",  mode: AddrMode::AbsoluteX,          cycles: 7 },
+    /* DF */ OpInfo { mnemonic: "bbs",  mode: AddrMode::ZeroPageRelative,   cycles: 5 },
+    /* E0 */ OpInfo { mnemonic: "cpx",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* E1 */ OpInfo { mnemonic: "sbc",  mode: AddrMode::IndirectX,          cycles: 6 },
+    /* E2 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* E3 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* E4 */ OpInfo { mnemonic: "cpx",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* E5 */ OpInfo { mnemonic: "sbc",  mode: AddrMode::ZeroPage,           cycles: 3 },
+    /* E6 */ OpInfo { mnemonic: "inc",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* E7 */ OpInfo { mnemonic: "smb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* E8 */ OpInfo { mnemonic: "inx",  mode: AddrMode::Implied,            cycles: 2 },
+    /* E9 */ OpInfo { mnemonic: "sbc",  mode: AddrMode::Immediate,          cycles: 2 },
+    /* EA */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 2 },
+    /* EB */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* EC */ OpInfo { mnemonic: "cpx",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* ED */ OpInfo { mnemonic: "sbc",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* EE */ OpInfo { mnemonic: "inc",  mode: AddrMode::Absolute,           cycles: 6 },
+    /* EF */ OpInfo { mnemonic: "bbs",  mode: AddrMode::ZeroPageRelative,   cycles: 5 },
+    /* F0 */ OpInfo { mnemonic: "beq",  mode: AddrMode::Relative,           cycles: 2 },
+    /* F1 */ OpInfo { mnemonic: "sbc",  mode: AddrMode::IndirectY,          cycles: 5 },
+    /* F2 */ OpInfo { mnemonic: "sbc",  mode: AddrMode::ZeroPageIndirect,   cycles: 5 },
+    /* F3 */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* F4 */ OpInfo { mnemonic: "nop",  mode: AddrMode::ZeroPageX,          cycles: 4 },
+    /* F5 */ OpInfo { mnemonic: "sbc",  mode: AddrMode::ZeroPageX,          cycles: 4 },
+    /* F6 */ OpInfo { mnemonic: "inc",  mode: AddrMode::ZeroPageX,          cycles: 6 },
+    /* F7 */ OpInfo { mnemonic: "smb",  mode: AddrMode::ZeroPage,           cycles: 5 },
+    /* F8 */ OpInfo { mnemonic: "sed",  mode: AddrMode::Implied,            cycles: 2 },
+    /* F9 */ OpInfo { mnemonic: "sbc",  mode: AddrMode::AbsoluteY,          cycles: 4 },
+    /* FA */ OpInfo { mnemonic: "plx",  mode: AddrMode::Implied,            cycles: 4 },
+    /* FB */ OpInfo { mnemonic: "nop",  mode: AddrMode::Implied,            cycles: 1 },
+    /* FC */ OpInfo { mnemonic: "nop",  mode: AddrMode::Absolute,           cycles: 4 },
+    /* FD */ OpInfo { mnemonic: "sbc",  mode: AddrMode::AbsoluteX,          cycles: 4 },
+    /* FE */ OpInfo { mnemonic: "inc",  mode: AddrMode::AbsoluteX,          cycles: 7 },
+    /* FF */ OpInfo { mnemonic: "bbs",  mode: AddrMode::ZeroPageRelative,   cycles: 5 },
+];
+
+
+// Save states.
+//
+// A snapshot is the magic bytes "F6502SNP", a little-endian u16 format version, and then any
+// number of chunks, each a four-byte tag, a little-endian u32 length, and that many bytes of
+// data. The CPU writes a "CPU " chunk; whatever's behind the Backplane can add chunks of its own
+// by implementing SaveState too (see CPU::save_snapshot()). Chunks nobody asks for are skipped
+// over when loading, so a machine can grow new devices without breaking old snapshots.
+
+const SNAPSHOT_MAGIC: &[u8; 8] = b"F6502SNP";
+pub const SNAPSHOT_VERSION: u16 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    // Doesn't start with the magic bytes, so it's not a snapshot at all.
+    BadMagic,
+    // Written by a newer version of this code than the one reading it.
+    UnsupportedVersion(u16),
+    // Ran out of data in the middle of something.
+    Truncated,
+    MissingChunk([u8; 4]),
+    // The chunk was there but didn't make sense.
+    BadChunk([u8; 4]),
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            SnapshotError::BadMagic => write!(f, "not a snapshot"),
+            SnapshotError::UnsupportedVersion(v) => write!(f, "unsupported snapshot version {}", v),
+            SnapshotError::Truncated => write!(f, "snapshot is truncated"),
+            SnapshotError::MissingChunk(t) => write!(f, "snapshot has no {:?} chunk", String::from_utf8_lossy(&t)),
+            SnapshotError::BadChunk(t) => write!(f, "snapshot's {:?} chunk is corrupt", String::from_utf8_lossy(&t)),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+pub struct SnapshotWriter {
+    buf: Vec<u8>,
+}
+
+impl Default for SnapshotWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SnapshotWriter {
+    pub fn new() -> SnapshotWriter {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(SNAPSHOT_MAGIC);
+        buf.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        SnapshotWriter { buf }
+    }
+
+    pub fn chunk(&mut self, tag: &[u8; 4], data: &[u8]) {
+        self.buf.extend_from_slice(tag);
+        self.buf.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        self.buf.extend_from_slice(data);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+pub struct SnapshotReader<'a> {
+    version: u16,
+    chunks: Vec<([u8; 4], &'a [u8])>,
+}
+
+impl<'a> SnapshotReader<'a> {
+    pub fn new(data: &'a [u8]) -> Result<SnapshotReader<'a>, SnapshotError> {
+        if data.len() < 10 || &data[0..8] != SNAPSHOT_MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = u16::from_le_bytes([data[8], data[9]]);
+        if version > SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let mut chunks = Vec::new();
+        let mut rest = &data[10..];
+        while !rest.is_empty() {
+            if rest.len() < 8 {
+                return Err(SnapshotError::Truncated);
+            }
+            let tag = [rest[0], rest[1], rest[2], rest[3]];
+            let len = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+            if rest.len() - 8 < len {
+                return Err(SnapshotError::Truncated);
+            }
+            chunks.push((tag, &rest[8..8 + len]));
+            rest = &rest[8 + len..];
+        }
+
+        Ok(SnapshotReader { version, chunks })
+    }
+
+    // The format version the snapshot was written with, for chunks whose layout has changed.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn chunk(&self, tag: &[u8; 4]) -> Option<&'a [u8]> {
+        self.chunks.iter().find(|c| &c.0 == tag).map(|c| c.1)
+    }
 }
 
+// Implement this on your Backplane (or anything else with state) to have it saved alongside the
+// CPU. Pick chunk tags that won't collide with "CPU ".
+pub trait SaveState {
+    fn save_state(&self, out: &mut SnapshotWriter);
+    fn load_state(&mut self, snapshot: &SnapshotReader) -> Result<(), SnapshotError>;
+}
 
+// For machines with nothing to save besides the CPU.
+impl SaveState for () {
+    fn save_state(&self, _out: &mut SnapshotWriter) {}
+    fn load_state(&mut self, _snapshot: &SnapshotReader) -> Result<(), SnapshotError> {
+        Ok(())
+    }
+}
+
+// Small helpers for packing fields into, and unpacking them from, a chunk.
+struct ChunkCursor<'a> {
+    tag: [u8; 4],
+    data: &'a [u8],
+}
+
+impl<'a> ChunkCursor<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.data.len() < n {
+            return Err(SnapshotError::BadChunk(self.tag));
+        }
+        let (head, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, SnapshotError> {
+        Ok(self.u8()? != 0)
+    }
+
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
 
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        let b = self.take(8)?;
+        Ok(u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+    }
+
+    // Version 1 snapshots had 32-bit cycle and instruction counters.
+    fn counter(&mut self, version: u16) -> Result<u64, SnapshotError> {
+        if version < 2 {
+            Ok(u64::from(self.u32()?))
+        } else {
+            self.u64()
+        }
+    }
+}
+
+const CPU_CHUNK: &[u8; 4] = b"CPU ";
+
+const ADDR_MODES: [AddrMode; 16] = [
+    AddrMode::Implied, AddrMode::Accumulator, AddrMode::Immediate, AddrMode::ZeroPage,
+    AddrMode::ZeroPageX, AddrMode::ZeroPageY, AddrMode::Relative, AddrMode::Absolute,
+    AddrMode::AbsoluteX, AddrMode::AbsoluteY, AddrMode::Indirect, AddrMode::IndirectX,
+    AddrMode::IndirectY, AddrMode::ZeroPageIndirect, AddrMode::AbsoluteXIndirect,
+    AddrMode::ZeroPageRelative,
+];
+
+const CPU_VARIANTS: [CpuVariant; 4] = [
+    CpuVariant::Nmos6502, CpuVariant::Nmos6502Illegal, CpuVariant::Ricoh2A03, CpuVariant::Wdc65C02,
+];
+
+impl SaveState for CPU {
+    // Everything goes in, hidden helpers included, so that a restored CPU carries on exactly
+    // where the saved one was, even part way through an exec() budget.
+    fn save_state(&self, out: &mut SnapshotWriter) {
+        let mut d: Vec<u8> = Vec::new();
+        d.extend_from_slice(&self.pc.0.to_le_bytes());
+        d.push(self.sp.0);
+        d.push(self.a.0);
+        d.push(self.x.0);
+        d.push(self.y.0);
+        d.push(self.status.0);
+
+        d.push(CPU_VARIANTS.iter().position(|v| *v == self.variant).unwrap() as u8);
+        d.extend_from_slice(&self.instructions_ran.to_le_bytes());
+        d.extend_from_slice(&self.clockticks.to_le_bytes());
+        d.extend_from_slice(&self.clockgoal.to_le_bytes());
+        d.push(self.addr_acc as u8);
+        d.extend_from_slice(&self.oldpc.to_le_bytes());
+        d.extend_from_slice(&self.ea.to_le_bytes());
+        d.extend_from_slice(&self.reladdr.to_le_bytes());
+        d.push(self.penaltyop);
+        d.push(self.penaltyaddr);
+        d.extend_from_slice(&self.value.to_le_bytes());
+        d.extend_from_slice(&self.result.to_le_bytes());
+        d.push(self.opcode);
+        d.push(self.oldstatus);
+        d.push(ADDR_MODES.iter().position(|m| *m == self.mode).unwrap() as u8);
+        d.push(self.branch_taken as u8);
+        d.push(self.waiting as u8);
+        d.push(self.cycle_accurate as u8);
+        d.push(self.bus_mode as u8);
+        d.push(self.latch);
+        d.extend_from_slice(&self.bus_cycles.to_le_bytes());
+        match self.jam {
+            None => d.extend_from_slice(&[0, 0, 0, 0]),
+            Some(JamReason::Jam { opcode, pc }) => {
+                d.push(1);
+                d.push(opcode);
+                d.extend_from_slice(&pc.to_le_bytes());
+            }
+            Some(JamReason::Stopped { pc }) => {
+                d.push(2);
+                d.push(0);
+                d.extend_from_slice(&pc.to_le_bytes());
+            }
+        }
+        d.push(self.do_callback as u8);
+        d.push(self.ane_magic);
+        d.push(self.lxa_magic);
+        d.extend_from_slice(&self.irq_lines.to_le_bytes());
+        d.push(self.nmi_line as u8);
+        d.push(self.nmi_pending as u8);
+        d.push(self.rdy_line as u8);
+        d.extend_from_slice(&self.rdy_hold.to_le_bytes());
+        d.push(self.so_line as u8);
+        d.extend_from_slice(&self.rdy_stalled_cycles.to_le_bytes());
+        d.push(match self.delayed_i {
+            None => 0,
+            Some(false) => 1,
+            Some(true) => 2,
+        });
+        d.push(self.detect_traps as u8);
+
+        out.chunk(CPU_CHUNK, &d);
+    }
+
+    fn load_state(&mut self, snapshot: &SnapshotReader) -> Result<(), SnapshotError> {
+        let data = snapshot.chunk(CPU_CHUNK).ok_or(SnapshotError::MissingChunk(*CPU_CHUNK))?;
+        let bad = SnapshotError::BadChunk(*CPU_CHUNK);
+        let mut c = ChunkCursor { tag: *CPU_CHUNK, data };
+        let version = snapshot.version();
+
+        // Read everything into a fresh CPU first, so a bad snapshot leaves this one alone.
+        let mut n = CPU::new(CpuVariant::Nmos6502);
+        n.pc = Wrapping(c.u16()?);
+        n.sp = Wrapping(c.u8()?);
+        n.a = Wrapping(c.u8()?);
+        n.x = Wrapping(c.u8()?);
+        n.y = Wrapping(c.u8()?);
+        n.status = Status(c.u8()?);
+
+        n.variant = *CPU_VARIANTS.get(c.u8()? as usize).ok_or(bad.clone())?;
+        n.instructions_ran = c.counter(version)?;
+        n.clockticks = c.counter(version)?;
+        n.clockgoal = c.counter(version)?;
+        n.addr_acc = c.bool()?;
+        n.oldpc = c.u16()?;
+        n.ea = c.u16()?;
+        n.reladdr = c.u16()?;
+        n.penaltyop = c.u8()?;
+        n.penaltyaddr = c.u8()?;
+        n.value = c.u16()?;
+        n.result = c.u16()?;
+        n.opcode = c.u8()?;
+        n.oldstatus = c.u8()?;
+        n.mode = *ADDR_MODES.get(c.u8()? as usize).ok_or(bad.clone())?;
+        n.branch_taken = c.bool()?;
+        n.waiting = c.bool()?;
+        n.cycle_accurate = c.bool()?;
+        n.bus_mode = c.bool()?;
+        n.latch = c.u8()?;
+        n.bus_cycles = c.u32()?;
+        let (kind, opcode, pc) = (c.u8()?, c.u8()?, c.u16()?);
+        n.jam = match kind {
+            0 => None,
+            1 => Some(JamReason::Jam { opcode, pc }),
+            2 => Some(JamReason::Stopped { pc }),
+            _ => return Err(bad),
+        };
+        n.do_callback = c.bool()?;
+        n.ane_magic = c.u8()?;
+        n.lxa_magic = c.u8()?;
+        n.irq_lines = c.u32()?;
+        n.nmi_line = c.bool()?;
+        n.nmi_pending = c.bool()?;
+        n.rdy_line = c.bool()?;
+        n.rdy_hold = c.u32()?;
+        n.so_line = c.bool()?;
+        n.rdy_stalled_cycles = c.counter(version)?;
+        n.delayed_i = match c.u8()? {
+            0 => None,
+            1 => Some(false),
+            2 => Some(true),
+            _ => return Err(bad),
+        };
+        if version >= 3 {
+            n.detect_traps = c.bool()?;
+        }
+
+        // The trace output and the breakpoints belong to the host, not to the machine being
+        // restored.
+        n.tracer = self.tracer.take();
+        n.breakpoints = std::mem::take(&mut self.breakpoints);
+        // The history is kept going, but what's in it no longer leads up to here.
+        n.history = self.history.take().map(|mut h| {
+            h.clear();
+            h
+        });
+        *self = n;
+        Ok(())
+    }
+}
+
+impl CPU {
+    // Snapshot the CPU together with the rest of the machine. If there is no rest of the machine
+    // worth saving, CPU::save_state() can be used by itself.
+    pub fn save_snapshot<T: SaveState>(&self, machine: &T) -> Vec<u8> {
+        let mut out = SnapshotWriter::new();
+        self.save_state(&mut out);
+        machine.save_state(&mut out);
+        out.finish()
+    }
+
+    pub fn load_snapshot<T: SaveState>(&mut self, machine: &mut T, data: &[u8]) -> Result<(), SnapshotError> {
+        let snapshot = SnapshotReader::new(data)?;
+        self.load_state(&snapshot)?;
+        machine.load_state(&snapshot)
+    }
+}
 
 
 //static void (*addrtable[256])();
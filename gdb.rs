@@ -0,0 +1,378 @@
+// A stub for GDB's remote serial protocol, so a debugger front end can drive the CPU over a TCP
+// socket (or any other byte stream). gdbserver.rs wraps it up as a program.
+//
+// GDB has no 6502 support of its own, so the registers are described with a target description
+// (target.xml), in the order the 'g' packet uses:
+//
+//     a, x, y, p, sp    8 bits each
+//     pc                16 bits, little-endian
+//
+// Supported: ?, g, G, p, P, m, M, s, c, Z0/z0 and Z1/z1 (both are breakpoints on the pc), Z2/z2,
+// Z3/z3 and Z4/z4 (write, read and access watchpoints over a range), D, k, qSupported,
+// qXfer:features:read, QStartNoAckMode, and a break (Ctrl-C) from the client while running, if
+// the connection can tell us about it. Everything else gets the empty "not supported" reply.
+//
+// Breakpoints and watchpoints go into the CPU's own (see breakpoints.rs), and running is done with
+// exec(), so they behave just as they do there, and Backplane::each_instr() can stop things too.
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+use super::breakpoints::WatchKind;
+use super::{Backplane, StopReason, CPU};
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.gnu.gdb.m6502.core">
+    <reg name="a" bitsize="8" type="uint8"/>
+    <reg name="x" bitsize="8" type="uint8"/>
+    <reg name="y" bitsize="8" type="uint8"/>
+    <reg name="p" bitsize="8" type="uint8"/>
+    <reg name="sp" bitsize="8" type="uint8"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+  </feature>
+</target>
+"#;
+
+// Signal numbers for stop replies.
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+
+// The most bytes an 'm' packet gets back, so the reply fits in PacketSize.
+const MAX_MEMORY_READ: u32 = 0x7F0;
+
+// How many cycles "c" runs between checks for a break from the client.
+const POLL_INTERVAL: u64 = 16384;
+
+// What the stub talks to GDB over.
+pub trait Connection: Read + Write {
+    // Has the client sent a break (a lone 0x03 byte) while the CPU is running? This must not
+    // block; the default is never to notice, which leaves a running CPU to a breakpoint or a
+    // watchpoint to stop it.
+    fn interrupt_pending(&mut self) -> bool {
+        false
+    }
+}
+
+// This flips the socket to non-blocking and back each time it's asked, which resume() does every
+// POLL_INTERVAL cycles. That's deliberate: it's a couple of system calls every few milliseconds
+// of emulated time, which costs less than a reader thread and the locking around the stream
+// that would take, and the socket is only ever non-blocking for the length of one peek.
+impl Connection for TcpStream {
+    fn interrupt_pending(&mut self) -> bool {
+        let mut byte = [0];
+        if self.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let waiting = matches!(self.peek(&mut byte), Ok(1));
+        let _ = self.set_nonblocking(false);
+        if waiting && byte[0] == 0x03 {
+            return self.read_exact(&mut byte).is_ok();
+        }
+        false
+    }
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|d| d as u8)
+}
+
+fn parse_hex(s: &str) -> Option<u32> {
+    u32::from_str_radix(s, 16).ok()
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    let pairs = s.as_bytes().chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    pairs.map(|pair| Some(hex_digit(pair[0])? << 4 | hex_digit(pair[1])?)).collect()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// "addr,len" as in the m and M packets.
+fn parse_range(s: &str) -> Option<(u16, u32)> {
+    let mut parts = s.splitn(2, ',');
+    let address = parse_hex(parts.next()?)?;
+    let len = parse_hex(parts.next()?)?;
+    if address > 0xFFFF {
+        return None;
+    }
+    Some((address as u16, len))
+}
+
+// How much of 'len' bytes from 'address' there is before the end of memory.
+fn in_memory(address: u16, len: u32) -> u32 {
+    len.min(0x10000 - address as u32)
+}
+
+pub struct GdbStub<C: Connection> {
+    conn: C,
+    // Whether packets are being acknowledged with '+'; GDB turns this off with QStartNoAckMode.
+    ack: bool,
+}
+
+impl<C: Connection> GdbStub<C> {
+    pub fn new(conn: C) -> GdbStub<C> {
+        GdbStub { conn, ack: true }
+    }
+
+    fn read_byte(&mut self) -> io::Result<u8> {
+        let mut byte = [0];
+        self.conn.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    // The next packet's contents, checksum checked (and acknowledged, unless that's been turned
+    // off). A break byte between packets comes back as "\x03".
+    fn read_packet(&mut self) -> io::Result<String> {
+        loop {
+            match self.read_byte()? {
+                b'$' => {}
+                0x03 => return Ok("\x03".to_string()),
+                _ => continue,
+            }
+            let mut data = Vec::new();
+            loop {
+                match self.read_byte()? {
+                    b'#' => break,
+                    c => data.push(c),
+                }
+            }
+            let (hi, lo) = (self.read_byte()?, self.read_byte()?);
+            let sum = data.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+            let good = hex_digit(hi).zip(hex_digit(lo)).map(|(hi, lo)| hi << 4 | lo) == Some(sum);
+            if self.ack {
+                self.conn.write_all(if good { b"+" } else { b"-" })?;
+                self.conn.flush()?;
+            }
+            if good {
+                return Ok(String::from_utf8_lossy(&data).into_owned());
+            }
+        }
+    }
+
+    fn send_packet(&mut self, data: &str) -> io::Result<()> {
+        let sum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        let packet = format!("${}#{:02x}", data, sum);
+        loop {
+            self.conn.write_all(packet.as_bytes())?;
+            self.conn.flush()?;
+            if !self.ack {
+                return Ok(());
+            }
+            // Anything other than a '-' counts as an acknowledgement.
+            if self.read_byte()? != b'-' {
+                return Ok(());
+            }
+        }
+    }
+
+    fn registers(cpu: &CPU) -> [u8; 7] {
+        let pc = cpu.pc.0;
+        [cpu.a.0, cpu.x.0, cpu.y.0, cpu.status.bits(), cpu.sp.0, pc as u8, (pc >> 8) as u8]
+    }
+
+    fn set_register(cpu: &mut CPU, n: u32, bytes: &[u8]) -> bool {
+        match (n, bytes) {
+            (0, &[v]) => cpu.a.0 = v,
+            (1, &[v]) => cpu.x.0 = v,
+            (2, &[v]) => cpu.y.0 = v,
+            (3, &[v]) => cpu.status = super::Status::from_bits(v),
+            (4, &[v]) => cpu.sp.0 = v,
+            (5, &[lo, hi]) => cpu.pc.0 = lo as u16 | (hi as u16) << 8,
+            _ => return false,
+        }
+        true
+    }
+
+    // The stop reply for why exec() stopped, or None if it just ran out of cycles.
+    fn stop_reply(cpu: &CPU, reason: StopReason) -> Option<String> {
+        match reason {
+            StopReason::BudgetExhausted => None,
+            StopReason::Jammed => Some(format!("S{:02x}", SIGILL)),
+            StopReason::Watchpoint(hit) => {
+                let kind = match cpu.breakpoints.get(hit.id).map(|b| b.kind) {
+                    Some(WatchKind::Write) => "watch",
+                    Some(WatchKind::Read) => "rwatch",
+                    _ => "awatch",
+                };
+                Some(format!("T{:02x}{}:{:04x};", SIGTRAP, kind, hit.address))
+            }
+            StopReason::Breakpoint(_) | StopReason::TrapDetected(_) | StopReason::HostRequested(_) => {
+                Some(format!("S{:02x}", SIGTRAP))
+            }
+        }
+    }
+
+    fn resume<T: Backplane>(&mut self, cpu: &mut CPU, mem: &mut T, single: bool) -> String {
+        if single {
+            // A goal one cycle away runs exactly one instruction (or a cycle of being stalled).
+            let target = cpu.clockticks + 1;
+            let reason = cpu.exec_until(mem, target);
+            return Self::stop_reply(cpu, reason).unwrap_or_else(|| format!("S{:02x}", SIGTRAP));
+        }
+        loop {
+            let target = cpu.clockticks + POLL_INTERVAL;
+            let reason = cpu.exec_until(mem, target);
+            if let Some(reply) = Self::stop_reply(cpu, reason) {
+                return reply;
+            }
+            if self.conn.interrupt_pending() {
+                return format!("S{:02x}", SIGINT);
+            }
+        }
+    }
+
+    // Z and z packets: "type,addr,kind". These go in the CPU's breakpoints; removing one takes out
+    // a breakpoint of the same kind over the same range, whoever put it there.
+    fn breakpoint(&mut self, cpu: &mut CPU, packet: &str, insert: bool) -> &'static str {
+        let fields: Vec<&str> = packet[1..].split(',').collect();
+        // (The range is inclusive, and stops at the end of memory.)
+        let (kind, address, last) = match fields.as_slice() {
+            [kind, address, len] => match (parse_hex(address), parse_hex(len)) {
+                (Some(address), Some(len)) if address <= 0xFFFF => {
+                    (*kind, address as u16, (address + len.clamp(1, 0x10000 - address) - 1) as u16)
+                }
+                _ => return "E01",
+            },
+            _ => return "E01",
+        };
+        let (kind, to) = match kind {
+            "0" | "1" => (WatchKind::Execute, address),
+            "2" => (WatchKind::Write, last),
+            "3" => (WatchKind::Read, last),
+            "4" => (WatchKind::ReadWrite, last),
+            _ => return "",
+        };
+        let existing = cpu.breakpoints.iter().find(|(_, b)| b.kind == kind && b.from == address && b.to == to).map(|(id, _)| id);
+        match (existing, insert) {
+            (None, true) => {
+                cpu.breakpoints.watch(kind, address, to);
+            }
+            (Some(id), false) => {
+                cpu.breakpoints.remove(id);
+            }
+            _ => {}
+        }
+        "OK"
+    }
+
+    fn read_xfer(&self, args: &str) -> String {
+        // "target.xml:offset,length"
+        let range = args.strip_prefix("target.xml:").and_then(|r| {
+            let mut parts = r.splitn(2, ',');
+            Some((parse_hex(parts.next()?)? as usize, parse_hex(parts.next()?)? as usize))
+        });
+        match range {
+            Some((offset, length)) => {
+                let start = offset.min(TARGET_XML.len());
+                let end = (start + length).min(TARGET_XML.len());
+                let more = if end < TARGET_XML.len() { 'm' } else { 'l' };
+                format!("{}{}", more, &TARGET_XML[start..end])
+            }
+            None => "E00".to_string(),
+        }
+    }
+
+    // Handles packets until the client detaches, kills the session or goes away. The CPU and
+    // memory are left as they were at that point.
+    pub fn serve<T: Backplane>(&mut self, cpu: &mut CPU, mem: &mut T) -> io::Result<()> {
+        loop {
+            let packet = match self.read_packet() {
+                Ok(p) => p,
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(e),
+            };
+            let reply = match packet.as_bytes().first() {
+                Some(0x03) => format!("S{:02x}", SIGINT),
+                Some(b'?') => format!("S{:02x}", SIGTRAP),
+                Some(b'g') => encode_hex(&Self::registers(cpu)),
+                Some(b'G') => match decode_hex(&packet[1..]) {
+                    Some(ref b) if b.len() == 7 => {
+                        for (n, r) in [(0, 0..1), (1, 1..2), (2, 2..3), (3, 3..4), (4, 4..5), (5, 5..7)].iter().cloned() {
+                            Self::set_register(cpu, n, &b[r]);
+                        }
+                        "OK".to_string()
+                    }
+                    _ => "E01".to_string(),
+                },
+                Some(b'p') => match parse_hex(&packet[1..]) {
+                    Some(n) if n < 5 => encode_hex(&Self::registers(cpu)[n as usize..n as usize + 1]),
+                    Some(5) => encode_hex(&Self::registers(cpu)[5..7]),
+                    _ => "E01".to_string(),
+                },
+                Some(b'P') => {
+                    let mut parts = packet[1..].splitn(2, '=');
+                    let n = parts.next().and_then(parse_hex);
+                    let value = parts.next().and_then(decode_hex);
+                    match (n, value) {
+                        (Some(n), Some(value)) if Self::set_register(cpu, n, &value) => "OK".to_string(),
+                        _ => "E01".to_string(),
+                    }
+                }
+                // Reads get cut down to what fits in a reply packet as well.
+                Some(b'm') => match parse_range(&packet[1..]) {
+                    Some((address, len)) => {
+                        let len = in_memory(address, len).min(MAX_MEMORY_READ);
+                        let bytes: Vec<u8> = (0..len).map(|i| mem.read(address.wrapping_add(i as u16))).collect();
+                        encode_hex(&bytes)
+                    }
+                    None => "E01".to_string(),
+                },
+                Some(b'M') => {
+                    let mut parts = packet[1..].splitn(2, ':');
+                    let range = parts.next().and_then(parse_range);
+                    let data = parts.next().and_then(decode_hex);
+                    match (range, data) {
+                        (Some((address, len)), Some(ref data)) if data.len() == len as usize => {
+                            for (i, &b) in data[..in_memory(address, len) as usize].iter().enumerate() {
+                                mem.write(address.wrapping_add(i as u16), b);
+                            }
+                            "OK".to_string()
+                        }
+                        _ => "E01".to_string(),
+                    }
+                }
+                Some(&c) if c == b's' || c == b'c' => {
+                    if packet.len() > 1 {
+                        match parse_hex(&packet[1..]) {
+                            Some(address) if address <= 0xFFFF => cpu.pc.0 = address as u16,
+                            _ => {
+                                self.send_packet("E01")?;
+                                continue;
+                            }
+                        }
+                    }
+                    self.resume(cpu, mem, c == b's')
+                }
+                Some(b'Z') => self.breakpoint(cpu, &packet, true).to_string(),
+                Some(b'z') => self.breakpoint(cpu, &packet, false).to_string(),
+                Some(b'D') => {
+                    self.send_packet("OK")?;
+                    return Ok(());
+                }
+                Some(b'k') => return Ok(()),
+                Some(b'H') => "OK".to_string(),
+                _ if packet.starts_with("qSupported") => "PacketSize=1000;qXfer:features:read+;QStartNoAckMode+".to_string(),
+                _ if packet.starts_with("qXfer:features:read:") => self.read_xfer(&packet["qXfer:features:read:".len()..]),
+                _ if packet == "QStartNoAckMode" => {
+                    self.send_packet("OK")?;
+                    self.ack = false;
+                    continue;
+                }
+                _ if packet == "qAttached" => "1".to_string(),
+                _ if packet == "qC" => "QC1".to_string(),
+                _ if packet == "qfThreadInfo" => "m1".to_string(),
+                _ if packet == "qsThreadInfo" => "l".to_string(),
+                _ => String::new(),
+            };
+            self.send_packet(&reply)?;
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use fake6502::gdb::{Connection, GdbStub};
+use fake6502::{Backplane, Control, CpuVariant, CPU};
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{self, Stdin, Stdout};
+use std::net::TcpListener;
+use std::process::exit;
+
+// Serves a CPU with 64K of flat RAM to GDB (or anything else that speaks its remote protocol):
+//
+//     gdbserver [--variant nmos|illegal|2a03|65c02] [--port N | --stdio] [image[@address]]...
+//
+// Images load at 0 unless given an address (hex). The CPU is reset before the first connection,
+// so put a reset vector at $FFFC. With --port (1234 by default) it listens on 127.0.0.1 and takes
+// one connection after another; from GDB, "target remote :1234". With --stdio it talks over stdin
+// and stdout for a single session, for "target remote | gdbserver --stdio ...".
+
+struct FlatSystem {
+    mem: Vec<u8>,
+}
+
+impl Backplane for FlatSystem {
+    fn read(&self, address: u16) -> u8 {
+        self.mem[address as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.mem[address as usize] = value;
+    }
+
+    fn each_instr(&mut self, _cpu: &mut CPU) -> Control {
+        Control::Continue
+    }
+}
+
+// Stdin and stdout together. There's no way to peek at stdin without blocking, so a break from
+// the client won't be noticed while the CPU is running.
+struct Stdio {
+    stdin: Stdin,
+    stdout: Stdout,
+}
+
+impl Read for Stdio {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stdin.read(buf)
+    }
+}
+
+impl Write for Stdio {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stdout.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()
+    }
+}
+
+impl Connection for Stdio {}
+
+fn usage() -> ! {
+    eprintln!("usage: gdbserver [--variant nmos|illegal|2a03|65c02] [--port N | --stdio] [image[@address]]...");
+    exit(2);
+}
+
+fn main() {
+    let mut variant = CpuVariant::Nmos6502;
+    let mut port = 1234;
+    let mut stdio = false;
+    let mut sys = FlatSystem { mem: vec![0; 0x10000] };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--variant" => {
+                variant = match args.next().as_deref() {
+                    Some("nmos") => CpuVariant::Nmos6502,
+                    Some("illegal") => CpuVariant::Nmos6502Illegal,
+                    Some("2a03") => CpuVariant::Ricoh2A03,
+                    Some("65c02") => CpuVariant::Wdc65C02,
+                    _ => usage(),
+                }
+            }
+            "--port" => port = args.next().and_then(|p| p.parse().ok()).unwrap_or_else(|| usage()),
+            "--stdio" => stdio = true,
+            _ if arg.starts_with("--") => usage(),
+            _ => {
+                let mut parts = arg.splitn(2, '@');
+                let path = parts.next().unwrap();
+                let address = match parts.next().map(|a| u16::from_str_radix(a.trim_start_matches('$'), 16)) {
+                    None => 0,
+                    Some(Ok(a)) => a as usize,
+                    Some(Err(_)) => usage(),
+                };
+                let mut data = Vec::new();
+                if let Err(e) = File::open(path).and_then(|mut f| f.read_to_end(&mut data)) {
+                    eprintln!("{}: {}", path, e);
+                    exit(1);
+                }
+                let len = data.len().min(0x10000 - address);
+                sys.mem[address..address + len].copy_from_slice(&data[..len]);
+            }
+        }
+    }
+
+    let mut cpu = CPU::new(variant);
+    cpu.reset(&sys);
+
+    if stdio {
+        let mut stub = GdbStub::new(Stdio { stdin: io::stdin(), stdout: io::stdout() });
+        if let Err(e) = stub.serve(&mut cpu, &mut sys) {
+            eprintln!("gdbserver: {}", e);
+            exit(1);
+        }
+        return;
+    }
+
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("gdbserver: can't listen on port {}: {}", port, e);
+            exit(1);
+        }
+    };
+    eprintln!("listening on 127.0.0.1:{}", port);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("gdbserver: {}", e);
+                continue;
+            }
+        };
+        let _ = stream.set_nodelay(true);
+        // The machine carries on from where the last session left it.
+        let mut stub = GdbStub::new(stream);
+        if let Err(e) = stub.serve(&mut cpu, &mut sys) {
+            eprintln!("gdbserver: {}", e);
+        }
+        eprintln!("session over");
+    }
+}
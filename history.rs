@@ -0,0 +1,142 @@
+// A record of recent execution that can be played backwards, for working out how memory came to
+// hold what it does. Turn it on with CPU::record_history(); from then on, every instruction the
+// CPU runs (through exec() or step()) leaves behind
+//
+//   - the CPU's state before it, in the save-state format (so hidden state like the interrupt
+//     latches and the cycle counters comes back too), and
+//   - the old value of every byte it wrote through Backplane::write().
+//
+// CPU::step_back(), CPU::rewind_cycles() and CPU::rewind_to_write() then undo instructions
+// newest first. The oldest entries are dropped to stay within the byte budget.
+//
+// Only memory comes back, and only through Backplane::read() and write(): the old value is read
+// just before each write, and written back when rewinding. Devices where either of those does
+// something, or that keep state of their own, won't be wound back with it; SaveState is the way
+// to capture those.
+
+use std::collections::VecDeque;
+
+// One instruction's worth of history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    // Where the instruction was fetched from (after taking an interrupt, if one came first).
+    pub pc: u16,
+    // CPU::clockticks before it ran.
+    pub clockticks: u64,
+    // (address, old value) for each write, in the order they happened.
+    pub writes: Vec<(u16, u8)>,
+    state: Vec<u8>,
+}
+
+impl Entry {
+    pub(crate) fn new(state: Vec<u8>, clockticks: u64) -> Entry {
+        Entry { pc: 0, clockticks, writes: Vec::new(), state }
+    }
+
+    pub(crate) fn state(&self) -> &[u8] {
+        &self.state
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of::<Entry>() + self.state.len() + self.writes.len() * std::mem::size_of::<(u16, u8)>()
+    }
+
+    fn wrote(&self, address: u16) -> bool {
+        self.writes.iter().any(|&(a, _)| a == address)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct History {
+    entries: VecDeque<Entry>,
+    bytes: usize,
+    max_bytes: usize,
+    // The state from when the CPU stopped for RDY or WAI, while it's still stopped.
+    stall: Option<Entry>,
+}
+
+impl History {
+    // Roughly how much memory the history may use. Each instruction takes about two hundred
+    // bytes, so a megabyte goes back five thousand or so.
+    pub fn new(max_bytes: usize) -> History {
+        History { entries: VecDeque::new(), bytes: 0, max_bytes, stall: None }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.bytes = 0;
+        self.stall = None;
+    }
+
+    // Oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter()
+    }
+
+    // How far back it's possible to go.
+    pub fn oldest_cycle(&self) -> Option<u64> {
+        self.entries.front().map(|e| e.clockticks)
+    }
+
+    pub(crate) fn push(&mut self, mut entry: Entry) {
+        // Time spent stalled isn't worth an entry of its own, so it goes in with the instruction
+        // that ends it: going back past that instruction goes back past the stall too.
+        if let Some(stall) = self.stall.take() {
+            entry.state = stall.state;
+            entry.clockticks = stall.clockticks;
+        }
+        self.bytes += entry.size();
+        self.entries.push_back(entry);
+        while self.bytes > self.max_bytes {
+            match self.entries.pop_front() {
+                Some(e) => self.bytes -= e.size(),
+                None => break,
+            }
+        }
+    }
+
+    // Called for each cycle the CPU sits stalled on RDY or in WAI; only the first one matters.
+    pub(crate) fn stalled(&mut self, entry: Entry) {
+        if self.stall.is_none() {
+            self.stall = Some(entry);
+        }
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<Entry> {
+        // Going back at all goes back past any stall in progress.
+        self.stall = None;
+        let e = self.entries.pop_back()?;
+        self.bytes -= e.size();
+        Some(e)
+    }
+
+    // How many entries, counting back from the newest, it takes to get to (and include) the last
+    // one that wrote to 'address'.
+    pub(crate) fn back_to_write(&self, address: u16) -> Option<usize> {
+        self.entries.iter().rev().position(|e| e.wrote(address)).map(|n| n + 1)
+    }
+
+    // How many entries it takes to get back to at or before 'cycle', or as near as there is.
+    pub(crate) fn back_to_cycle(&self, cycle: u64) -> usize {
+        match self.entries.iter().rev().position(|e| e.clockticks <= cycle) {
+            Some(n) => n + 1,
+            None => self.entries.len(),
+        }
+    }
+}
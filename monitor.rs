@@ -0,0 +1,581 @@
+use fake6502::asm::assemble;
+use fake6502::breakpoints::WatchKind;
+use fake6502::disasm::{decode, Options};
+use fake6502::trace::TraceOptions;
+use fake6502::{Backplane, Control, CpuVariant, JamReason, Status, StopReason, CPU};
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{self, BufReader};
+
+// A machine-language monitor in the old style, driving a CPU with 64K of flat RAM:
+//
+//     monitor [--variant nmos|illegal|2a03|65c02] [image[@address]]...
+//
+// Type "help" at the "." prompt for the commands. Numbers are hex throughout, with or without a
+// leading '$'. Commands come from stdin, so a script can be piped in as well.
+
+const HELP: &str = "\
+load <file> [addr]       load a binary image (a .prg file loads at its own address by default)
+save <file> <from> <to>  save memory from..to, inclusive
+reset | irq | nmi        reset the CPU, or interrupt it
+r [reg=val ...]          show registers, or set them (pc, a, x, y, sp, p, or the flags n v d i z c)
+m [from [to]]            show memory
+> addr bb bb ...         change memory
+f from to bb             fill memory
+d [addr [count]]         disassemble; with no address, around the pc
+a addr instruction       assemble one instruction into memory
+s [count]                step
+n                        step over a JSR
+c [limit]                continue until a breakpoint, watchpoint, jam, self-loop or WAI
+g addr                   set the pc and continue
+b [addr]                 set a breakpoint on the pc, or list breakpoints and watchpoints
+bd addr | bd *           delete a breakpoint, or all of them
+w r|w|rw from [to]       set a watchpoint on reads (not counting instruction fetches), writes or both
+wd n | wd *              delete watchpoint n, or all of them
+trace <file> | trace off log every instruction in nestest.log format
+history, !!, !n          show the command history, or repeat a command from it
+q                        quit
+An empty line repeats the last s, n, m or d.";
+
+// How long "c" runs before giving up, so that a program that's wandered off into a loop the trap
+// check doesn't catch still hands back control eventually.
+const DEFAULT_RUN_LIMIT: u64 = 100_000_000;
+
+// Breakpoints and watchpoints are the CPU's own (see breakpoints.rs), so they only go off while
+// it's running, not when the monitor looks at memory for "m" and "d".
+struct MonitorSystem {
+    mem: Vec<u8>,
+}
+
+impl Backplane for MonitorSystem {
+    fn read(&self, address: u16) -> u8 {
+        self.mem[address as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.mem[address as usize] = value;
+    }
+
+    fn each_instr(&mut self, _cpu: &mut CPU) -> Control {
+        Control::Continue
+    }
+}
+
+struct Monitor {
+    cpu: CPU,
+    sys: MonitorSystem,
+    history: Vec<String>,
+    // Where "m" and "d" carry on from when they're repeated.
+    next_m: u16,
+    next_d: u16,
+    quit: bool,
+}
+
+fn parse_hex(s: &str) -> Result<u16, String> {
+    let digits = s.trim_start_matches('$');
+    u16::from_str_radix(digits, 16).map_err(|_| format!("bad number: {}", s))
+}
+
+fn parse_byte(s: &str) -> Result<u8, String> {
+    let n = parse_hex(s)?;
+    if n > 0xFF {
+        return Err(format!("not a byte: {}", s));
+    }
+    Ok(n as u8)
+}
+
+fn parse_variant(s: &str) -> Option<CpuVariant> {
+    match s {
+        "nmos" => Some(CpuVariant::Nmos6502),
+        "illegal" => Some(CpuVariant::Nmos6502Illegal),
+        "2a03" => Some(CpuVariant::Ricoh2A03),
+        "65c02" => Some(CpuVariant::Wdc65C02),
+        _ => None,
+    }
+}
+
+impl Monitor {
+    fn new(variant: CpuVariant) -> Monitor {
+        let mut cpu = CPU::new(variant);
+        // The monitor doesn't have anything to do after each instruction.
+        cpu.do_callback = false;
+        Monitor {
+            cpu,
+            sys: MonitorSystem { mem: vec![0; 0x10000] },
+            history: Vec::new(),
+            next_m: 0,
+            next_d: 0,
+            quit: false,
+        }
+    }
+
+    fn registers(&self) -> String {
+        let c = &self.cpu;
+        format!("  PC  A  X  Y  SP NV-BDIZC   CYCLES\n{:04X} {:02X} {:02X} {:02X} {:02X} {} {:8}",
+                c.pc.0, c.a.0, c.x.0, c.y.0, c.sp.0, c.status, c.clockticks)
+    }
+
+    // The id of the breakpoint on 'address', if there is one.
+    fn breakpoint_at(&self, address: u16) -> Option<u32> {
+        self.cpu.breakpoints.iter().find(|(_, b)| b.kind == WatchKind::Execute && b.covers(address)).map(|(id, _)| id)
+    }
+
+    fn disassemble_line(&self, address: u16) -> (String, u16) {
+        let inst = decode(&self.sys, address, self.cpu.variant);
+        let options = Options { hex_dump: true, ..Options::default() };
+        let marker = if address == self.cpu.pc.0 { '>' } else if self.breakpoint_at(address).is_some() { '*' } else { ' ' };
+        (format!("{} {}", marker, inst.format(&options)), address.wrapping_add(inst.length))
+    }
+
+    // Disassembling backwards is guesswork, since any byte could be the end of an instruction.
+    // Look for the furthest-back start (up to 'before' instructions) that decodes straight into
+    // the pc; if nothing does, just start at the pc.
+    fn start_before(&self, address: u16, before: u16) -> u16 {
+        for back in (1..=before * 3).rev() {
+            let start = address.wrapping_sub(back);
+            let mut at = start;
+            let mut count = 0;
+            while at != address && address.wrapping_sub(at) <= back && count < before {
+                at = at.wrapping_add(decode(&self.sys, at, self.cpu.variant).length);
+                count += 1;
+            }
+            if at == address && count == before {
+                return start;
+            }
+        }
+        address
+    }
+
+    fn disassemble(&mut self, start: u16, count: usize) -> String {
+        let mut out = Vec::new();
+        let mut at = start;
+        for _ in 0..count {
+            let (line, next) = self.disassemble_line(at);
+            out.push(line);
+            at = next;
+        }
+        self.next_d = at;
+        out.join("\n")
+    }
+
+    fn memory(&mut self, from: u16, to: u16) -> String {
+        let mut out = Vec::new();
+        let mut row = from & 0xFFF0;
+        loop {
+            let mut hex = String::new();
+            let mut text = String::new();
+            for i in 0..16u16 {
+                let address = row.wrapping_add(i);
+                if address < from || address > to {
+                    hex += "   ";
+                    text.push(' ');
+                } else {
+                    let b = self.sys.mem[address as usize];
+                    hex += &format!(" {:02X}", b);
+                    text.push(if (0x20..0x7F).contains(&b) { b as char } else { '.' });
+                }
+            }
+            out.push(format!("{:04X} {}  {}", row, hex, text));
+            if to.wrapping_sub(row) < 16 || row >= 0xFFF0 {
+                break;
+            }
+            row += 16;
+        }
+        self.next_m = to.wrapping_add(1);
+        out.join("\n")
+    }
+
+    fn set_register(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let cpu = &mut self.cpu;
+        let flag = || -> Result<bool, String> {
+            match value {
+                "0" => Ok(false),
+                "1" => Ok(true),
+                _ => Err(format!("flags are 0 or 1: {}", value)),
+            }
+        };
+        match name {
+            "pc" => cpu.pc.0 = parse_hex(value)?,
+            "a" => cpu.a.0 = parse_byte(value)?,
+            "x" => cpu.x.0 = parse_byte(value)?,
+            "y" => cpu.y.0 = parse_byte(value)?,
+            "sp" | "s" => cpu.sp.0 = parse_byte(value)?,
+            "p" => cpu.status = Status::from_bits(parse_byte(value)?),
+            "n" => cpu.status.set_negative(flag()?),
+            "v" => cpu.status.set_overflow(flag()?),
+            "d" => cpu.status.set_decimal(flag()?),
+            "i" => cpu.status.set_interrupt(flag()?),
+            "z" => cpu.status.set_zero(flag()?),
+            "c" => cpu.status.set_carry(flag()?),
+            _ => return Err(format!("no such register: {}", name)),
+        }
+        Ok(())
+    }
+
+    fn load(&mut self, path: &str, address: Option<u16>) -> Result<String, String> {
+        let mut data = Vec::new();
+        File::open(path).and_then(|mut f| f.read_to_end(&mut data)).map_err(|e| format!("{}: {}", path, e))?;
+        let is_prg = path.to_ascii_lowercase().ends_with(".prg") && data.len() >= 2;
+        let (start, body) = match address {
+            Some(a) => (a, &data[..]),
+            None if is_prg => (data[0] as u16 | (data[1] as u16) << 8, &data[2..]),
+            None => (0, &data[..]),
+        };
+        let len = body.len().min(0x10000 - start as usize);
+        self.sys.mem[start as usize..start as usize + len].copy_from_slice(&body[..len]);
+        Ok(format!("loaded {} bytes at {:04X}-{:04X}", len, start, (start as usize + len).saturating_sub(1)))
+    }
+
+    // Runs one instruction, unless a breakpoint is in the way. Returns a message if something
+    // should stop the run.
+    fn step_one(&mut self) -> Option<String> {
+        // A goal one cycle away runs exactly one instruction.
+        let target = self.cpu.clockticks + 1;
+        match self.cpu.exec_until(&mut self.sys, target) {
+            StopReason::BudgetExhausted | StopReason::HostRequested(_) | StopReason::TrapDetected(_) => None,
+            StopReason::Breakpoint(pc) => Some(format!("breakpoint at {:04X}", pc)),
+            StopReason::Watchpoint(hit) => Some(format!("watchpoint: {} {:04X} by the instruction at {:04X}",
+                                                        if hit.write { "write to" } else { "read from" }, hit.address, hit.pc)),
+            StopReason::Jammed => Some(jam_message(self.cpu.jam_reason().unwrap())),
+        }
+    }
+
+    // Stepping and running start from wherever the pc is, without stopping at a breakpoint there.
+    fn resume(&mut self) {
+        let pc = self.cpu.pc.0;
+        self.cpu.breakpoints.resume_at(pc);
+    }
+
+    // Keeps stepping until a breakpoint, a watchpoint, a jam, a self-loop, 'until' (for "n"), or
+    // 'limit' instructions.
+    fn run(&mut self, limit: u64, until: Option<(u16, u8)>) -> String {
+        for _ in 0..limit {
+            let (pc, ran) = (self.cpu.pc.0, self.cpu.instructions_ran);
+            if let Some(message) = self.step_one() {
+                return message;
+            }
+            let now = self.cpu.pc.0;
+            if let Some((address, sp)) = until {
+                if now == address && self.cpu.sp.0 == sp {
+                    return String::new();
+                }
+            }
+            // Nothing is going to assert an interrupt while this is running, so there's no point
+            // sitting in a WAI until the limit runs out.
+            if self.cpu.instructions_ran == ran && self.cpu.is_waiting() {
+                return format!("waiting for an interrupt at {:04X}", now);
+            }
+            if now == pc && self.cpu.instructions_ran != ran {
+                return format!("stuck in a loop at {:04X}", now);
+            }
+        }
+        format!("stopped after {} instructions", limit)
+    }
+
+    fn show_position(&mut self) -> String {
+        let (line, _) = self.disassemble_line(self.cpu.pc.0);
+        format!("{}\n{}", self.registers(), line)
+    }
+
+    fn command(&mut self, line: &str) -> Result<String, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let arg = |i: usize| -> Result<u16, String> {
+            words.get(i).ok_or_else(|| "missing argument".to_string()).and_then(|w| parse_hex(w))
+        };
+        let (first, rest) = match words.split_first() {
+            Some((first, rest)) => (first.to_ascii_lowercase(), rest),
+            None => return Ok(String::new()),
+        };
+
+        match first.as_str() {
+            "help" | "?" => Ok(HELP.to_string()),
+            "q" | "quit" | "x" => {
+                self.quit = true;
+                Ok(String::new())
+            }
+
+            "load" | "l" => {
+                let path = rest.first().ok_or("load what?")?;
+                let address = if rest.len() > 1 { Some(arg(2)?) } else { None };
+                self.load(path, address)
+            }
+            "save" => {
+                let path = rest.first().ok_or("save to what?")?;
+                let (from, to) = (arg(2)?, arg(3)?);
+                if to < from {
+                    return Err("the end comes before the start".to_string());
+                }
+                File::create(path).and_then(|mut f| f.write_all(&self.sys.mem[from as usize..=to as usize]))
+                    .map_err(|e| format!("{}: {}", path, e))?;
+                Ok(format!("saved {:04X}-{:04X}", from, to))
+            }
+            "reset" => {
+                self.cpu.reset(&self.sys);
+                Ok(self.show_position())
+            }
+            "irq" => {
+                if !self.cpu.irq(&mut self.sys) {
+                    return Err("IRQ masked by the I flag".to_string());
+                }
+                Ok(self.show_position())
+            }
+            "nmi" => {
+                self.cpu.nmi(&mut self.sys);
+                Ok(self.show_position())
+            }
+
+            "r" => {
+                for assignment in rest {
+                    let mut parts = assignment.splitn(2, '=');
+                    let name = parts.next().unwrap_or("").to_ascii_lowercase();
+                    let value = parts.next().ok_or_else(|| format!("expected reg=value: {}", assignment))?;
+                    self.set_register(&name, value)?;
+                }
+                Ok(self.registers())
+            }
+            "m" => {
+                let from = if rest.is_empty() { self.next_m } else { arg(1)? };
+                let to = if rest.len() > 1 { arg(2)? } else { from.saturating_add(0x7F) };
+                if to < from {
+                    return Err("the end comes before the start".to_string());
+                }
+                Ok(self.memory(from, to))
+            }
+            ">" => {
+                let address = arg(1)?;
+                for (i, b) in rest[1..].iter().enumerate() {
+                    self.sys.mem[address.wrapping_add(i as u16) as usize] = parse_byte(b)?;
+                }
+                Ok(self.memory(address, address.wrapping_add(rest.len().saturating_sub(2) as u16)))
+            }
+            "f" => {
+                let (from, to) = (arg(1)?, arg(2)?);
+                let value = parse_byte(words.get(3).ok_or("fill with what?")?)?;
+                if to < from {
+                    return Err("the end comes before the start".to_string());
+                }
+                for b in &mut self.sys.mem[from as usize..=to as usize] {
+                    *b = value;
+                }
+                Ok(String::new())
+            }
+            "d" => {
+                let count = if rest.len() > 1 { arg(2)? as usize } else { 16 };
+                let start = if rest.is_empty() { self.start_before(self.cpu.pc.0, 4) } else { arg(1)? };
+                Ok(self.disassemble(start, count))
+            }
+            "a" => {
+                let address = arg(1)?;
+                let source = rest[1..].join(" ");
+                let assembled = assemble(&source, address, self.cpu.variant).map_err(|e| e.message)?;
+                assembled.load_into(&mut self.sys.mem);
+                let (line, next) = self.disassemble_line(address);
+                self.next_d = next;
+                Ok(line)
+            }
+
+            "s" | "z" => {
+                let count = if rest.is_empty() { 1 } else { arg(1)? };
+                self.resume();
+                let mut out = Vec::new();
+                for _ in 0..count {
+                    if let Some(message) = self.step_one() {
+                        out.push(message);
+                        break;
+                    }
+                }
+                out.push(self.show_position());
+                Ok(out.join("\n"))
+            }
+            "n" => {
+                let pc = self.cpu.pc.0;
+                self.resume();
+                let message = if self.sys.mem[pc as usize] == 0x20 {
+                    // Run until the JSR returns, with the stack back where it was.
+                    let sp = self.cpu.sp.0;
+                    self.run(DEFAULT_RUN_LIMIT, Some((pc.wrapping_add(3), sp)))
+                } else {
+                    self.step_one().unwrap_or_default()
+                };
+                Ok(format!("{}{}{}", message, if message.is_empty() { "" } else { "\n" }, self.show_position()))
+            }
+            "c" | "g" => {
+                if first == "g" {
+                    self.cpu.pc.0 = arg(1)?;
+                }
+                let limit = if first == "c" && !rest.is_empty() { u64::from(arg(1)?) } else { DEFAULT_RUN_LIMIT };
+                self.resume();
+                let message = self.run(limit, None);
+                Ok(format!("{}\n{}", message, self.show_position()))
+            }
+
+            "b" => {
+                if rest.is_empty() {
+                    let out: Vec<String> = self.cpu.breakpoints.iter().map(|(id, b)| {
+                        let kind = match b.kind {
+                            WatchKind::Execute => return format!("break {:04X}", b.from),
+                            WatchKind::Read => "r",
+                            WatchKind::Write => "w",
+                            WatchKind::ReadWrite => "rw",
+                        };
+                        format!("watch {} {:<2} {:04X}-{:04X}", id, kind, b.from, b.to)
+                    }).collect();
+                    return Ok(out.join("\n"));
+                }
+                let address = arg(1)?;
+                if self.breakpoint_at(address).is_none() {
+                    self.cpu.breakpoints.break_at(address);
+                }
+                Ok(String::new())
+            }
+            "bd" => {
+                if rest.first() == Some(&"*") {
+                    let ids: Vec<u32> = self.cpu.breakpoints.iter().filter(|(_, b)| b.kind == WatchKind::Execute).map(|(id, _)| id).collect();
+                    for id in ids {
+                        self.cpu.breakpoints.remove(id);
+                    }
+                } else {
+                    let address = arg(1)?;
+                    let id = self.breakpoint_at(address).ok_or_else(|| format!("no breakpoint at {:04X}", address))?;
+                    self.cpu.breakpoints.remove(id);
+                }
+                Ok(String::new())
+            }
+            "w" => {
+                let kind = match rest.first().map(|k| k.to_ascii_lowercase()).as_deref() {
+                    Some("r") => WatchKind::Read,
+                    Some("w") => WatchKind::Write,
+                    Some("rw") => WatchKind::ReadWrite,
+                    _ => return Err("watch r, w or rw?".to_string()),
+                };
+                let from = arg(2)?;
+                let to = if rest.len() > 2 { arg(3)? } else { from };
+                if to < from {
+                    return Err("the end comes before the start".to_string());
+                }
+                Ok(format!("watchpoint {}", self.cpu.breakpoints.watch(kind, from, to)))
+            }
+            "wd" => {
+                let watchpoints: Vec<u32> = self.cpu.breakpoints.iter().filter(|(_, b)| b.kind != WatchKind::Execute).map(|(id, _)| id).collect();
+                if rest.first() == Some(&"*") {
+                    for id in watchpoints {
+                        self.cpu.breakpoints.remove(id);
+                    }
+                    return Ok(String::new());
+                }
+                let n: u32 = rest.first().and_then(|n| n.parse().ok()).ok_or("delete which watchpoint?")?;
+                if !watchpoints.contains(&n) {
+                    return Err(format!("no watchpoint {}", n));
+                }
+                self.cpu.breakpoints.remove(n);
+                Ok(String::new())
+            }
+
+            "trace" => match rest.first() {
+                Some(&"off") => {
+                    self.cpu.stop_tracing();
+                    Ok(String::new())
+                }
+                Some(path) => {
+                    let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+                    self.cpu.trace_to(Box::new(io::BufWriter::new(file)), TraceOptions::default());
+                    Ok(format!("tracing to {}", path))
+                }
+                None => Ok(if self.cpu.is_tracing() { "tracing is on" } else { "tracing is off" }.to_string()),
+            },
+            "history" | "h" => {
+                let lines: Vec<String> = self.history.iter().enumerate().map(|(i, l)| format!("{:4}  {}", i + 1, l)).collect();
+                Ok(lines.join("\n"))
+            }
+
+            _ => Err(format!("unknown command: {} (try help)", first)),
+        }
+    }
+
+    // Handles the history and repeat shortcuts, then runs the command.
+    fn input(&mut self, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        let line = if line.is_empty() {
+            // Repeat stepping and listing commands, carrying on from where they left off.
+            match self.history.last().and_then(|l| l.split_whitespace().next()) {
+                Some("s") | Some("z") | Some("n") => self.history.last().unwrap().clone(),
+                Some("m") => "m".to_string(),
+                Some("d") => format!("d {:04X}", self.next_d),
+                _ => return Ok(String::new()),
+            }
+        } else if line == "!!" {
+            self.history.last().cloned().ok_or("no history yet")?
+        } else if let Some(n) = line.strip_prefix('!') {
+            let n: usize = n.parse().map_err(|_| format!("bad history number: {}", n))?;
+            self.history.get(n.wrapping_sub(1)).cloned().ok_or_else(|| format!("no command {} in history", n))?
+        } else {
+            line.to_string()
+        };
+        if !line.starts_with("history") && line != "h" {
+            self.history.push(line.clone());
+        }
+        self.command(&line)
+    }
+}
+
+fn jam_message(reason: JamReason) -> String {
+    match reason {
+        JamReason::Jam { opcode, pc } => format!("CPU jammed by opcode {:02X} at {:04X}; reset to recover", opcode, pc),
+        JamReason::Stopped { pc } => format!("CPU stopped by STP at {:04X}; reset to recover", pc),
+    }
+}
+
+fn main() {
+    let mut variant = CpuVariant::Nmos6502;
+    let mut images = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--variant" {
+            variant = match args.next().as_deref().and_then(parse_variant) {
+                Some(v) => v,
+                None => {
+                    eprintln!("--variant takes nmos, illegal, 2a03 or 65c02");
+                    std::process::exit(2);
+                }
+            };
+        } else {
+            images.push(arg);
+        }
+    }
+
+    let mut monitor = Monitor::new(variant);
+    for image in images {
+        let mut parts = image.splitn(2, '@');
+        let path = parts.next().unwrap().to_string();
+        let result = match parts.next().map(parse_hex) {
+            Some(Err(e)) => Err(e),
+            Some(Ok(address)) => monitor.load(&path, Some(address)),
+            None => monitor.load(&path, None),
+        };
+        match result {
+            Ok(message) => println!("{}", message),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+    monitor.cpu.reset(&monitor.sys);
+    println!("{}", monitor.show_position());
+
+    let stdin = BufReader::new(io::stdin());
+    let mut lines = stdin.lines();
+    while !monitor.quit {
+        print!(".");
+        io::stdout().flush().unwrap();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => break,
+        };
+        match monitor.input(&line) {
+            Ok(out) if out.is_empty() => {}
+            Ok(out) => println!("{}", out),
+            Err(e) => println!("? {}", e),
+        }
+    }
+    // Make sure a trace file gets flushed.
+    monitor.cpu.stop_tracing();
+}
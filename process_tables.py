@@ -1,4 +1,5 @@
 import json
+import sys
 
 # This file takes the data in instruction_tables.json and spits out a segment
 # of code that I pasted into fake6502.rs while I was converting it from Rust to
@@ -8,12 +9,20 @@ import json
 with open("instruction_tables.json") as tables:
     itbls = json.load(tables)
 
+# Run with "65c02" as an argument to get the table for run_one_op_65c02 instead of the NMOS one,
+# and with "table" to get the entries of the NMOS_OPCODES/CMOS_OPCODES arrays instead of the
+# match.
+suffix = ""
+if "65c02" in sys.argv[1:]:
+    suffix = "_65c02"
+as_table = "table" in sys.argv[1:]
+
 def strip(x):
     return x.strip()
 
-ops = list(map(strip, itbls["opcodes"].split(",")))
-modes = list(map(strip, itbls["addressing_modes"].split(",")))
-ticks = list(map(strip, itbls["ticks"].split(",")))
+ops = list(map(strip, itbls["opcodes" + suffix].split(",")))
+modes = list(map(strip, itbls["addressing_modes" + suffix].split(",")))
+ticks = list(map(strip, itbls["ticks" + suffix].split(",")))
 
 assert len(ops) == 256
 assert len(modes) == 256
@@ -37,7 +46,11 @@ mode_rustfns = {
     'ind': 'addr_indirect',
     'indy': 'addr_indirect_y',
     'zp': 'addr_zeropage',
-    'zpx': 'addr_zeropage_x'
+    'zpx': 'addr_zeropage_x',
+    # 65C02 only
+    'zpind': 'addr_zeropage_indirect',
+    'absxind': 'addr_absolute_x_indirect',
+    'zprel': 'addr_zeropage_relative',
 }
 
 width_ops = max(map(lambda x: len("inst_%s(mem);" % x), ops)) + 2
@@ -46,6 +59,34 @@ width_modes = max(map(lambda x: len(mode_rustfns[x] + "(mem);"), modes)) + 2
 def padded(targ_width, value):
     return value + ' '*(targ_width-len(value))
 
+mode_enum = {
+    'imp': 'Implied',
+    'acc': 'Accumulator',
+    'imm': 'Immediate',
+    'zp': 'ZeroPage',
+    'zpx': 'ZeroPageX',
+    'zpy': 'ZeroPageY',
+    'rel': 'Relative',
+    'abso': 'Absolute',
+    'absx': 'AbsoluteX',
+    'absy': 'AbsoluteY',
+    'ind': 'Indirect',
+    'indx': 'IndirectX',
+    'indy': 'IndirectY',
+    'zpind': 'ZeroPageIndirect',
+    'absxind': 'AbsoluteXIndirect',
+    'zprel': 'ZeroPageRelative',
+}
+
+if as_table:
+    width_names = max(map(lambda x: len('"%s",' % x), ops)) + 1
+    width_enums = max(map(lambda x: len("AddrMode::%s," % mode_enum[x]), modes)) + 1
+    for i in range(0, 256):
+        name = padded(width_names, '"%s",' % ops[i])
+        mode = padded(width_enums, "AddrMode::%s," % mode_enum[modes[i]])
+        print("/* %02X */ OpInfo { mnemonic: %s mode: %s cycles: %s }," % (i, name, mode, ticks[i]))
+    sys.exit(0)
+
 for i in range(0, 256):
     op = "inst_%s(mem);" % ops[i]
     mode = mode_rustfns[modes[i]] + "(mem);"
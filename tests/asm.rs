@@ -0,0 +1,166 @@
+mod common;
+
+use common::FlatSystem;
+use fake6502::asm::assemble;
+use fake6502::disasm::{disassemble, is_undocumented, Options};
+use fake6502::{CpuVariant, NMOS_OPCODES};
+
+fn bytes(source: &str, variant: CpuVariant) -> Vec<u8> {
+    assemble(source, 0x0400, variant).unwrap().bytes()
+}
+
+#[test]
+fn addressing_modes() {
+    let nmos = CpuVariant::Nmos6502;
+    assert_eq!(bytes("nop", nmos), [0xEA]);
+    assert_eq!(bytes("asl", nmos), [0x0A]);
+    assert_eq!(bytes("ASL A", nmos), [0x0A]);
+    assert_eq!(bytes("lda #$12", nmos), [0xA9, 0x12]);
+    assert_eq!(bytes("lda $12", nmos), [0xA5, 0x12]);
+    assert_eq!(bytes("lda $0012", nmos), [0xA5, 0x12]);
+    assert_eq!(bytes("lda $1234", nmos), [0xAD, 0x34, 0x12]);
+    assert_eq!(bytes("lda $12,x", nmos), [0xB5, 0x12]);
+    assert_eq!(bytes("ldx $12, Y", nmos), [0xB6, 0x12]);
+    // No LDA zp,Y, so that has to be absolute.
+    assert_eq!(bytes("lda $12,y", nmos), [0xB9, 0x12, 0x00]);
+    assert_eq!(bytes("lda ($12,x)", nmos), [0xA1, 0x12]);
+    assert_eq!(bytes("lda ($12),y", nmos), [0xB1, 0x12]);
+    assert_eq!(bytes("jmp ($fffc)", nmos), [0x6C, 0xFC, 0xFF]);
+    assert_eq!(bytes("bne *", nmos), [0xD0, 0xFE]);
+    // Parentheses that are just part of the expression.
+    assert_eq!(bytes("lda (1+2)*4", nmos), [0xA5, 0x0C]);
+}
+
+#[test]
+fn labels_and_expressions() {
+    let source = "
+        SCREEN = $0200
+                .org $0600
+        start:  ldx #0
+        @loop:  lda message,x   ; forward reference, so absolute
+                beq @done
+                sta SCREEN,x
+                inx
+                bne @loop
+        @done:  jmp start
+        other:  beq @loop       ; a different @loop
+        @loop:  .word start, >start, <(message + 1)
+        message: .byte \"hi\", 0, 'a', -1
+    ";
+    let out = assemble(source, 0x0400, CpuVariant::Nmos6502).unwrap();
+    assert_eq!(out.origin(), 0x0600);
+    assert_eq!(out.labels["start"], 0x0600);
+    assert_eq!(out.labels["start@loop"], 0x0602);
+    assert_eq!(out.labels["other@loop"], 0x0612);
+    assert_eq!(out.labels["message"], 0x0618);
+    assert_eq!(out.bytes(), [
+        0xA2, 0x00,
+        0xBD, 0x18, 0x06,
+        0xF0, 0x06,
+        0x9D, 0x00, 0x02,
+        0xE8,
+        0xD0, 0xF5,
+        0x4C, 0x00, 0x06,
+        0xF0, 0x00,
+        0x00, 0x06, 0x06, 0x00, 0x19, 0x00,
+        0x68, 0x69, 0x00, 0x61, 0xFF,
+    ]);
+}
+
+// Only labels start a new scope for @locals; a constant in between doesn't.
+#[test]
+fn constants_dont_change_scope() {
+    let source = "
+        start:  ldx #3
+        COUNT = 3
+        @loop:  dex
+                bne @loop
+    ";
+    let out = assemble(source, 0x0400, CpuVariant::Nmos6502).unwrap();
+    assert_eq!(out.labels["start@loop"], 0x0402);
+    assert!(!out.labels.contains_key("COUNT@loop"));
+}
+
+// Arithmetic wraps around like it does in breakpoint conditions, rather than overflowing.
+#[test]
+fn arithmetic_wraps() {
+    let source = "
+        BIG = $7FFFFFFFFFFFFFFF
+        .byte <(BIG + 1 + $FF), <(BIG * 2), <(-(BIG + 1)), <((BIG + 1) / -1)
+    ";
+    let out = assemble(source, 0x0400, CpuVariant::Nmos6502).unwrap();
+    assert_eq!(out.bytes(), [0xFF, 0xFE, 0x00, 0x00]);
+}
+
+#[test]
+fn org_and_segments() {
+    let out = assemble(".org $fffc\n.word $0400\n.org $0400\nrts", 0, CpuVariant::Nmos6502).unwrap();
+    let mut mem = vec![0; 0x10000];
+    out.load_into(&mut mem);
+    assert_eq!(&mem[0xFFFC..], [0x00, 0x04, 0x00, 0x00]);
+    assert_eq!(mem[0x0400], 0x60);
+}
+
+#[test]
+fn variants() {
+    assert!(assemble("lax $12", 0, CpuVariant::Nmos6502).is_err());
+    assert!(assemble("stz $12", 0, CpuVariant::Nmos6502).is_err());
+}
+
+#[test]
+#[cfg(not(feature = "no-undocumented"))]
+fn undocumented_opcodes() {
+    assert_eq!(bytes("lax $12", CpuVariant::Nmos6502Illegal), [0xA7, 0x12]);
+    assert_eq!(bytes("nop", CpuVariant::Nmos6502Illegal), [0xEA]);
+    assert_eq!(bytes("nop #1", CpuVariant::Nmos6502Illegal), [0x80, 0x01]);
+}
+
+#[test]
+#[cfg(not(feature = "no-65c02"))]
+fn cmos_opcodes() {
+    let cmos = CpuVariant::Wdc65C02;
+    assert_eq!(bytes("stz $12", cmos), [0x64, 0x12]);
+    assert_eq!(bytes("lda ($12)", cmos), [0xB2, 0x12]);
+    assert_eq!(bytes("jmp ($1234,x)", cmos), [0x7C, 0x34, 0x12]);
+    assert_eq!(bytes("rmb3 $12", cmos), [0x37, 0x12]);
+    assert_eq!(bytes("bbs7 $12,*", cmos), [0xFF, 0x12, 0xFD]);
+    assert_eq!(bytes("inc", cmos), [0x1A]);
+}
+
+#[test]
+fn errors() {
+    let err = assemble("nop\nfrob $12", 0, CpuVariant::Nmos6502).unwrap_err();
+    assert_eq!(err.line, 2);
+    assert!(assemble("lda missing", 0, CpuVariant::Nmos6502).is_err());
+    assert!(assemble("x: nop\nx: nop", 0, CpuVariant::Nmos6502).is_err());
+    assert!(assemble("bne far\n.org $0500\nfar: rts", 0x0400, CpuVariant::Nmos6502).is_err());
+    assert!(assemble("lda #256", 0, CpuVariant::Nmos6502).is_err());
+    assert!(assemble("jmp $12,y", 0, CpuVariant::Nmos6502).is_err());
+}
+
+// Every NMOS instruction the disassembler prints, the assembler should turn back into the same
+// bytes. (Only the documented ones, when the undocumented ones are compiled out.)
+#[test]
+fn round_trips_with_the_disassembler() {
+    let variant = if cfg!(feature = "no-undocumented") {
+        CpuVariant::Nmos6502
+    } else {
+        CpuVariant::Nmos6502Illegal
+    };
+    for opcode in 0..=255u8 {
+        if variant == CpuVariant::Nmos6502 && is_undocumented(variant, opcode) {
+            continue;
+        }
+        let len = 1 + NMOS_OPCODES[opcode as usize].mode.operand_len() as usize;
+        let rom = FlatSystem::holding(0x0400, &[opcode, 0x12, 0x34]);
+        let text = disassemble(&rom, 0x0400, 1, variant, &Options::default());
+        let out = assemble(&text, 0x0400, variant).unwrap_or_else(|e| panic!("{}: {}", text.trim(), e));
+        let first = out.bytes()[0];
+        // Duplicate encodings (the extra NOPs, SBC $EB...) come back as the preferred one.
+        if NMOS_OPCODES[first as usize] == NMOS_OPCODES[opcode as usize] {
+            assert_eq!(&out.bytes()[1..], &rom.mem[0x0401..0x0400 + len], "{}", text.trim());
+        } else {
+            assert_eq!(out.bytes(), &rom.mem[0x0400..0x0400 + len], "{}", text.trim());
+        }
+    }
+}
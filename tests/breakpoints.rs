@@ -0,0 +1,146 @@
+mod common;
+
+use common::FlatSystem;
+use fake6502::breakpoints::{Condition, Hit, WatchKind};
+use fake6502::{CpuVariant, StopReason, CPU};
+
+const PROGRAM: [u8; 12] = [
+    0xA9, 0x40,       // 0400  LDA #$40
+    0x8D, 0x00, 0xD0, // 0402  STA $D000
+    0xE8,             // 0405  INX
+    0xAD, 0x12, 0xD0, // 0406  LDA $D012
+    0x4C, 0x00, 0x04, // 0409  JMP $0400
+];
+
+fn boot() -> (CPU, FlatSystem) {
+    let (cpu, mut sys) = common::boot(CpuVariant::Nmos6502, &PROGRAM);
+    sys.mem[0xD012] = 0x80;
+    (cpu, sys)
+}
+
+#[test]
+fn execute_breakpoint_stops_before_the_instruction() {
+    let (mut cpu, mut sys) = boot();
+    let id = cpu.breakpoints.break_at(0x0405);
+
+    assert_eq!(cpu.exec(&mut sys, 1000), StopReason::Breakpoint(0x0405));
+    assert_eq!((cpu.pc.0, cpu.x.0), (0x0405, 0));
+    assert_eq!(cpu.breakpoints.last_hit(), Some(Hit { id, address: 0x0405, write: false, pc: 0x0405 }));
+
+    // Carrying on runs the INX and goes round again, rather than stopping where it is.
+    assert_eq!(cpu.exec(&mut sys, 1000), StopReason::Breakpoint(0x0405));
+    assert_eq!(cpu.x.0, 1);
+    assert_eq!(cpu.breakpoints.get(id).unwrap().hits, 2);
+
+    assert!(cpu.breakpoints.remove(id));
+    assert!(!cpu.breakpoints.remove(id));
+    assert_eq!(cpu.exec(&mut sys, 1000), StopReason::BudgetExhausted);
+}
+
+// An interrupt moves the pc to the handler without an instruction there being fetched yet, and a
+// breakpoint at the handler's entry should still catch it.
+#[test]
+fn breakpoint_on_an_interrupt_handler() {
+    let (mut cpu, mut sys) = boot();
+    sys.load(0x0500, &[0x40]); // 0500  RTI
+    sys.load(0xFFFE, &[0x00, 0x05]);
+    cpu.status.set_interrupt(false);
+    let id = cpu.breakpoints.break_at(0x0500);
+
+    cpu.exec(&mut sys, 20);
+    cpu.set_irq(1, true);
+    assert_eq!(cpu.exec(&mut sys, 1000), StopReason::Breakpoint(0x0500));
+    assert_eq!(cpu.breakpoints.last_hit().unwrap().id, id);
+    assert!(cpu.status.interrupt());
+    assert_eq!(cpu.sp.0, 0xFA);
+
+    // A watchpoint sees the interrupt's pushes as writes, made on the way into the handler.
+    cpu.set_irq(1, false);
+    cpu.breakpoints.clear();
+    cpu.exec(&mut sys, 20);
+    cpu.breakpoints.watch(WatchKind::Write, 0x0100, 0x01FF);
+    cpu.set_irq(1, true);
+    match cpu.exec(&mut sys, 1000) {
+        StopReason::Watchpoint(hit) => assert_eq!((hit.address, hit.write, hit.pc), (0x01FD, true, 0x0500)),
+        other => panic!("stopped for {:?}", other),
+    }
+}
+
+#[test]
+fn hit_counts_and_conditions() {
+    let (mut cpu, mut sys) = boot();
+    let id = cpu.breakpoints.break_at(0x0405);
+    cpu.breakpoints.get_mut(id).unwrap().ignore = 2;
+    assert_eq!(cpu.exec(&mut sys, 1000), StopReason::Breakpoint(0x0405));
+    assert_eq!(cpu.x.0, 2);
+    assert_eq!(cpu.breakpoints.get(id).unwrap().hits, 3);
+
+    let (mut cpu, mut sys) = boot();
+    let id = cpu.breakpoints.watch(WatchKind::Execute, 0x0400, 0x040B);
+    cpu.breakpoints.get_mut(id).unwrap().condition = Some(Condition::parse("x == 5 && pc == $0406").unwrap());
+    assert_eq!(cpu.exec(&mut sys, 1000), StopReason::Breakpoint(0x0406));
+    assert_eq!(cpu.x.0, 5);
+
+    // A disabled breakpoint doesn't stop anything, or count hits.
+    cpu.breakpoints.get_mut(id).unwrap().enabled = false;
+    let hits = cpu.breakpoints.get(id).unwrap().hits;
+    assert_eq!(cpu.exec(&mut sys, 1000), StopReason::BudgetExhausted);
+    assert_eq!(cpu.breakpoints.get(id).unwrap().hits, hits);
+}
+
+#[test]
+fn watchpoints_stop_after_the_access() {
+    let (mut cpu, mut sys) = boot();
+    let id = cpu.breakpoints.watch(WatchKind::Write, 0xD000, 0xD00F);
+    let reason = cpu.exec(&mut sys, 1000);
+    assert_eq!(reason, StopReason::Watchpoint(Hit { id, address: 0xD000, write: true, pc: 0x0402 }));
+    assert_eq!(cpu.pc.0, 0x0405);
+    cpu.breakpoints.clear();
+
+    let id = cpu.breakpoints.watch(WatchKind::ReadWrite, 0xD010, 0xD01F);
+    let reason = cpu.exec(&mut sys, 1000);
+    assert_eq!(reason, StopReason::Watchpoint(Hit { id, address: 0xD012, write: false, pc: 0x0406 }));
+    assert_eq!(cpu.pc.0, 0x0409);
+    cpu.breakpoints.clear();
+
+    // Fetching instructions and operands doesn't count as reading them.
+    cpu.breakpoints.watch(WatchKind::Read, 0x0400, 0x040B);
+    assert_eq!(cpu.exec(&mut sys, 1000), StopReason::BudgetExhausted);
+}
+
+#[test]
+fn watchpoint_conditions_see_the_state_after_the_instruction() {
+    let (mut cpu, mut sys) = boot();
+    let id = cpu.breakpoints.watch(WatchKind::Write, 0xD000, 0xD000);
+    cpu.breakpoints.get_mut(id).unwrap().condition = Some(Condition::parse("[$D012] > $80").unwrap());
+    assert_eq!(cpu.exec(&mut sys, 1000), StopReason::BudgetExhausted);
+
+    cpu.breakpoints.get_mut(id).unwrap().condition = Some(Condition::parse("A == $40 && [$D012] > 100").unwrap());
+    assert!(matches!(cpu.exec(&mut sys, 1000), StopReason::Watchpoint(_)));
+}
+
+#[test]
+fn condition_expressions() {
+    let (mut cpu, sys) = boot();
+    cpu.a.0 = 0x40;
+    cpu.x.0 = 3;
+    cpu.status.set_carry(true);
+    let eval = |text: &str| Condition::parse(text).unwrap().eval(&cpu, &sys);
+
+    assert_eq!(eval("1 + 2 * 3"), 7);
+    assert_eq!(eval("(1 << 4 | 1) == 17"), 1);
+    assert_eq!(eval("1 << 4 | 1 == 17"), 16);
+    assert_eq!(eval("a == $40 && X >= 3 && c"), 1);
+    assert_eq!(eval("!z || [$d012] < %10000000"), 1);
+    assert_eq!(eval("[$D000 + $12] - 1"), 0x7F);
+    assert_eq!(eval("-x & ~0"), -3);
+    assert_eq!(eval("pc / 0 + 7 % 0"), 0);
+    assert_eq!(eval("A != 64"), 0);
+
+    let err = Condition::parse("A == Q").unwrap_err();
+    assert_eq!(err.position, 5);
+    assert!(Condition::parse("[$D012 > 1").is_err());
+    assert!(Condition::parse("A =").is_err());
+    assert!(Condition::parse("A = 1").is_err());
+    assert_eq!(Condition::parse("  x == 5 ").unwrap().to_string(), "x == 5");
+}
@@ -0,0 +1,154 @@
+// The 65C02's own instructions, and the places where it behaves differently from the NMOS chips.
+// (With the "no-65c02" feature there's no 65C02 to test.)
+
+#![cfg(not(feature = "no-65c02"))]
+
+mod common;
+
+use common::FlatSystem;
+use fake6502::{CpuVariant, JamReason, StopReason, CPU};
+
+fn boot(program: &[u8]) -> (CPU, FlatSystem) {
+    common::boot(CpuVariant::Wdc65C02, program)
+}
+
+#[test]
+#[cfg(not(feature = "no-bcd"))]
+fn decimal_mode_flags() {
+    let program = [
+        0xF8,       // 0400  SED
+        0x18,       // 0401  CLC
+        0xA9, 0x99, // 0402  LDA #$99
+        0x69, 0x01, // 0404  ADC #$01
+        0x38,       // 0406  SEC
+        0xA9, 0x00, // 0407  LDA #$00
+        0xE9, 0x01, // 0409  SBC #$01
+    ];
+    let (mut cpu, mut sys) = boot(&program);
+    for _ in 0..3 {
+        cpu.step(&mut sys);
+    }
+
+    // N and Z come from the decimal result, at the cost of a cycle.
+    assert_eq!(cpu.step(&mut sys).cycles, 3);
+    assert_eq!(cpu.a.0, 0x00);
+    assert!(cpu.status.carry() && cpu.status.zero() && !cpu.status.negative());
+
+    cpu.step(&mut sys);
+    cpu.step(&mut sys);
+    assert_eq!(cpu.step(&mut sys).cycles, 3);
+    assert_eq!(cpu.a.0, 0x99);
+    assert!(!cpu.status.carry() && !cpu.status.zero() && cpu.status.negative());
+
+    // Whereas the NMOS chip sets Z from the binary sum, $9A.
+    let (mut nmos, mut sys) = common::boot(CpuVariant::Nmos6502, &program);
+    for _ in 0..3 {
+        nmos.step(&mut sys);
+    }
+    assert_eq!(nmos.step(&mut sys).cycles, 2);
+    assert_eq!(nmos.a.0, 0x00);
+    assert!(!nmos.status.zero());
+}
+
+#[test]
+fn bbr_and_bbs() {
+    let program = [
+        0x0F, 0x10, 0x03, // 0400  BBR0 $10,$0406 (bit 0 is set)
+        0x8F, 0x10, 0x01, // 0403  BBS0 $10,$0407
+        0xEA,             // 0406  NOP
+        0x9F, 0x10, 0xFC, // 0407  BBS1 $10,$0406 (bit 1 is clear)
+        0x1F, 0x10, 0xF8, // 040A  BBR1 $10,$0405
+    ];
+    let (mut cpu, mut sys) = boot(&program);
+    sys.mem[0x10] = 0x01;
+
+    let expected = [(0x0400, 5, false), (0x0403, 6, true), (0x0407, 5, false), (0x040A, 6, true)];
+    for &(pc, cycles, taken) in expected.iter() {
+        let r = cpu.step(&mut sys);
+        assert_eq!((r.pc, r.cycles, r.branch_taken), (pc, cycles, taken));
+    }
+    assert_eq!(cpu.pc.0, 0x0405);
+    // They only look at the bit.
+    assert_eq!(sys.mem[0x10], 0x01);
+}
+
+#[test]
+fn tsb_and_trb() {
+    let program = [
+        0xA9, 0x0F,       // 0400  LDA #$0F
+        0x04, 0x20,       // 0402  TSB $20
+        0x14, 0x20,       // 0404  TRB $20
+        0x0C, 0x00, 0x03, // 0406  TSB $0300
+        0x1C, 0x00, 0x03, // 0409  TRB $0300
+    ];
+    let (mut cpu, mut sys) = boot(&program);
+    sys.mem[0x20] = 0x30;
+    sys.mem[0x300] = 0xFF;
+    cpu.step(&mut sys);
+
+    // Z says whether any of A's bits were set before; the rest of the flags are left alone.
+    assert_eq!(cpu.step(&mut sys).cycles, 5);
+    assert_eq!((sys.mem[0x20], cpu.status.zero()), (0x3F, true));
+    assert_eq!(cpu.step(&mut sys).cycles, 5);
+    assert_eq!((sys.mem[0x20], cpu.status.zero()), (0x30, false));
+    assert_eq!(cpu.step(&mut sys).cycles, 6);
+    assert_eq!((sys.mem[0x300], cpu.status.zero()), (0xFF, false));
+    assert_eq!(cpu.step(&mut sys).cycles, 6);
+    assert_eq!((sys.mem[0x300], cpu.status.zero()), (0xF0, false));
+    assert_eq!(cpu.a.0, 0x0F);
+}
+
+#[test]
+fn wai_waits_for_an_interrupt_line() {
+    let (mut cpu, mut sys) = boot(&[0xCB, 0xE8]); // WAI, INX
+    cpu.step(&mut sys);
+    let target = cpu.clockticks + 100;
+    cpu.exec_until(&mut sys, target);
+    assert_eq!((cpu.pc.0, cpu.x.0), (0x0401, 0));
+    assert!(cpu.is_waiting());
+
+    // I is set, so the IRQ isn't taken, but it still ends the wait.
+    cpu.set_irq(1, true);
+    let r = cpu.step(&mut sys);
+    assert_eq!((r.pc, cpu.x.0), (0x0401, 1));
+    assert!(!cpu.is_waiting());
+}
+
+#[test]
+fn stp_stops_until_reset() {
+    let (mut cpu, mut sys) = boot(&[0xEA, 0xDB, 0xE8]); // NOP, STP, INX
+    assert_eq!(cpu.exec(&mut sys, 1000), StopReason::Jammed);
+    assert_eq!(cpu.jam_reason(), Some(JamReason::Stopped { pc: 0x0401 }));
+
+    // Not even an NMI gets it going.
+    cpu.set_nmi(true);
+    assert_eq!(cpu.exec(&mut sys, 1000), StopReason::Jammed);
+    assert_eq!(cpu.pc.0, 0x0402);
+
+    cpu.reset(&sys);
+    assert!(!cpu.is_jammed());
+    cpu.step(&mut sys);
+    assert_eq!(cpu.pc.0, 0x0401);
+}
+
+#[test]
+fn bra_stz_and_jmp_indirect() {
+    let program = [
+        0x80, 0x01,       // 0400  BRA $0403
+        0xEA,             // 0402  NOP
+        0x64, 0x20,       // 0403  STZ $20
+        0x6C, 0xFF, 0x10, // 0405  JMP ($10FF)
+    ];
+    let (mut cpu, mut sys) = boot(&program);
+    sys.mem[0x20] = 0xAA;
+    // The high byte comes from $1100, not $1000 as on the NMOS chip.
+    sys.load(0x10FF, &[0x34, 0x12]);
+    sys.mem[0x1000] = 0x56;
+
+    assert_eq!(cpu.step(&mut sys).cycles, 3);
+    assert_eq!(cpu.pc.0, 0x0403);
+    cpu.step(&mut sys);
+    assert_eq!(sys.mem[0x20], 0);
+    assert_eq!(cpu.step(&mut sys).cycles, 6);
+    assert_eq!(cpu.pc.0, 0x1234);
+}
@@ -0,0 +1,65 @@
+// What most of the integration tests need: a machine that's nothing but 64K of RAM, and a quick
+// way to get a program running in it.
+
+#![allow(dead_code)]
+
+use fake6502::{Backplane, Control, CpuVariant, CPU};
+
+// Where boot() puts the program, and points the reset vector.
+pub const PROGRAM_ADDR: u16 = 0x0400;
+
+pub struct FlatSystem {
+    pub mem: Vec<u8>,
+    // each_instr() asks exec() to stop once instructions_ran gets this far.
+    pub stop_after: Option<u64>,
+}
+
+impl FlatSystem {
+    pub fn new() -> FlatSystem {
+        FlatSystem { mem: vec![0; 0x10000], stop_after: None }
+    }
+
+    // Just 'bytes' at 'address', for tests that only need something to read code from.
+    pub fn holding(address: u16, bytes: &[u8]) -> FlatSystem {
+        let mut sys = FlatSystem::new();
+        sys.load(address, bytes);
+        sys
+    }
+
+    pub fn load(&mut self, address: u16, bytes: &[u8]) {
+        let start = address as usize;
+        self.mem[start..start + bytes.len()].copy_from_slice(bytes);
+    }
+
+    // Points the reset vector at 'address'.
+    pub fn set_reset_vector(&mut self, address: u16) {
+        self.load(0xFFFC, &address.to_le_bytes());
+    }
+}
+
+impl Backplane for FlatSystem {
+    fn read(&self, address: u16) -> u8 {
+        self.mem[address as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.mem[address as usize] = value;
+    }
+
+    fn each_instr(&mut self, cpu: &mut CPU) -> Control {
+        match self.stop_after {
+            Some(n) if cpu.instructions_ran >= n => Control::Stop(0),
+            _ => Control::Continue,
+        }
+    }
+}
+
+// A FlatSystem with 'program' at PROGRAM_ADDR, and a CPU that's been reset into it.
+pub fn boot(variant: CpuVariant, program: &[u8]) -> (CPU, FlatSystem) {
+    let mut sys = FlatSystem::new();
+    sys.load(PROGRAM_ADDR, program);
+    sys.set_reset_vector(PROGRAM_ADDR);
+    let mut cpu = CPU::new(variant);
+    cpu.reset(&sys);
+    (cpu, sys)
+}
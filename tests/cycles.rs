@@ -0,0 +1,156 @@
+mod common;
+
+use common::FlatSystem;
+use fake6502::{Backplane, Control, CpuVariant, CPU};
+
+// NOP, NOP, JMP $0400, forever. That's 2 + 2 + 3 cycles a time around.
+const PROGRAM: [u8; 5] = [0xEA, 0xEA, 0x4C, 0x00, 0x04];
+
+fn boot() -> (CPU, FlatSystem) {
+    common::boot(CpuVariant::Nmos6502, &PROGRAM)
+}
+
+#[test]
+fn counters_go_past_32_bits() {
+    let (mut cpu, mut sys) = boot();
+    cpu.clockticks = u64::from(u32::MAX) - 10;
+    cpu.instructions_ran = u64::from(u32::MAX) - 1;
+
+    let target = cpu.clockticks + 1000;
+    cpu.exec_until(&mut sys, target);
+
+    assert!(cpu.clockticks >= target);
+    assert!(cpu.clockticks > u64::from(u32::MAX));
+    assert!(cpu.instructions_ran > u64::from(u32::MAX));
+    assert_eq!(cpu.overshoot(), cpu.clockticks - target);
+    assert!(cpu.overshoot() < 3);
+}
+
+#[test]
+fn exec_until_past_target_runs_nothing() {
+    let (mut cpu, mut sys) = boot();
+    cpu.exec_until(&mut sys, 500);
+    let (ticks, ran) = (cpu.clockticks, cpu.instructions_ran);
+
+    cpu.exec_until(&mut sys, 100);
+    assert_eq!(cpu.clockticks, ticks);
+    assert_eq!(cpu.instructions_ran, ran);
+}
+
+#[test]
+fn exec_takes_overshoot_out_of_next_budget() {
+    let (mut cpu, mut sys) = boot();
+    let start = cpu.clockticks;
+
+    for i in 1..=1000u64 {
+        cpu.exec(&mut sys, 10);
+        assert!(cpu.overshoot() < 3);
+        assert_eq!(cpu.clockticks, start + i * 10 + cpu.overshoot());
+    }
+}
+
+// A taken branch costs a cycle more, two if it lands on another page, and cycle-accurate mode
+// has to agree with the tables about that.
+#[test]
+fn branch_cycles() {
+    let program = [
+        0xA9, 0x01,       // 0400  LDA #$01
+        0xD0, 0x00,       // 0402  BNE $0404
+        0xF0, 0x00,       // 0404  BEQ $0406 (not taken)
+        0x4C, 0xF0, 0x04, // 0406  JMP $04F0
+    ];
+    for &accurate in &[false, true] {
+        let (mut cpu, mut sys) = common::boot(CpuVariant::Nmos6502, &program);
+        sys.load(0x04F0, &[0xD0, 0x10]); // 04F0  BNE $0502
+        cpu.cycle_accurate = accurate;
+        let cycles: Vec<u32> = (0..5).map(|_| cpu.step(&mut sys).cycles).collect();
+        assert_eq!(cycles, [2, 3, 2, 3, 4], "cycle_accurate = {}", accurate);
+        assert_eq!(cpu.pc.0, 0x0502);
+    }
+}
+
+// The undocumented stores take back the cycle a page crossing would have cost; that mustn't
+// happen in cycle-accurate mode because of a page crossing from before it was switched on.
+#[test]
+fn cycle_accurate_ignores_earlier_penalties() {
+    let program = [
+        0xA2, 0x01,       // LDX #$01
+        0xBD, 0xFF, 0x04, // LDA $04FF,X
+        0x87, 0x10,       // SAX $10
+    ];
+    let (mut cpu, mut sys) = common::boot(CpuVariant::Nmos6502Illegal, &program);
+    assert_eq!(cpu.step(&mut sys).cycles, 2);
+    assert_eq!(cpu.step(&mut sys).cycles, 5);
+    cpu.cycle_accurate = true;
+    assert_eq!(cpu.step(&mut sys).cycles, 3);
+}
+
+// A FlatSystem that keeps a note of every tick() call.
+struct TickSystem {
+    inner: FlatSystem,
+    ticks: Vec<u32>,
+}
+
+impl Backplane for TickSystem {
+    fn read(&self, address: u16) -> u8 {
+        self.inner.read(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.inner.write(address, value)
+    }
+
+    fn each_instr(&mut self, cpu: &mut CPU) -> Control {
+        self.inner.each_instr(cpu)
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        self.ticks.push(cycles);
+    }
+}
+
+#[test]
+fn ticks_add_up() {
+    let program = [
+        0xA2, 0x01,       // 0400  LDX #$01
+        0xBD, 0xFF, 0x04, // 0402  LDA $04FF,X (crosses a page)
+        0x9D, 0x00, 0x03, // 0405  STA $0300,X
+        0xE6, 0x10,       // 0408  INC $10
+        0xD0, 0x00,       // 040A  BNE $040C
+        0x20, 0x20, 0x04, // 040C  JSR $0420
+    ];
+    for &accurate in &[false, true] {
+        let (mut cpu, inner) = common::boot(CpuVariant::Nmos6502, &program);
+        let mut sys = TickSystem { inner, ticks: Vec::new() };
+        sys.inner.load(0x0420, &[0x60]); // 0420  RTS
+        sys.inner.load(0x0500, &[0x40]); // 0500  RTI
+        sys.inner.load(0xFFFA, &[0x00, 0x05]);
+        cpu.cycle_accurate = accurate;
+        let start = cpu.clockticks;
+
+        let mut cycles = Vec::new();
+        for i in 0..8 {
+            // An NMI on the way into the RTS.
+            if i == 6 {
+                cpu.set_nmi(true);
+            }
+            cycles.push(cpu.step(&mut sys).cycles);
+        }
+        assert_eq!(cycles, [2, 5, 5, 5, 3, 6, 7 + 6, 6], "cycle_accurate = {}", accurate);
+
+        let total: u32 = sys.ticks.iter().sum();
+        assert_eq!(u64::from(total), cpu.clockticks - start);
+        if accurate {
+            // One per bus access.
+            assert!(sys.ticks.iter().all(|&t| t == 1));
+        } else {
+            // One per instruction, interrupt sequence included.
+            assert_eq!(sys.ticks, cycles);
+        }
+
+        // And exec() the same, whatever the program's wandered off into by now.
+        cpu.exec(&mut sys, 1000);
+        let total: u32 = sys.ticks.iter().sum();
+        assert_eq!(u64::from(total), cpu.clockticks - start);
+    }
+}
@@ -0,0 +1,254 @@
+// Runs the Rust port and the original fake6502.c (built by build.rs) side by side, one
+// instruction at a time, and stops at the first place they disagree about the registers, the
+// memory writes an instruction made, or the number of cycles it took. Only the last value written
+// to each address counts, since the C version's SAX goes by way of STA and STX and so writes the
+// same byte three times.
+//
+// fake6502.c is built with UNDOCUMENTED and NES_CPU defined, so it's compared against the Ricoh
+// 2A03 variant. The opcodes the C version doesn't really implement (the JAMs and the less common
+// undocumented ones, which it treats as NOPs) end a run rather than count as a divergence, and so
+// does every undocumented opcode with the "no-undocumented" feature.
+//
+// DIFF_SEED and DIFF_PROGRAMS control the random programs; DIFF_ROMS is a list of extra ROM images
+// to run, separated by ':', each loaded so that it ends at $FFFF and started from its reset vector.
+
+#![cfg(fake6502_c)]
+
+use fake6502::disasm::is_undocumented;
+use fake6502::{Backplane, Control, CpuVariant, Status, CPU};
+
+use std::cell::RefCell;
+use std::num::Wrapping;
+use std::sync::Mutex;
+
+// The C core's state is all globals.
+#[link(name = "fake6502c", kind = "static")]
+extern "C" {
+    #[link_name = "pc"]
+    static mut C_PC: u16;
+    #[link_name = "sp"]
+    static mut C_SP: u8;
+    #[link_name = "a"]
+    static mut C_A: u8;
+    #[link_name = "x"]
+    static mut C_X: u8;
+    #[link_name = "y"]
+    static mut C_Y: u8;
+    #[link_name = "status"]
+    static mut C_STATUS: u8;
+    #[link_name = "clockticks6502"]
+    static mut C_CLOCKTICKS: u32;
+    fn step6502();
+}
+
+// Memory as seen by the C core, which calls back into read6502() and write6502() below.
+thread_local! {
+    static C_MACHINE: RefCell<Machine> = RefCell::new(Machine::new());
+}
+
+// ...and since it's globals, only one test can be driving it at a time.
+static C_CORE: Mutex<()> = Mutex::new(());
+
+#[no_mangle]
+pub extern "C" fn read6502(address: u16) -> u8 {
+    C_MACHINE.with(|m| m.borrow().mem[address as usize])
+}
+
+#[no_mangle]
+pub extern "C" fn write6502(address: u16, value: u8) {
+    C_MACHINE.with(|m| m.borrow_mut().write(address, value))
+}
+
+const SKIPPED_OPCODES: [u8; 25] = [
+    // JAM
+    0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2,
+    // ANC, ALR, ARR, ANE, SHA, TAS, SHY, SHX, LXA, LAS, SBX
+    0x0B, 0x2B, 0x4B, 0x6B, 0x8B, 0x93, 0x9F, 0x9B, 0x9C, 0x9E, 0xAB, 0xBB, 0xCB,
+];
+
+#[derive(Clone)]
+struct Machine {
+    mem: Vec<u8>,
+    writes: Vec<(u16, u8)>,
+}
+
+impl Machine {
+    fn new() -> Machine {
+        Machine { mem: vec![0; 0x10000], writes: Vec::new() }
+    }
+}
+
+impl Backplane for Machine {
+    fn read(&self, address: u16) -> u8 {
+        self.mem[address as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.mem[address as usize] = value;
+        self.writes.push((address, value));
+    }
+
+    fn each_instr(&mut self, _cpu: &mut CPU) -> Control {
+        Control::Continue
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Snapshot {
+    pc: u16,
+    sp: u8,
+    a: u8,
+    x: u8,
+    y: u8,
+    // Without the break and constant bits, which the two versions handle differently and which
+    // don't exist in the real register anyway.
+    p: u8,
+    cycles: u64,
+    writes: Vec<(u16, u8)>,
+}
+
+// The last value written to each address, in the order the addresses were last written.
+fn last_writes(writes: &[(u16, u8)]) -> Vec<(u16, u8)> {
+    let mut last: Vec<(u16, u8)> = Vec::new();
+    for &(address, value) in writes {
+        last.retain(|&(a, _)| a != address);
+        last.push((address, value));
+    }
+    last
+}
+
+// Xorshift; nothing fancy needed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+fn env_num(var: &str, default: u64) -> u64 {
+    match std::env::var(var) {
+        Ok(v) => v.parse().unwrap_or_else(|_| panic!("{} isn't a number: {:?}", var, v)),
+        Err(_) => default,
+    }
+}
+
+// Runs both cores from the same memory image and start address for up to 'steps' instructions.
+fn lockstep(name: &str, image: &[u8], start: u16, steps: u64) {
+    let _guard = C_CORE.lock().unwrap_or_else(|e| e.into_inner());
+
+    let mut rust_sys = Machine::new();
+    rust_sys.mem.copy_from_slice(image);
+    let mut cpu = CPU::new(CpuVariant::Ricoh2A03);
+    cpu.pc = Wrapping(start);
+    cpu.sp = Wrapping(0xFD);
+    cpu.status = Status::from_bits(0x24);
+    cpu.do_callback = false;
+
+    C_MACHINE.with(|m| *m.borrow_mut() = rust_sys.clone());
+    unsafe {
+        C_PC = start;
+        C_SP = 0xFD;
+        C_A = 0;
+        C_X = 0;
+        C_Y = 0;
+        C_STATUS = 0x24;
+        C_CLOCKTICKS = 0;
+    }
+    let rust_start = cpu.clockticks;
+
+    let mut history: Vec<String> = Vec::new();
+    for step in 0..steps {
+        let at = cpu.pc.0;
+        let opcode = rust_sys.mem[at as usize];
+        if SKIPPED_OPCODES.contains(&opcode) ||
+           (cfg!(feature = "no-undocumented") && is_undocumented(CpuVariant::Ricoh2A03, opcode)) {
+            return;
+        }
+
+        rust_sys.writes.clear();
+        cpu.step(&mut rust_sys);
+        let ours = Snapshot {
+            pc: cpu.pc.0,
+            sp: cpu.sp.0,
+            a: cpu.a.0,
+            x: cpu.x.0,
+            y: cpu.y.0,
+            p: cpu.status.bits() & 0xCF,
+            cycles: cpu.clockticks - rust_start,
+            writes: last_writes(&rust_sys.writes),
+        };
+
+        C_MACHINE.with(|m| m.borrow_mut().writes.clear());
+        let theirs = unsafe {
+            step6502();
+            Snapshot {
+                pc: C_PC,
+                sp: C_SP,
+                a: C_A,
+                x: C_X,
+                y: C_Y,
+                p: C_STATUS & 0xCF,
+                cycles: C_CLOCKTICKS as u64,
+                writes: C_MACHINE.with(|m| last_writes(&m.borrow().writes)),
+            }
+        };
+
+        history.push(format!("{:04X}: {:02X} {:02X} {:02X}", at, opcode,
+                             rust_sys.mem[at.wrapping_add(1) as usize], rust_sys.mem[at.wrapping_add(2) as usize]));
+        if history.len() > 16 {
+            history.remove(0);
+        }
+
+        if ours != theirs {
+            panic!("{}: diverged at step {} (${:02X} at ${:04X})\n  rust: {:X?}\n  C:    {:X?}\nlast instructions:\n  {}",
+                   name, step, opcode, at, ours, theirs, history.join("\n  "));
+        }
+    }
+}
+
+#[test]
+fn random_programs() {
+    let mut rng = Rng(env_num("DIFF_SEED", 0x6502_6502_6502_6502) | 1);
+    let programs = env_num("DIFF_PROGRAMS", 200);
+
+    for n in 0..programs {
+        let mut image = vec![0u8; 0x10000];
+        for b in image.iter_mut() {
+            *b = rng.next() as u8;
+            // With the undocumented opcodes compiled out the Rust side runs them as NOPs, and the
+            // C side doesn't. lockstep() stops at one, so keep them out of the program to begin
+            // with (a program can still store one somewhere and run it).
+            while cfg!(feature = "no-undocumented") && is_undocumented(CpuVariant::Ricoh2A03, *b) {
+                *b = rng.next() as u8;
+            }
+        }
+        let start = rng.next() as u16;
+        lockstep(&format!("random program {}", n), &image, start, 10_000);
+    }
+}
+
+#[test]
+fn functional_test_image() {
+    let image = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/test.bin")).unwrap();
+    lockstep("test.bin", &image, 0x0400, 2_000_000);
+}
+
+#[test]
+fn rom_images() {
+    let roms = match std::env::var("DIFF_ROMS") {
+        Ok(r) => r,
+        Err(_) => return,
+    };
+    for path in roms.split(':').filter(|p| !p.is_empty()) {
+        let rom = std::fs::read(path).unwrap_or_else(|e| panic!("can't read {}: {}", path, e));
+        assert!(rom.len() <= 0x10000, "{} is bigger than 64K", path);
+        let mut image = vec![0u8; 0x10000];
+        image[0x10000 - rom.len()..].copy_from_slice(&rom);
+        let start = image[0xFFFC] as u16 | (image[0xFFFD] as u16) << 8;
+        lockstep(path, &image, start, 5_000_000);
+    }
+}
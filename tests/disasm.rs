@@ -0,0 +1,94 @@
+mod common;
+
+use common::FlatSystem;
+use fake6502::disasm::{decode, disassemble, Options};
+use fake6502::{AddrMode, CpuVariant};
+
+fn one(variant: CpuVariant, bytes: &[u8]) -> String {
+    decode(&FlatSystem::holding(0x0400, bytes), 0x0400, variant).to_string()
+}
+
+#[test]
+fn addressing_modes() {
+    let nmos = CpuVariant::Nmos6502;
+    assert_eq!(one(nmos, &[0xEA]), "NOP");
+    assert_eq!(one(nmos, &[0x0A]), "ASL A");
+    assert_eq!(one(nmos, &[0xA9, 0x01]), "LDA #$01");
+    assert_eq!(one(nmos, &[0xA5, 0x10]), "LDA $10");
+    assert_eq!(one(nmos, &[0xB5, 0x10]), "LDA $10,X");
+    assert_eq!(one(nmos, &[0xB6, 0x10]), "LDX $10,Y");
+    assert_eq!(one(nmos, &[0xAD, 0x34, 0x12]), "LDA $1234");
+    assert_eq!(one(nmos, &[0xBD, 0x34, 0x12]), "LDA $1234,X");
+    assert_eq!(one(nmos, &[0xB9, 0x34, 0x12]), "LDA $1234,Y");
+    assert_eq!(one(nmos, &[0x6C, 0xFC, 0xFF]), "JMP ($FFFC)");
+    assert_eq!(one(nmos, &[0xA1, 0x20]), "LDA ($20,X)");
+    assert_eq!(one(nmos, &[0xB1, 0x20]), "LDA ($20),Y");
+    // Branch targets are worked out from the address after the branch.
+    assert_eq!(one(nmos, &[0xD0, 0xFE]), "BNE $0400");
+    assert_eq!(one(nmos, &[0x10, 0x10]), "BPL $0412");
+}
+
+#[cfg(not(feature = "no-65c02"))]
+#[test]
+fn cmos_addressing_modes() {
+    let cmos = CpuVariant::Wdc65C02;
+    assert_eq!(one(cmos, &[0xB2, 0x20]), "LDA ($20)");
+    assert_eq!(one(cmos, &[0x7C, 0x00, 0x80]), "JMP ($8000,X)");
+    assert_eq!(one(cmos, &[0x8F, 0x12, 0xFD]), "BBS0 $12,$0400");
+    assert_eq!(one(cmos, &[0x77, 0x12]), "RMB7 $12");
+}
+
+#[test]
+fn instruction_fields() {
+    let i = decode(&FlatSystem::holding(0x0400, &[0xBD, 0x34, 0x12]), 0x0400, CpuVariant::Nmos6502);
+    assert_eq!(i.mnemonic, "lda");
+    assert_eq!(i.mode, AddrMode::AbsoluteX);
+    assert_eq!(i.operand, 0x1234);
+    assert_eq!(i.length, 3);
+    assert_eq!(i.cycles, 4);
+    assert!(!i.undocumented);
+}
+
+#[cfg(not(feature = "no-undocumented"))]
+#[test]
+fn illegal_opcodes() {
+    let rom = FlatSystem::holding(0x0400, &[0xA7, 0x10, 0x02]);
+    let i = decode(&rom, 0x0400, CpuVariant::Nmos6502Illegal);
+    assert!(i.undocumented);
+    assert_eq!(i.to_string(), "LAX $10");
+
+    let plain = Options { illegal_mnemonics: false, ..Options::default() };
+    assert_eq!(i.format(&plain), ".BYTE $A7");
+    assert_eq!(disassemble(&rom, 0x0400, 2, CpuVariant::Nmos6502Illegal, &Options::default()),
+               "LAX $10\nJAM\n");
+}
+
+#[test]
+fn illegal_opcodes_as_nops() {
+    // A plain NMOS 6502 runs $A7 as a NOP, operand and all.
+    let rom = FlatSystem::holding(0x0400, &[0xA7, 0x10, 0x02]);
+    let i = decode(&rom, 0x0400, CpuVariant::Nmos6502);
+    assert!(i.undocumented);
+    assert_eq!((i.to_string(), i.length), ("NOP $10".to_string(), 2));
+    assert_eq!(disassemble(&rom, 0x0400, 2, CpuVariant::Nmos6502, &Options::default()), "NOP $10\nJAM\n");
+}
+
+#[cfg(not(feature = "no-65c02"))]
+#[test]
+fn cmos_has_no_illegal_opcodes() {
+    // $07 is a perfectly good instruction (RMB0) on the 65C02.
+    assert!(!decode(&FlatSystem::holding(0x0400, &[0x07, 0x10]), 0x0400, CpuVariant::Wdc65C02).undocumented);
+}
+
+#[test]
+fn hex_dump() {
+    let rom = FlatSystem::holding(0x0400, &[0xA9, 0x01, 0x8D, 0x00, 0x02, 0x60]);
+    let options = Options { hex_dump: true, ..Options::default() };
+    assert_eq!(disassemble(&rom, 0x0400, 3, CpuVariant::Nmos6502, &options),
+               "0400  A9 01     LDA #$01\n\
+                0402  8D 00 02  STA $0200\n\
+                0405  60        RTS\n");
+
+    let lower = Options { uppercase: false, ..Options::default() };
+    assert_eq!(disassemble(&rom, 0x0400, 1, CpuVariant::Nmos6502, &lower), "lda #$01\n");
+}
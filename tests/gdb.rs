@@ -0,0 +1,193 @@
+mod common;
+
+use fake6502::gdb::GdbStub;
+use fake6502::CpuVariant;
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+const PROGRAM: [u8; 12] = [
+    0xA9, 0x42,       // 0400  LDA #$42
+    0x8D, 0x00, 0x02, // 0402  STA $0200
+    0xE8,             // 0405  INX
+    0xAD, 0x00, 0x02, // 0406  LDA $0200
+    0x4C, 0x09, 0x04, // 0409  JMP $0409
+];
+
+// Runs a stub on a thread for one session and hands back the memory at the end. 'stop_after' is
+// passed on to the FlatSystem, whose each_instr() stops exec() there.
+fn start_server(stop_after: Option<u64>) -> (TcpStream, thread::JoinHandle<Vec<u8>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (mut cpu, mut sys) = common::boot(CpuVariant::Nmos6502, &PROGRAM);
+        sys.stop_after = stop_after;
+
+        let (stream, _) = listener.accept().unwrap();
+        stream.set_nodelay(true).unwrap();
+        GdbStub::new(stream).serve(&mut cpu, &mut sys).unwrap();
+        sys.mem
+    });
+    let stream = TcpStream::connect(address).unwrap();
+    stream.set_nodelay(true).unwrap();
+    (stream, server)
+}
+
+// The client end of the protocol, as GDB would do it.
+struct Client {
+    stream: TcpStream,
+    ack: bool,
+}
+
+impl Client {
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [0];
+        self.stream.read_exact(&mut byte).unwrap();
+        byte[0]
+    }
+
+    fn send(&mut self, data: &str) {
+        let sum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        write!(self.stream, "${}#{:02x}", data, sum).unwrap();
+        if self.ack {
+            assert_eq!(self.read_byte(), b'+');
+        }
+    }
+
+    fn reply(&mut self) -> String {
+        assert_eq!(self.read_byte(), b'$');
+        let mut data = Vec::new();
+        loop {
+            match self.read_byte() {
+                b'#' => break,
+                c => data.push(c),
+            }
+        }
+        let checksum = [self.read_byte(), self.read_byte()];
+        let sum = data.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+        assert_eq!(std::str::from_utf8(&checksum).unwrap(), format!("{:02x}", sum));
+        if self.ack {
+            self.stream.write_all(b"+").unwrap();
+        }
+        String::from_utf8(data).unwrap()
+    }
+
+    fn request(&mut self, data: &str) -> String {
+        self.send(data);
+        self.reply()
+    }
+}
+
+fn connect() -> (Client, thread::JoinHandle<Vec<u8>>) {
+    connect_stopping_after(None)
+}
+
+fn connect_stopping_after(stop_after: Option<u64>) -> (Client, thread::JoinHandle<Vec<u8>>) {
+    let (stream, server) = start_server(stop_after);
+    let mut client = Client { stream, ack: true };
+    assert!(client.request("qSupported:swbreak+").contains("QStartNoAckMode+"));
+    assert_eq!(client.request("QStartNoAckMode"), "OK");
+    client.ack = false;
+    (client, server)
+}
+
+#[test]
+fn registers_and_memory() {
+    let (mut client, server) = connect();
+
+    let regs = client.request("g");
+    assert_eq!(regs.len(), 14);
+    assert_eq!(&regs[8..], "fd0004");
+
+    assert_eq!(client.request("s"), "S05");
+    assert_eq!(client.request("p0"), "42");
+    assert_eq!(client.request("p5"), "0204");
+
+    assert_eq!(client.request("P1=7f"), "OK");
+    assert_eq!(client.request("p1"), "7f");
+    let regs = client.request("g");
+    assert_eq!(client.request(&format!("G{}0004", &regs[..10])), "OK");
+    assert_eq!(client.request("p5"), "0004");
+
+    assert_eq!(client.request("M300,3:abcdef"), "OK");
+    assert_eq!(client.request("m300,3"), "abcdef");
+    assert_eq!(client.request("m400,2"), "a942");
+    assert_eq!(client.request("M300,2:ab"), "E01");
+
+    assert!(client.request("qXfer:features:read:target.xml:0,fff").starts_with("l<?xml"));
+    assert_eq!(client.request("vMustReplyEmpty"), "");
+
+    assert_eq!(client.request("D"), "OK");
+    let mem = server.join().unwrap();
+    assert_eq!(&mem[0x300..0x303], &[0xAB, 0xCD, 0xEF]);
+}
+
+#[test]
+fn large_writes() {
+    let (mut client, server) = connect();
+
+    // Writes can be longer than a read reply has room for.
+    let data: Vec<u8> = (0..0x900).map(|i| i as u8).collect();
+    let hex: String = data.iter().map(|b| format!("{:02x}", b)).collect();
+    assert_eq!(client.request(&format!("M1000,900:{}", hex)), "OK");
+    // ...but anything past the end of memory is dropped.
+    assert_eq!(client.request("Mfffe,4:11223344"), "OK");
+    assert_eq!(client.request("mfffe,4"), "1122");
+
+    assert_eq!(client.request("D"), "OK");
+    let mem = server.join().unwrap();
+    assert_eq!(&mem[0x1000..0x1900], &data[..]);
+    assert_eq!(&mem[..2], &[0, 0]);
+}
+
+#[test]
+fn host_stops_continue() {
+    // each_instr() asks to stop once the STA has run.
+    let (mut client, server) = connect_stopping_after(Some(2));
+
+    assert_eq!(client.request("c"), "S05");
+    assert_eq!(client.request("p5"), "0504");
+
+    assert_eq!(client.request("D"), "OK");
+    server.join().unwrap();
+}
+
+#[test]
+fn breakpoints_and_watchpoints() {
+    let (mut client, server) = connect();
+
+    assert_eq!(client.request("Z0,405,1"), "OK");
+    assert_eq!(client.request("c"), "S05");
+    assert_eq!(client.request("p5"), "0504");
+    assert_eq!(client.request("z0,405,1"), "OK");
+
+    // The STA has already happened, so watch the LDA read it back.
+    assert_eq!(client.request("Z3,1ff,4"), "OK");
+    assert_eq!(client.request("c"), "T05rwatch:0200;");
+    assert_eq!(client.request("p5"), "0904");
+    assert_eq!(client.request("z3,1ff,4"), "OK");
+
+    // Back to the start to catch the write.
+    assert_eq!(client.request("Z2,200,1"), "OK");
+    assert_eq!(client.request("c400"), "T05watch:0200;");
+    assert_eq!(client.request("p5"), "0504");
+    assert_eq!(client.request("z2,200,1"), "OK");
+
+    assert_eq!(client.request("D"), "OK");
+    server.join().unwrap();
+}
+
+#[test]
+fn break_while_running() {
+    let (mut client, server) = connect();
+
+    // Nothing stops the JMP * loop but the client.
+    client.send("c");
+    client.stream.write_all(&[0x03]).unwrap();
+    assert_eq!(client.reply(), "S02");
+    assert_eq!(client.request("p5"), "0904");
+
+    assert_eq!(client.request("D"), "OK");
+    server.join().unwrap();
+}
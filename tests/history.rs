@@ -0,0 +1,127 @@
+mod common;
+
+use common::FlatSystem;
+use fake6502::{CpuVariant, CPU};
+
+// Six instructions, 17 cycles a time around, leaving X in $0200 and X*2 in $0201.
+const PROGRAM: [u8; 12] = [
+    0xE8,             // 0400  INX
+    0x8A,             // 0401  TXA
+    0x8D, 0x00, 0x02, // 0402  STA $0200
+    0x0A,             // 0405  ASL A
+    0x8D, 0x01, 0x02, // 0406  STA $0201
+    0x4C, 0x00, 0x04, // 0409  JMP $0400
+];
+
+fn boot() -> (CPU, FlatSystem) {
+    common::boot(CpuVariant::Nmos6502, &PROGRAM)
+}
+
+// The registers and the program's two bytes of output.
+fn state(cpu: &CPU, sys: &FlatSystem) -> (u16, u8, u8, u8, u8, u64, u8, u8) {
+    (cpu.pc.0, cpu.a.0, cpu.x.0, cpu.sp.0, cpu.status.bits(), cpu.clockticks, sys.mem[0x200], sys.mem[0x201])
+}
+
+#[test]
+fn step_back_undoes_registers_and_memory() {
+    let (mut cpu, mut sys) = boot();
+    assert!(!cpu.step_back(&mut sys));
+
+    cpu.record_history(1 << 20);
+    let start = state(&cpu, &sys);
+    for _ in 0..6 {
+        cpu.step(&mut sys);
+    }
+    let once = state(&cpu, &sys);
+    for _ in 0..6 {
+        cpu.step(&mut sys);
+    }
+    let twice = state(&cpu, &sys);
+    assert_eq!((twice.2, twice.6, twice.7), (2, 2, 4));
+
+    for _ in 0..6 {
+        assert!(cpu.step_back(&mut sys));
+    }
+    assert_eq!(state(&cpu, &sys), once);
+
+    // Going forward again ends up in the same place.
+    for _ in 0..6 {
+        cpu.step(&mut sys);
+    }
+    assert_eq!(state(&cpu, &sys), twice);
+
+    while cpu.step_back(&mut sys) {}
+    assert_eq!(state(&cpu, &sys), start);
+    assert!(cpu.history().unwrap().is_empty());
+}
+
+// Cycles spent stalled on RDY don't get entries of their own, but go with the instruction after.
+#[test]
+fn step_back_goes_back_past_a_stall() {
+    let (mut cpu, mut sys) = boot();
+    cpu.record_history(1 << 20);
+    cpu.step(&mut sys);
+    let before = state(&cpu, &sys);
+
+    cpu.hold_rdy(5);
+    for _ in 0..6 {
+        cpu.step(&mut sys);
+    }
+    assert_eq!(cpu.history().unwrap().len(), 2);
+    assert_eq!(cpu.rdy_stalled_cycles, 5);
+
+    assert!(cpu.step_back(&mut sys));
+    assert_eq!(state(&cpu, &sys), before);
+    assert_eq!(cpu.rdy_stalled_cycles, 0);
+}
+
+#[test]
+fn rewind_to_last_write() {
+    let (mut cpu, mut sys) = boot();
+    cpu.record_history(1 << 20);
+    cpu.exec(&mut sys, 1000);
+
+    assert_eq!(cpu.rewind_to_write(&mut sys, 0x0200), Some(0x0402));
+    assert_eq!(cpu.pc.0, 0x0402);
+    // The STA is about to store A over the previous value.
+    assert_eq!(sys.mem[0x200], cpu.a.0.wrapping_sub(1));
+
+    let before = state(&cpu, &sys);
+    assert_eq!(cpu.rewind_to_write(&mut sys, 0x0300), None);
+    assert_eq!(state(&cpu, &sys), before);
+}
+
+#[test]
+fn rewind_cycles_matches_a_fresh_run() {
+    let (mut cpu, mut sys) = boot();
+    cpu.record_history(1 << 20);
+    cpu.exec(&mut sys, 1000);
+    let end = cpu.clockticks;
+
+    let back = cpu.rewind_cycles(&mut sys, 500);
+    assert!(back >= 500);
+    assert_eq!(cpu.clockticks, end - back);
+
+    let (mut fresh, mut fresh_sys) = boot();
+    while fresh.clockticks < cpu.clockticks {
+        fresh.step(&mut fresh_sys);
+    }
+    assert_eq!(state(&fresh, &fresh_sys), state(&cpu, &sys));
+}
+
+#[test]
+fn history_stays_within_its_budget() {
+    let (mut cpu, mut sys) = boot();
+    cpu.record_history(4096);
+    cpu.exec(&mut sys, 100_000);
+
+    let history = cpu.history().unwrap();
+    assert!(history.bytes() <= 4096);
+    assert!(!history.is_empty());
+    let oldest = history.oldest_cycle().unwrap();
+    assert!(oldest > 90_000);
+
+    cpu.rewind_cycles(&mut sys, u64::MAX);
+    assert_eq!(cpu.clockticks, oldest);
+    assert!(!cpu.step_back(&mut sys));
+}
@@ -0,0 +1,271 @@
+mod common;
+
+use common::FlatSystem;
+use fake6502::{Backplane, Control, CpuVariant, Status, CPU};
+
+// The IRQ handler counts in X and the NMI handler in Y, and both go straight back.
+const IRQ_HANDLER: u16 = 0x0500;
+const NMI_HANDLER: u16 = 0x0600;
+
+fn boot(program: &[u8], cycle_accurate: bool) -> (CPU, FlatSystem) {
+    let (mut cpu, mut sys) = common::boot(CpuVariant::Nmos6502, program);
+    sys.load(IRQ_HANDLER, &[0xE8, 0x40]); // INX, RTI
+    sys.load(NMI_HANDLER, &[0xC8, 0x40]); // INY, RTI
+    sys.load(0xFFFE, &IRQ_HANDLER.to_le_bytes());
+    sys.load(0xFFFA, &NMI_HANDLER.to_le_bytes());
+    cpu.cycle_accurate = cycle_accurate;
+    (cpu, sys)
+}
+
+// Where each of the next 'n' steps fetched its opcode from.
+fn trace(cpu: &mut CPU, sys: &mut FlatSystem, n: usize) -> Vec<u16> {
+    (0..n).map(|_| cpu.step(sys).pc).collect()
+}
+
+#[test]
+fn irq_is_level_triggered() {
+    let program = [
+        0x58,             // 0400  CLI
+        0xEA,             // 0401  NOP
+        0xEA,             // 0402  NOP
+        0x4C, 0x02, 0x04, // 0403  JMP $0402
+    ];
+    for cycle_accurate in [false, true] {
+        let (mut cpu, mut sys) = boot(&program, cycle_accurate);
+        cpu.set_irq(1, true);
+
+        // Nothing in the handler quiets the device, so it's back in as soon as RTI clears I.
+        assert_eq!(trace(&mut cpu, &mut sys, 6), [0x0400, 0x0401, 0x0500, 0x0501, 0x0500, 0x0501]);
+        assert_eq!(cpu.x.0, 2);
+
+        // A second device keeps the line asserted after the first lets go.
+        cpu.set_irq(2, true);
+        cpu.set_irq(1, false);
+        assert!(cpu.irq_line());
+        assert_eq!(trace(&mut cpu, &mut sys, 2), [0x0500, 0x0501]);
+
+        cpu.set_irq(2, false);
+        assert!(!cpu.irq_line());
+        assert_eq!(trace(&mut cpu, &mut sys, 3), [0x0402, 0x0403, 0x0402]);
+        assert_eq!(cpu.x.0, 3);
+    }
+}
+
+#[test]
+fn irq_is_masked_by_i() {
+    // I is still set from the reset.
+    let (mut cpu, mut sys) = boot(&[0xEA, 0xEA, 0xEA], false);
+    cpu.set_irq(1, true);
+    assert_eq!(trace(&mut cpu, &mut sys, 3), [0x0400, 0x0401, 0x0402]);
+    assert_eq!(cpu.x.0, 0);
+}
+
+#[test]
+fn nmi_is_edge_triggered() {
+    let program = [
+        0xEA,             // 0400  NOP
+        0x4C, 0x00, 0x04, // 0401  JMP $0400
+    ];
+    for cycle_accurate in [false, true] {
+        let (mut cpu, mut sys) = boot(&program, cycle_accurate);
+
+        // Taken even though I is set, and only once however long the line stays asserted.
+        cpu.set_nmi(true);
+        let first = cpu.step(&mut sys);
+        assert_eq!(first.pc, NMI_HANDLER);
+        assert_eq!(first.cycles, 7 + 2);
+        // The pushed status has the break flag clear.
+        assert_eq!(sys.mem[0x01FB] & 0x10, 0);
+        assert_eq!(trace(&mut cpu, &mut sys, 4), [0x0601, 0x0400, 0x0401, 0x0400]);
+
+        // Asserting it again without letting go first does nothing either.
+        cpu.set_nmi(true);
+        assert_eq!(trace(&mut cpu, &mut sys, 1), [0x0401]);
+
+        cpu.set_nmi(false);
+        cpu.set_nmi(true);
+        assert_eq!(trace(&mut cpu, &mut sys, 2), [0x0600, 0x0601]);
+        assert_eq!(cpu.y.0, 2);
+    }
+}
+
+#[test]
+fn nmi_wins_over_irq() {
+    let (mut cpu, mut sys) = boot(&[0x58, 0xEA, 0xEA], false);
+    cpu.step(&mut sys);
+    cpu.step(&mut sys);
+    cpu.set_irq(1, true);
+    cpu.set_nmi(true);
+    // The NMI handler's first instruction runs before the IRQ gets a look in, since the NMI
+    // sequence sets I.
+    assert_eq!(trace(&mut cpu, &mut sys, 3), [0x0600, 0x0601, 0x0500]);
+}
+
+// CLI, SEI and PLP change I too late for the interrupt poll that comes at the end of them, so an
+// IRQ sees the old value for one more instruction.
+#[test]
+fn cli_lets_one_more_instruction_run() {
+    let (mut cpu, mut sys) = boot(&[0x58, 0xEA, 0xEA], false);
+    cpu.set_irq(1, true);
+    assert_eq!(trace(&mut cpu, &mut sys, 3), [0x0400, 0x0401, 0x0500]);
+}
+
+#[test]
+fn sei_lets_one_irq_through() {
+    let program = [
+        0x58, // 0400  CLI
+        0x78, // 0401  SEI
+        0xEA, // 0402  NOP
+        0xEA, // 0403  NOP
+    ];
+    let (mut cpu, mut sys) = boot(&program, false);
+    assert_eq!(trace(&mut cpu, &mut sys, 2), [0x0400, 0x0401]);
+    assert!(cpu.status.interrupt());
+
+    cpu.set_irq(1, true);
+    assert_eq!(trace(&mut cpu, &mut sys, 1), [0x0500]);
+    // The handler returns with I set, and that one sticks.
+    assert_eq!(trace(&mut cpu, &mut sys, 3), [0x0501, 0x0402, 0x0403]);
+    assert_eq!(cpu.x.0, 1);
+}
+
+#[test]
+fn plp_lets_one_more_instruction_run() {
+    let program = [
+        0xA9, 0x00, // 0400  LDA #$00
+        0x48,       // 0402  PHA
+        0x28,       // 0403  PLP
+        0xEA,       // 0404  NOP
+        0xEA,       // 0405  NOP
+    ];
+    let (mut cpu, mut sys) = boot(&program, false);
+    cpu.set_irq(1, true);
+    assert_eq!(trace(&mut cpu, &mut sys, 5), [0x0400, 0x0402, 0x0403, 0x0404, 0x0500]);
+}
+
+// nmi(), irq() and reset() deliver things straight away, without going through the lines.
+
+#[test]
+fn reset_uses_its_vector() {
+    let (mut cpu, mut sys) = boot(&[0xEA], false);
+    cpu.a.0 = 0x11;
+    cpu.x.0 = 0x22;
+    cpu.y.0 = 0x33;
+    cpu.sp.0 = 0x44;
+    cpu.status.set_interrupt(false);
+    sys.set_reset_vector(0x1234);
+    let ticks = cpu.clockticks;
+
+    cpu.reset(&sys);
+    assert_eq!(cpu.pc.0, 0x1234);
+    assert_eq!((cpu.a.0, cpu.x.0, cpu.y.0, cpu.sp.0), (0, 0, 0, 0xFD));
+    assert!(cpu.status.interrupt());
+    assert_eq!(cpu.clockticks, ticks + 7);
+    // Nothing gets pushed.
+    assert!(sys.mem[0x0100..0x0200].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn nmi_pushes_pc_and_status() {
+    let (mut cpu, mut sys) = boot(&[0xEA], false);
+    cpu.status = Status::from_bits(0xC3); // N V Z C, I clear
+    let ticks = cpu.clockticks;
+
+    cpu.nmi(&mut sys);
+    assert_eq!(cpu.pc.0, NMI_HANDLER);
+    assert_eq!(cpu.sp.0, 0xFA);
+    // Return address high byte first, then the status with B clear and the constant bit set.
+    assert_eq!(&sys.mem[0x01FB..0x01FE], &[0xE3, 0x00, 0x04]);
+    assert!(cpu.status.interrupt());
+    assert_eq!(cpu.clockticks, ticks + 7);
+}
+
+#[test]
+fn irq_respects_i() {
+    let (mut cpu, mut sys) = boot(&[0xEA], false);
+    let ticks = cpu.clockticks;
+    assert!(!cpu.irq(&mut sys));
+    assert_eq!((cpu.pc.0, cpu.sp.0, cpu.clockticks), (0x0400, 0xFD, ticks));
+
+    cpu.status.set_interrupt(false);
+    assert!(cpu.irq(&mut sys));
+    assert_eq!(cpu.pc.0, IRQ_HANDLER);
+    assert_eq!(cpu.sp.0, 0xFA);
+    assert_eq!(&sys.mem[0x01FB..0x01FE], &[0x20, 0x00, 0x04]);
+    assert!(cpu.status.interrupt());
+    assert_eq!(cpu.clockticks, ticks + 7);
+
+    // The handler runs and comes back with I clear again.
+    assert_eq!(trace(&mut cpu, &mut sys, 3), [0x0500, 0x0501, 0x0400]);
+    assert!(!cpu.status.interrupt());
+}
+
+#[test]
+fn brk_pushes_b() {
+    let (mut cpu, mut sys) = boot(&[0x00, 0xFF, 0xEA], false);
+    cpu.step(&mut sys);
+    assert_eq!(cpu.pc.0, IRQ_HANDLER);
+    // BRK skips a byte, so it comes back to $0402.
+    assert_eq!(&sys.mem[0x01FB..0x01FE], &[0x34, 0x02, 0x04]);
+}
+
+// A FlatSystem with a device on it that pulls /NMI once, when tick() has counted 'at' cycles.
+struct NmiDevice {
+    sys: FlatSystem,
+    cycle: u32,
+    at: u32,
+}
+
+impl Backplane for NmiDevice {
+    fn read(&self, address: u16) -> u8 {
+        self.sys.read(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.sys.write(address, value)
+    }
+
+    fn each_instr(&mut self, cpu: &mut CPU) -> Control {
+        self.sys.each_instr(cpu)
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        self.cycle += cycles;
+    }
+
+    fn nmi(&mut self) -> bool {
+        self.cycle == self.at
+    }
+}
+
+#[test]
+fn nmi_during_brk_hijacks_it() {
+    // BRK's seven cycles are the opcode, the padding byte, three pushes and the two vector bytes.
+    // An NMI that's in before the vector gets read sends BRK to the NMI handler and counts as
+    // taken; one that comes any later waits for the next instruction.
+    for (at, vector, sp) in [(3, NMI_HANDLER, 0xFA), (5, NMI_HANDLER, 0xFA), (6, IRQ_HANDLER, 0xF7)] {
+        let (mut cpu, sys) = boot(&[0x00, 0xFF, 0xEA], true);
+        let mut sys = NmiDevice { sys, cycle: 0, at };
+        cpu.step(&mut sys);
+        assert_eq!(cpu.pc.0, vector);
+        // B is set in the pushed status either way.
+        assert_eq!(&sys.sys.mem[0x01FB..0x01FE], &[0x34, 0x02, 0x04]);
+
+        cpu.step(&mut sys);
+        assert_eq!((cpu.y.0, cpu.sp.0), (1, sp));
+    }
+}
+
+#[test]
+#[cfg(not(feature = "no-65c02"))]
+fn cmos_clears_decimal_on_interrupts() {
+    let (mut cpu, mut sys) = common::boot(CpuVariant::Wdc65C02, &[0xEA]);
+    sys.load(0xFFFA, &NMI_HANDLER.to_le_bytes());
+    cpu.status.set_decimal(true);
+    cpu.nmi(&mut sys);
+    assert!(!cpu.status.decimal());
+
+    cpu.status.set_decimal(true);
+    cpu.reset(&sys);
+    assert!(!cpu.status.decimal());
+}
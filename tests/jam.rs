@@ -0,0 +1,53 @@
+mod common;
+
+use fake6502::{CpuVariant, JamReason, StopReason};
+
+const JAMS: [u8; 12] = [0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2];
+
+#[test]
+fn every_jam_opcode() {
+    for variant in [CpuVariant::Nmos6502, CpuVariant::Nmos6502Illegal, CpuVariant::Ricoh2A03] {
+        for &opcode in JAMS.iter() {
+            let (mut cpu, mut sys) = common::boot(variant, &[0xEA, opcode, 0xE8]);
+            let start = cpu.clockticks;
+
+            // exec() hands back the rest of its budget instead of burning through it.
+            assert_eq!(cpu.exec(&mut sys, 1_000_000), StopReason::Jammed, "{:02X}", opcode);
+            assert!(cpu.is_jammed());
+            assert_eq!(cpu.jam_reason(), Some(JamReason::Jam { opcode, pc: 0x0401 }));
+            assert!(cpu.clockticks - start < 10);
+            assert_eq!(cpu.instructions_ran, 2);
+        }
+    }
+}
+
+#[test]
+fn only_reset_recovers() {
+    let (mut cpu, mut sys) = common::boot(CpuVariant::Nmos6502, &[0x02, 0xE8]);
+    sys.load(0x0500, &[0xC8, 0x40]);
+    sys.load(0xFFFA, &[0x00, 0x05]);
+    sys.load(0xFFFE, &[0x00, 0x05]);
+    cpu.step(&mut sys);
+    let (pc, ticks, ran) = (cpu.pc.0, cpu.clockticks, cpu.instructions_ran);
+
+    // Interrupts don't get through, however they're delivered.
+    cpu.set_nmi(true);
+    cpu.status.set_interrupt(false);
+    cpu.set_irq(1, true);
+    assert_eq!(cpu.exec(&mut sys, 100), StopReason::Jammed);
+    assert_eq!(cpu.step(&mut sys).cycles, 0);
+    cpu.nmi(&mut sys);
+    assert!(!cpu.irq(&mut sys));
+    assert_eq!((cpu.pc.0, cpu.clockticks, cpu.instructions_ran), (pc, ticks, ran));
+    assert_eq!(cpu.y.0, 0);
+
+    cpu.set_nmi(false);
+    cpu.set_irq(1, false);
+    cpu.reset(&sys);
+    assert!(!cpu.is_jammed());
+    assert_eq!(cpu.jam_reason(), None);
+    assert_eq!(cpu.pc.0, 0x0400);
+    // ...and it goes straight back to jamming, being the same program.
+    cpu.step(&mut sys);
+    assert!(cpu.is_jammed());
+}
@@ -0,0 +1,255 @@
+// Runs Klaus Dormann's 6502 test suites (https://github.com/Klaus2m5/6502_65C02_functional_tests).
+//
+// The functional test is checked in as test.bin. The decimal-mode and interrupt tests aren't, since
+// they have to be assembled with settings that suit whoever's running them, so they're #[ignore]d;
+// drop your builds next to test.bin, or point KLAUS_DECIMAL_BIN and KLAUS_INTERRUPT_BIN at them,
+// and run them with `cargo test --test klaus -- --ignored`. A suite whose image can't be found
+// fails rather than quietly passing.
+// Each suite's addresses can be overridden the same way (see Suite::from_env()) in case your
+// builds put things somewhere else.
+//
+// The suites indicate success and failure by looping: success is a "JMP *" at a known address,
+// failure is a "JMP *" or "BNE *" anywhere else. The CPU spots those for us (detect_traps).
+
+use fake6502::{Backplane, Control, CpuVariant, StopReason, CPU};
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::prelude::*;
+
+// An IRQ source number for CPU::set_irq(), for the interrupt test's feedback port.
+const FEEDBACK_IRQ: u32 = 0;
+
+struct Suite {
+    name: &'static str,
+    file: String,
+    load_addr: u16,
+    start_addr: u16,
+    // Where the suite loops forever when it passes, if it does.
+    success_addr: Option<u16>,
+    // A byte the suite leaves nonzero if it failed (the decimal test's ERROR).
+    error_addr: Option<u16>,
+    // Where the suite keeps the number of the test it's on, for diagnostics.
+    test_case_addr: Option<u16>,
+    // The interrupt test drives IRQ and NMI by writing bits 0 and 1 of this address.
+    feedback_addr: Option<u16>,
+    max_cycles: u64,
+    trace_len: usize,
+}
+
+fn env_addr(var: &str, default: Option<u16>) -> Option<u16> {
+    match std::env::var(var) {
+        Ok(ref v) if v.is_empty() || v == "none" => None,
+        Ok(v) => {
+            let digits = v.trim_start_matches("0x").trim_start_matches('$');
+            Some(u16::from_str_radix(digits, 16).unwrap_or_else(|_| panic!("{} isn't a hex address: {:?}", var, v)))
+        }
+        Err(_) => default,
+    }
+}
+
+fn env_num(var: &str, default: u64) -> u64 {
+    match std::env::var(var) {
+        Ok(v) => v.parse().unwrap_or_else(|_| panic!("{} isn't a number: {:?}", var, v)),
+        Err(_) => default,
+    }
+}
+
+impl Suite {
+    // Reads KLAUS_<PREFIX>_BIN, _LOAD, _START, _SUCCESS, _ERROR, _TEST_CASE and _FEEDBACK, falling
+    // back on the defaults given. Addresses are in hex; "none" turns an optional one off.
+    fn from_env(prefix: &str, defaults: Suite) -> Suite {
+        let var = |field: &str| format!("KLAUS_{}_{}", prefix, field);
+        Suite {
+            name: defaults.name,
+            file: std::env::var(var("BIN")).unwrap_or(defaults.file),
+            load_addr: env_addr(&var("LOAD"), Some(defaults.load_addr)).unwrap(),
+            start_addr: env_addr(&var("START"), Some(defaults.start_addr)).unwrap(),
+            success_addr: env_addr(&var("SUCCESS"), defaults.success_addr),
+            error_addr: env_addr(&var("ERROR"), defaults.error_addr),
+            test_case_addr: env_addr(&var("TEST_CASE"), defaults.test_case_addr),
+            feedback_addr: env_addr(&var("FEEDBACK"), defaults.feedback_addr),
+            max_cycles: env_num("KLAUS_MAX_CYCLES", defaults.max_cycles),
+            trace_len: env_num("KLAUS_TRACE_LEN", defaults.trace_len as u64) as usize,
+        }
+    }
+}
+
+struct Traced {
+    pc: u16,
+    bytes: [u8; 3],
+    // Registers after the instruction ran.
+    a: u8,
+    x: u8,
+    y: u8,
+    sp: u8,
+    status: String,
+}
+
+struct TestSystem {
+    mem: Vec<u8>,
+    feedback_addr: Option<u16>,
+    // The IRQ and NMI bits last written to the feedback port, to hand over to the CPU.
+    feedback: u8,
+    trace: VecDeque<Traced>,
+    trace_len: usize,
+    // Where the instruction that just finished was fetched from.
+    last_pc: u16,
+}
+
+impl Backplane for TestSystem {
+    fn read(&self, address: u16) -> u8 {
+        self.mem[address as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.mem[address as usize] = value;
+        if Some(address) == self.feedback_addr {
+            self.feedback = value;
+        }
+    }
+
+    fn each_instr(&mut self, c: &mut CPU) -> Control {
+        if self.feedback_addr.is_some() {
+            c.set_irq(FEEDBACK_IRQ, self.feedback & 0x01 != 0);
+            c.set_nmi(self.feedback & 0x02 != 0);
+        }
+
+        if self.trace_len > 0 {
+            if self.trace.len() == self.trace_len {
+                self.trace.pop_front();
+            }
+            let pc = self.last_pc as usize;
+            self.trace.push_back(Traced {
+                pc: self.last_pc,
+                bytes: [self.mem[pc], self.mem[(pc + 1) & 0xFFFF], self.mem[(pc + 2) & 0xFFFF]],
+                a: c.a.0,
+                x: c.x.0,
+                y: c.y.0,
+                sp: c.sp.0,
+                status: c.status.to_string(),
+            });
+        }
+        self.last_pc = c.pc.0;
+
+        Control::Continue
+    }
+}
+
+fn diagnostics(suite: &Suite, sys: &TestSystem, cpu: &CPU, reason: StopReason) -> String {
+    let mut out = String::new();
+    out += &format!("{} failed: {:?} after {} cycles\n", suite.name, reason, cpu.clockticks);
+    out += &format!("PC={:04X} A={:02X} X={:02X} Y={:02X} SP={:02X} P={}\n",
+                    cpu.pc.0, cpu.a.0, cpu.x.0, cpu.y.0, cpu.sp.0, cpu.status);
+    if let Some(addr) = suite.test_case_addr {
+        out += &format!("test case: ${:02X}\n", sys.mem[addr as usize]);
+    }
+    if let Some(addr) = suite.error_addr {
+        out += &format!("error byte at ${:04X}: ${:02X}\n", addr, sys.mem[addr as usize]);
+    }
+    if let Some(reason) = cpu.jam_reason() {
+        out += &format!("jammed: {:?}\n", reason);
+    }
+    out += &format!("last {} instructions:\n", sys.trace.len());
+    for t in sys.trace.iter() {
+        out += &format!("  {:04X}  {:02X} {:02X} {:02X}   A={:02X} X={:02X} Y={:02X} SP={:02X} P={}\n",
+                        t.pc, t.bytes[0], t.bytes[1], t.bytes[2], t.a, t.x, t.y, t.sp, t.status);
+    }
+    out
+}
+
+fn run(suite: Suite, variant: CpuVariant) {
+    let mut image = Vec::new();
+    File::open(&suite.file)
+        .and_then(|mut f| f.read_to_end(&mut image))
+        .unwrap_or_else(|e| panic!("{}: can't read {}: {}", suite.name, suite.file, e));
+    let load = suite.load_addr as usize;
+    assert!(load + image.len() <= 0x10000, "{} doesn't fit at ${:04X}", suite.file, load);
+
+    let mut sys = TestSystem {
+        mem: vec![0; 0x10000],
+        feedback_addr: suite.feedback_addr,
+        feedback: 0,
+        trace: VecDeque::with_capacity(suite.trace_len),
+        trace_len: suite.trace_len,
+        last_pc: suite.start_addr,
+    };
+    sys.mem[load..load + image.len()].copy_from_slice(&image);
+    if let Some(addr) = suite.feedback_addr {
+        sys.mem[addr as usize] = 0;
+    }
+
+    let mut cpu = CPU::new(variant);
+    cpu.reset(&sys);
+    cpu.pc.0 = suite.start_addr;
+    cpu.detect_traps = true;
+
+    let reason = cpu.exec_until(&mut sys, suite.max_cycles);
+    let passed = match reason {
+        StopReason::TrapDetected(pc) => suite.success_addr.is_none() || suite.success_addr == Some(pc),
+        StopReason::Jammed => suite.success_addr.is_none(),
+        _ => false,
+    } && suite.error_addr.map_or(0, |e| sys.mem[e as usize]) == 0;
+
+    if !passed {
+        panic!("{}", diagnostics(&suite, &sys, &cpu, reason));
+    }
+}
+
+// Part of it checks ADC and SBC in decimal mode, which the "no-bcd" feature takes out.
+#[cfg(not(feature = "no-bcd"))]
+#[test]
+fn functional_test() {
+    let suite = Suite::from_env("FUNCTIONAL", Suite {
+        name: "6502 functional test",
+        file: concat!(env!("CARGO_MANIFEST_DIR"), "/test.bin").to_string(),
+        load_addr: 0x0000,
+        start_addr: 0x0400,
+        success_addr: Some(0x3469),
+        error_addr: None,
+        test_case_addr: Some(0x0200),
+        feedback_addr: None,
+        max_cycles: 200_000_000,
+        trace_len: 32,
+    });
+    run(suite, CpuVariant::Nmos6502);
+}
+
+// The decimal test doesn't have a success address of its own: it stores 0 to ERROR and then either
+// loops or (as assembled for the 65C02) executes STP, so a clean ERROR after either is a pass.
+#[cfg(not(feature = "no-bcd"))]
+#[test]
+#[ignore = "needs 6502_decimal_test.bin, which isn't checked in"]
+fn decimal_test() {
+    let suite = Suite::from_env("DECIMAL", Suite {
+        name: "6502 decimal test",
+        file: concat!(env!("CARGO_MANIFEST_DIR"), "/6502_decimal_test.bin").to_string(),
+        load_addr: 0x0000,
+        start_addr: 0x0200,
+        success_addr: None,
+        error_addr: Some(0x000B),
+        test_case_addr: None,
+        feedback_addr: None,
+        max_cycles: 100_000_000,
+        trace_len: 32,
+    });
+    run(suite, CpuVariant::Nmos6502);
+}
+
+#[test]
+#[ignore = "needs 6502_interrupt_test.bin, which isn't checked in"]
+fn interrupt_test() {
+    let suite = Suite::from_env("INTERRUPT", Suite {
+        name: "6502 interrupt test",
+        file: concat!(env!("CARGO_MANIFEST_DIR"), "/6502_interrupt_test.bin").to_string(),
+        load_addr: 0x0000,
+        start_addr: 0x0400,
+        success_addr: Some(0x06F5),
+        error_addr: None,
+        test_case_addr: Some(0x0200),
+        feedback_addr: Some(0xBFFC),
+        max_cycles: 10_000_000,
+        trace_len: 32,
+    });
+    run(suite, CpuVariant::Nmos6502);
+}
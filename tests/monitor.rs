@@ -0,0 +1,101 @@
+// Drives the monitor binary the way someone at the keyboard would, a script on stdin at a time.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn monitor(args: &[&str], script: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_monitor"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(script.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// Whether 'lines' turn up in 'output' in that order.
+fn assert_in_order(output: &str, lines: &[&str]) {
+    let mut rest = output;
+    for line in lines {
+        match rest.find(line) {
+            Some(at) => rest = &rest[at + line.len()..],
+            None => panic!("{:?} isn't in the right place in:\n{}", line, output),
+        }
+    }
+}
+
+const PROGRAM: &str = "\
+> 400 a9 42 8d 00 02 e8 4c 06 04
+r pc=400
+";
+// 0400  LDA #$42
+// 0402  STA $0200
+// 0405  INX
+// 0406  JMP $0406
+
+#[test]
+fn memory_and_disassembly() {
+    let output = monitor(&[], &format!("{}m 400 408\nd 400 4\na 409 lda #$10\nm 409 40a\nq\n", PROGRAM));
+    assert_in_order(&output, &[
+        "0400  A9 42 8D 00 02 E8 4C 06 04",
+        "0400  A9 42     LDA #$42",
+        "0402  8D 00 02  STA $0200",
+        "0405  E8        INX",
+        "0406  4C 06 04  JMP $0406",
+        "0409  A9 10",
+    ]);
+}
+
+#[test]
+fn breakpoints_and_watchpoints() {
+    let script = format!("{}b 405\nc\nw w 200\nr pc=400\nc\nwd *\nbd *\nc\nm 200 200\nq\n", PROGRAM);
+    let output = monitor(&[], &script);
+    assert_in_order(&output, &[
+        "breakpoint at 0405",
+        "0405 42 00 00 FD",
+        "watchpoint: write to 0200 by the instruction at 0402",
+        "stuck in a loop at 0406",
+        "0406 42 01 00 FD",
+        "0200  42",
+    ]);
+}
+
+#[test]
+fn stepping() {
+    let output = monitor(&[], &format!("{}s\n\ns 2\nq\n", PROGRAM));
+    // An empty line repeats the step, and "s 2" runs the INX and the JMP before showing where it
+    // got to.
+    assert_in_order(&output, &[
+        "0402 42 00 00 FD nv-bdIzc        9",
+        "0405 42 00 00 FD nv-bdIzc       13",
+        "0406 42 01 00 FD nv-bdIzc       18",
+    ]);
+}
+
+#[test]
+#[cfg(not(feature = "no-65c02"))]
+fn wai_isnt_a_loop() {
+    let script = "\
+> 400 58 cb 4c 00 04
+> 0 e8 40
+r pc=400
+c
+irq
+s 2
+q
+";
+    // 0400  CLI
+    // 0401  WAI
+    // 0402  JMP $0400
+    // 0000  INX, RTI (the IRQ handler, by way of the empty vector)
+    let output = monitor(&["--variant", "65c02"], script);
+    assert_in_order(&output, &[
+        "waiting for an interrupt at 0402",
+        "0000 00 00 00 FA",
+        "0402 00 01 00 FD",
+    ]);
+    assert!(!output.contains("stuck"));
+}
@@ -0,0 +1,105 @@
+use fake6502::{Backplane, Control, CpuVariant, CPU};
+
+use std::ops::Range;
+
+// RAM, plus a DMA controller that holds RDY low over a range of cycles, counted by tick().
+struct DmaSystem {
+    mem: Vec<u8>,
+    cycle: u64,
+    dma: Range<u64>,
+    callbacks: u64,
+}
+
+impl Backplane for DmaSystem {
+    fn read(&self, address: u16) -> u8 {
+        self.mem[address as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.mem[address as usize] = value;
+    }
+
+    fn each_instr(&mut self, _cpu: &mut CPU) -> Control {
+        self.callbacks += 1;
+        Control::Continue
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        self.cycle += u64::from(cycles);
+    }
+
+    fn ready(&mut self) -> bool {
+        !self.dma.contains(&self.cycle)
+    }
+}
+
+fn boot(program: &[u8], dma: Range<u64>, cycle_accurate: bool) -> (CPU, DmaSystem) {
+    let mut sys = DmaSystem { mem: vec![0; 0x10000], cycle: 0, dma: 0..0, callbacks: 0 };
+    sys.mem[0x0400..0x0400 + program.len()].copy_from_slice(program);
+    sys.mem[0xFFFC..0xFFFE].copy_from_slice(&[0x00, 0x04]);
+    let mut cpu = CPU::new(CpuVariant::Nmos6502);
+    cpu.reset(&sys);
+    cpu.cycle_accurate = cycle_accurate;
+    // Count cycles from here, not from the reset.
+    cpu.clockticks = 0;
+    sys.dma = dma;
+    (cpu, sys)
+}
+
+const NOPS: [u8; 4] = [0xEA, 0xEA, 0xEA, 0xEA];
+
+#[test]
+fn stalls_dont_call_back() {
+    for cycle_accurate in [false, true] {
+        let (mut cpu, mut sys) = boot(&NOPS, 0..0, cycle_accurate);
+        cpu.hold_rdy(5);
+        cpu.exec_until(&mut sys, 5 + 3 * 2);
+
+        assert_eq!(cpu.rdy_stalled_cycles, 5);
+        assert_eq!(cpu.instructions_ran, 3);
+        assert_eq!(sys.callbacks, 3);
+        assert_eq!(sys.cycle, cpu.clockticks);
+
+        // Nor does step(), which reports the one cycle it spent.
+        cpu.hold_rdy(1);
+        assert_eq!(cpu.step(&mut sys).cycles, 1);
+        assert_eq!(sys.callbacks, 3);
+        assert_eq!(cpu.step(&mut sys).pc, 0x0403);
+        assert_eq!(sys.callbacks, 4);
+    }
+}
+
+#[test]
+fn cycle_accurate_stalls_mid_instruction() {
+    // LDA abs reads its opcode, both address bytes and then the operand, on cycles 0 to 3. RDY
+    // goes low for cycles 2 and 3.
+    let program = [0xAD, 0x34, 0x12, 0xEA];
+
+    let (mut cpu, mut sys) = boot(&program, 2..4, true);
+    let step = cpu.step(&mut sys);
+    assert_eq!(step.cycles, 4 + 2);
+    assert_eq!(cpu.rdy_stalled_cycles, 2);
+    assert_eq!(sys.cycle, 6);
+    assert_eq!(sys.callbacks, 1);
+
+    // Otherwise RDY is only looked at between instructions, and by the next one it's high again.
+    let (mut cpu, mut sys) = boot(&program, 2..4, false);
+    assert_eq!(cpu.step(&mut sys).cycles, 4);
+    assert_eq!(cpu.rdy_stalled_cycles, 0);
+}
+
+#[test]
+fn writes_dont_stall() {
+    // STA abs writes on cycle 3, and the NOP after it is fetched on cycle 4.
+    let program = [0x8D, 0x34, 0x12, 0xEA];
+    let (mut cpu, mut sys) = boot(&program, 3..4, true);
+    assert_eq!(cpu.step(&mut sys).cycles, 4);
+    assert_eq!(cpu.rdy_stalled_cycles, 0);
+
+    // But RDY still being low on cycle 4 holds up the fetch.
+    let (mut cpu, mut sys) = boot(&program, 3..5, true);
+    assert_eq!(cpu.step(&mut sys).cycles, 4);
+    assert_eq!(cpu.step(&mut sys).cycles, 1);
+    assert_eq!(cpu.step(&mut sys).pc, 0x0403);
+    assert_eq!(cpu.rdy_stalled_cycles, 1);
+}
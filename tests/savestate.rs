@@ -0,0 +1,136 @@
+mod common;
+
+use common::FlatSystem;
+use fake6502::{CpuVariant, SaveState, SnapshotError, SnapshotReader, SnapshotWriter, StopReason, CPU};
+
+use std::num::Wrapping;
+
+// Counts X up, keeps a running total in A, and writes the totals out to 0x0200,X. Never stops.
+const PROGRAM: [u8; 15] = [
+    0xA2, 0x00,       // LDX #$00
+    0xE8,             // loop: INX
+    0x86, 0x10,       // STX $10
+    0x8A,             // TXA
+    0x65, 0x10,       // ADC $10
+    0x9D, 0x00, 0x02, // STA $0200,X
+    0x4C, 0x02, 0x04, // JMP loop
+    0x00,
+];
+
+impl SaveState for FlatSystem {
+    fn save_state(&self, out: &mut SnapshotWriter) {
+        out.chunk(b"RAM ", &self.mem);
+    }
+
+    fn load_state(&mut self, snapshot: &SnapshotReader) -> Result<(), SnapshotError> {
+        let ram = snapshot.chunk(b"RAM ").ok_or(SnapshotError::MissingChunk(*b"RAM "))?;
+        if ram.len() != 0x10000 {
+            return Err(SnapshotError::BadChunk(*b"RAM "));
+        }
+        self.mem.copy_from_slice(ram);
+        Ok(())
+    }
+}
+
+fn boot(variant: CpuVariant) -> (CPU, FlatSystem) {
+    let (mut cpu, sys) = common::boot(variant, &PROGRAM);
+    cpu.do_callback = true;
+    // Leave the SO pin in a non-default state so it has to survive too.
+    cpu.set_so(true);
+    (cpu, sys)
+}
+
+fn resumes_identically(variant: CpuVariant) {
+    let (mut cpu, mut sys) = boot(variant);
+
+    // Stop part way through a 1000 cycle budget.
+    sys.stop_after = Some(cpu.instructions_ran + 37);
+    assert_eq!(cpu.exec(&mut sys, 1000), StopReason::HostRequested(0));
+    assert!(cpu.clockticks < 1000 + 7, "exec() should have been stopped early");
+
+    let snap = cpu.save_snapshot(&sys);
+
+    let mut cpu2 = CPU::new(CpuVariant::Nmos6502);
+    let mut sys2 = FlatSystem::new();
+    cpu2.load_snapshot(&mut sys2, &snap).unwrap();
+    sys2.stop_after = sys.stop_after;
+
+    assert_eq!(cpu2.save_snapshot(&sys2), snap);
+
+    // Finish off the leftover budget, then keep going for a while, on both machines.
+    sys.stop_after = None;
+    sys2.stop_after = None;
+    cpu.exec(&mut sys, 0);
+    cpu2.exec(&mut sys2, 0);
+    for budget in [1, 2, 3, 50, 1000, 12345].iter() {
+        cpu.exec(&mut sys, *budget);
+        cpu2.exec(&mut sys2, *budget);
+        assert_eq!(cpu.pc, cpu2.pc);
+        assert_eq!(cpu.clockticks, cpu2.clockticks);
+    }
+
+    assert_eq!(cpu.instructions_ran, cpu2.instructions_ran);
+    assert!(sys.mem == sys2.mem);
+    assert_eq!(cpu.save_snapshot(&sys), cpu2.save_snapshot(&sys2));
+}
+
+#[test]
+fn resumes_identically_nmos() {
+    resumes_identically(CpuVariant::Nmos6502);
+}
+
+#[test]
+fn resumes_identically_65c02() {
+    resumes_identically(CpuVariant::Wdc65C02);
+}
+
+#[test]
+fn resumes_identically_cycle_accurate() {
+    let (mut cpu, mut sys) = boot(CpuVariant::Nmos6502Illegal);
+    cpu.cycle_accurate = true;
+    let snap = cpu.save_snapshot(&sys);
+
+    let mut cpu2 = CPU::new(CpuVariant::Nmos6502);
+    let mut sys2 = FlatSystem::new();
+    cpu2.load_snapshot(&mut sys2, &snap).unwrap();
+    assert!(cpu2.cycle_accurate);
+    assert_eq!(cpu2.variant, CpuVariant::Nmos6502Illegal);
+
+    cpu.exec(&mut sys, 5000);
+    cpu2.exec(&mut sys2, 5000);
+    assert_eq!(cpu.save_snapshot(&sys), cpu2.save_snapshot(&sys2));
+}
+
+#[test]
+fn cpu_only_snapshot() {
+    let (mut cpu, mut sys) = boot(CpuVariant::Nmos6502);
+    cpu.exec(&mut sys, 100);
+    cpu.a = Wrapping(0x5A);
+
+    let snap = cpu.save_snapshot(&());
+    let mut cpu2 = CPU::new(CpuVariant::Wdc65C02);
+    cpu2.load_snapshot(&mut (), &snap).unwrap();
+    assert_eq!(cpu2.a, Wrapping(0x5A));
+    assert_eq!(cpu2.pc, cpu.pc);
+    assert_eq!(cpu2.variant, CpuVariant::Nmos6502);
+}
+
+#[test]
+fn rejects_bad_snapshots() {
+    let (cpu, sys) = boot(CpuVariant::Nmos6502);
+    let snap = cpu.save_snapshot(&sys);
+    let mut cpu2 = CPU::new(CpuVariant::Nmos6502);
+    let mut sys2 = FlatSystem::new();
+
+    assert_eq!(cpu2.load_snapshot(&mut sys2, b"not a snapshot"), Err(SnapshotError::BadMagic));
+
+    let mut newer = snap.clone();
+    newer[8] = 0xFF;
+    assert!(matches!(cpu2.load_snapshot(&mut sys2, &newer), Err(SnapshotError::UnsupportedVersion(_))));
+
+    assert_eq!(cpu2.load_snapshot(&mut sys2, &snap[..snap.len() - 1]), Err(SnapshotError::Truncated));
+
+    // A snapshot with only the CPU in it is missing the machine's RAM.
+    let cpu_only = cpu.save_snapshot(&());
+    assert_eq!(cpu2.load_snapshot(&mut sys2, &cpu_only), Err(SnapshotError::MissingChunk(*b"RAM ")));
+}
@@ -0,0 +1,360 @@
+// Runs the per-opcode test vectors from https://github.com/SingleStepTests/65x02: one JSON file
+// per opcode ("a9.json" and so on), each an array of tests giving the registers and RAM before and
+// after a single instruction, plus every bus cycle it made along the way.
+//
+// The vectors are far too big to check in, so the test is #[ignore]d; point SINGLESTEP_DIR at a
+// directory of them (e.g. 65x02/6502/v1) and run
+//
+//     SINGLESTEP_DIR=/path/to/65x02/6502/v1 cargo test --release --test singlestep -- --ignored
+//
+// Other knobs:
+//   SINGLESTEP_VARIANT  nmos, illegal (the default), 2a03 or 65c02; match it to the directory.
+//   SINGLESTEP_SKIP     opcodes to leave out, in hex, comma separated. Defaults to the NMOS JAMs,
+//                       whose bus activity isn't worth modelling.
+//   SINGLESTEP_LIMIT    only run this many tests from each file.
+//
+// The bus log is only compared on NMOS variants, since the 65C02 doesn't have a cycle-accurate
+// mode; there, only the number of cycles is checked.
+
+use fake6502::{Backplane, Control, CpuVariant, Status, CPU};
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::num::Wrapping;
+use std::path::PathBuf;
+
+// Just enough JSON to read the test files with.
+
+#[derive(Debug)]
+enum Json {
+    Null,
+    Bool,
+    Num(f64),
+    Str(String),
+    Arr(Vec<Json>),
+    Obj(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn get(&self, key: &str) -> &Json {
+        match *self {
+            Json::Obj(ref fields) => {
+                fields.iter().find(|f| f.0 == key).map(|f| &f.1).unwrap_or(&Json::Null)
+            }
+            _ => &Json::Null,
+        }
+    }
+
+    fn num(&self) -> u64 {
+        match *self {
+            Json::Num(n) => n as u64,
+            _ => panic!("expected a number, got {:?}", self),
+        }
+    }
+
+    fn str(&self) -> &str {
+        match *self {
+            Json::Str(ref s) => s,
+            _ => panic!("expected a string, got {:?}", self),
+        }
+    }
+
+    fn arr(&self) -> &[Json] {
+        match *self {
+            Json::Arr(ref a) => a,
+            _ => panic!("expected an array, got {:?}", self),
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(text: &'a str) -> Json {
+        let mut p = Parser { text: text.as_bytes(), pos: 0 };
+        let v = p.value();
+        p.skip_space();
+        assert!(p.pos == p.text.len(), "trailing junk at byte {}", p.pos);
+        v
+    }
+
+    fn skip_space(&mut self) {
+        while self.pos < self.text.len() && (self.text[self.pos] as char).is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> u8 {
+        self.skip_space();
+        *self.text.get(self.pos).unwrap_or_else(|| panic!("unexpected end of JSON"))
+    }
+
+    fn expect(&mut self, c: u8) {
+        if self.peek() != c {
+            panic!("expected '{}' at byte {}", c as char, self.pos);
+        }
+        self.pos += 1;
+    }
+
+    fn literal(&mut self, word: &str, v: Json) -> Json {
+        assert!(self.text[self.pos..].starts_with(word.as_bytes()), "bad literal at byte {}", self.pos);
+        self.pos += word.len();
+        v
+    }
+
+    fn value(&mut self) -> Json {
+        match self.peek() {
+            b'{' => {
+                self.pos += 1;
+                let mut fields = Vec::new();
+                if self.peek() == b'}' {
+                    self.pos += 1;
+                    return Json::Obj(fields);
+                }
+                loop {
+                    let key = self.string();
+                    self.expect(b':');
+                    fields.push((key, self.value()));
+                    match self.peek() {
+                        b',' => self.pos += 1,
+                        _ => break,
+                    }
+                }
+                self.expect(b'}');
+                Json::Obj(fields)
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                if self.peek() == b']' {
+                    self.pos += 1;
+                    return Json::Arr(items);
+                }
+                loop {
+                    items.push(self.value());
+                    match self.peek() {
+                        b',' => self.pos += 1,
+                        _ => break,
+                    }
+                }
+                self.expect(b']');
+                Json::Arr(items)
+            }
+            b'"' => Json::Str(self.string()),
+            b't' => self.literal("true", Json::Bool),
+            b'f' => self.literal("false", Json::Bool),
+            b'n' => self.literal("null", Json::Null),
+            _ => {
+                let start = self.pos;
+                while self.pos < self.text.len() && b"+-.eE0123456789".contains(&self.text[self.pos]) {
+                    self.pos += 1;
+                }
+                let s = std::str::from_utf8(&self.text[start..self.pos]).unwrap();
+                Json::Num(s.parse().unwrap_or_else(|_| panic!("bad number {:?} at byte {}", s, start)))
+            }
+        }
+    }
+
+    // The test files never have escapes more interesting than these.
+    fn string(&mut self) -> String {
+        self.expect(b'"');
+        let mut s = String::new();
+        loop {
+            let c = self.text[self.pos];
+            self.pos += 1;
+            match c {
+                b'"' => return s,
+                b'\\' => {
+                    let e = self.text[self.pos];
+                    self.pos += 1;
+                    s.push(match e {
+                        b'n' => '\n',
+                        b't' => '\t',
+                        other => other as char,
+                    });
+                }
+                _ => s.push(c as char),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+}
+
+struct RecordingSystem {
+    mem: Vec<u8>,
+    // Addresses the current test touched, so they can be cleared for the next one.
+    touched: Vec<u16>,
+    // (Backplane::read() only gets &self.)
+    log: RefCell<Vec<(u16, u8, Access)>>,
+}
+
+impl Backplane for RecordingSystem {
+    fn read(&self, address: u16) -> u8 {
+        let value = self.mem[address as usize];
+        self.log.borrow_mut().push((address, value, Access::Read));
+        value
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.mem[address as usize] = value;
+        self.touched.push(address);
+        self.log.borrow_mut().push((address, value, Access::Write));
+    }
+
+    fn each_instr(&mut self, _cpu: &mut CPU) -> Control {
+        Control::Continue
+    }
+}
+
+// Per-opcode results: how many tests ran, and how many got each field wrong.
+#[derive(Default)]
+struct Tally {
+    ran: usize,
+    failed: usize,
+    fields: BTreeMap<&'static str, usize>,
+    first_failure: Option<String>,
+}
+
+fn run_test(cpu_variant: CpuVariant, sys: &mut RecordingSystem, test: &Json) -> Vec<(&'static str, String)> {
+    let initial = test.get("initial");
+    let fin = test.get("final");
+
+    for &addr in sys.touched.iter() {
+        sys.mem[addr as usize] = 0;
+    }
+    sys.touched.clear();
+    for entry in initial.get("ram").arr() {
+        let (addr, value) = (entry.arr()[0].num() as u16, entry.arr()[1].num() as u8);
+        sys.mem[addr as usize] = value;
+        sys.touched.push(addr);
+    }
+
+    let mut cpu = CPU::new(cpu_variant);
+    cpu.pc = Wrapping(initial.get("pc").num() as u16);
+    cpu.sp = Wrapping(initial.get("s").num() as u8);
+    cpu.a = Wrapping(initial.get("a").num() as u8);
+    cpu.x = Wrapping(initial.get("x").num() as u8);
+    cpu.y = Wrapping(initial.get("y").num() as u8);
+    cpu.status = Status::from_bits(initial.get("p").num() as u8);
+    cpu.cycle_accurate = true;
+    cpu.do_callback = false;
+
+    sys.log.borrow_mut().clear();
+    let result = cpu.step(sys);
+
+    let mut wrong = Vec::new();
+    let mut check = |field: &'static str, got: u64, want: u64| {
+        if got != want {
+            wrong.push((field, format!("{}: got {:X}, want {:X}", field, got, want)));
+        }
+    };
+    check("pc", cpu.pc.0 as u64, fin.get("pc").num());
+    check("s", cpu.sp.0 as u64, fin.get("s").num());
+    check("a", cpu.a.0 as u64, fin.get("a").num());
+    check("x", cpu.x.0 as u64, fin.get("x").num());
+    check("y", cpu.y.0 as u64, fin.get("y").num());
+    // The break and constant bits don't exist in the real register, so don't hold them against us.
+    check("p", (cpu.status.bits() & 0xCF) as u64, fin.get("p").num() & 0xCF);
+    for entry in fin.get("ram").arr() {
+        let (addr, value) = (entry.arr()[0].num() as u16, entry.arr()[1].num());
+        check("ram", sys.mem[addr as usize] as u64, value);
+    }
+
+    let cycles = test.get("cycles").arr();
+    check("cycles", result.cycles as u64, cycles.len() as u64);
+    if cpu_variant != CpuVariant::Wdc65C02 {
+        let log = sys.log.borrow();
+        let want: Vec<(u16, u8, Access)> = cycles.iter().map(|c| {
+            let c = c.arr();
+            let access = if c[2].str() == "write" { Access::Write } else { Access::Read };
+            (c[0].num() as u16, c[1].num() as u8, access)
+        }).collect();
+        if *log != want {
+            wrong.push(("bus", format!("bus: got {:X?}, want {:X?}", *log, want)));
+        }
+    }
+
+    wrong
+}
+
+#[test]
+#[ignore = "needs the SingleStepTests vectors; see the top of this file"]
+fn singlestep() {
+    let dir = PathBuf::from(std::env::var("SINGLESTEP_DIR").expect("SINGLESTEP_DIR isn't set"));
+    let variant = match std::env::var("SINGLESTEP_VARIANT").as_ref().map(|s| s.as_str()) {
+        Ok("nmos") => CpuVariant::Nmos6502,
+        Ok("illegal") | Err(_) => CpuVariant::Nmos6502Illegal,
+        Ok("2a03") => CpuVariant::Ricoh2A03,
+        Ok("65c02") => CpuVariant::Wdc65C02,
+        Ok(other) => panic!("unknown SINGLESTEP_VARIANT {:?}", other),
+    };
+    let skip: Vec<u8> = match std::env::var("SINGLESTEP_SKIP") {
+        Ok(list) => list.split(',').filter(|s| !s.trim().is_empty())
+                        .map(|s| u8::from_str_radix(s.trim(), 16).expect("bad opcode in SINGLESTEP_SKIP"))
+                        .collect(),
+        Err(_) if variant == CpuVariant::Wdc65C02 => vec![],
+        Err(_) => vec![0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2],
+    };
+    let limit = std::env::var("SINGLESTEP_LIMIT").ok().map(|s| s.parse::<usize>().expect("bad SINGLESTEP_LIMIT"));
+
+    let mut sys = RecordingSystem {
+        mem: vec![0; 0x10000],
+        touched: Vec::new(),
+        log: RefCell::new(Vec::new()),
+    };
+    let mut tallies: BTreeMap<u8, Tally> = BTreeMap::new();
+
+    for opcode in 0..=255u8 {
+        if skip.contains(&opcode) {
+            continue;
+        }
+        let path = dir.join(format!("{:02x}.json", opcode));
+        let text = match fs::read_to_string(&path) {
+            Ok(t) => t,
+            Err(_) => continue,
+        };
+        let tests = Parser::parse(&text);
+        let tally = tallies.entry(opcode).or_default();
+
+        for test in tests.arr().iter().take(limit.unwrap_or(usize::MAX)) {
+            tally.ran += 1;
+            let wrong = run_test(variant, &mut sys, test);
+            if wrong.is_empty() {
+                continue;
+            }
+            tally.failed += 1;
+            for &(field, _) in wrong.iter() {
+                *tally.fields.entry(field).or_insert(0) += 1;
+            }
+            if tally.first_failure.is_none() {
+                let details: Vec<String> = wrong.into_iter().map(|w| w.1).collect();
+                tally.first_failure = Some(format!("{:?}: {}", test.get("name").str(), details.join("; ")));
+            }
+        }
+    }
+
+    assert!(!tallies.is_empty(), "no test files found in {}", dir.display());
+
+    let mut failed_opcodes = 0;
+    for (opcode, tally) in tallies.iter() {
+        if tally.failed == 0 {
+            continue;
+        }
+        failed_opcodes += 1;
+        let fields: Vec<String> = tally.fields.iter().map(|(f, n)| format!("{} {}", f, n)).collect();
+        println!("${:02X}: {}/{} failed ({})", opcode, tally.failed, tally.ran, fields.join(", "));
+        println!("    first: {}", tally.first_failure.as_ref().unwrap());
+    }
+    let ran: usize = tallies.values().map(|t| t.ran).sum();
+    println!("{} tests over {} opcodes, {} opcodes with failures", ran, tallies.len(), failed_opcodes);
+    assert_eq!(failed_opcodes, 0);
+}
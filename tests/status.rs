@@ -0,0 +1,69 @@
+mod common;
+
+use fake6502::{CpuVariant, Status};
+
+// A flag's setter and getter, and its bit.
+type Flag = (fn(&mut Status, bool), fn(Status) -> bool, u8);
+
+#[test]
+fn flags() {
+    let mut s = Status::default();
+    let setters: [Flag; 7] = [
+        (Status::set_carry, Status::carry, 0x01),
+        (Status::set_zero, Status::zero, 0x02),
+        (Status::set_interrupt, Status::interrupt, 0x04),
+        (Status::set_decimal, Status::decimal, 0x08),
+        (Status::set_break_flag, Status::break_flag, 0x10),
+        (Status::set_overflow, Status::overflow, 0x40),
+        (Status::set_negative, Status::negative, 0x80),
+    ];
+    for &(set, get, bit) in setters.iter() {
+        set(&mut s, true);
+        assert!(get(s));
+        assert_eq!(s.bits(), bit);
+        set(&mut s, false);
+        assert!(!get(s));
+        assert_eq!(s.bits(), 0);
+    }
+
+    assert_eq!(Status::from_bits(0xA5).bits(), 0xA5);
+    assert_eq!(u8::from(Status::from(0x5A)), 0x5A);
+}
+
+#[test]
+fn pushed_and_pulled() {
+    let s = Status::from_bits(0xC3);
+    // PHP and BRK push B set; IRQ and NMI push it clear. Bit 5 is always set on the stack.
+    assert_eq!(s.to_pushed(true), 0xF3);
+    assert_eq!(s.to_pushed(false), 0xE3);
+    assert_eq!(Status::from_bits(0x10).to_pushed(false), 0x20);
+
+    // Pulling drops B and puts bit 5 back.
+    assert_eq!(Status::from_pulled(0xFF).bits(), 0xEF);
+    assert_eq!(Status::from_pulled(0x00).bits(), 0x20);
+}
+
+#[test]
+fn php_and_plp() {
+    let program = [
+        0x08,       // 0400  PHP
+        0xA9, 0xDF, // 0401  LDA #$DF
+        0x48,       // 0403  PHA
+        0x28,       // 0404  PLP
+    ];
+    let (mut cpu, mut sys) = common::boot(CpuVariant::Nmos6502, &program);
+    cpu.status = Status::from_bits(0x01);
+    for _ in 0..4 {
+        cpu.step(&mut sys);
+    }
+    assert_eq!(sys.mem[0x01FD], 0x31);
+    // $DF without B, and with bit 5 back.
+    assert_eq!(cpu.status.bits(), 0xEF);
+}
+
+#[test]
+fn display() {
+    assert_eq!(Status::from_bits(0xFF).to_string(), "NV-BDIZC");
+    assert_eq!(Status::from_bits(0x00).to_string(), "nv-bdizc");
+    assert_eq!(Status::from_bits(0x85).to_string(), "Nv-bdIzC");
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use fake6502::{AddrMode, CpuVariant, StepResult};
+
+fn step(pc: u16, opcode: u8, mode: AddrMode, ea: Option<u16>, cycles: u32, branch_taken: bool) -> StepResult {
+    StepResult { ran: true, pc, opcode, mode, ea, cycles, branch_taken }
+}
+
+#[test]
+fn reports_each_instruction() {
+    let program = [
+        0xA9, 0x00,       // 0400  LDA #$00
+        0xA2, 0xFF,       // 0402  LDX #$FF
+        0xBD, 0x01, 0x12, // 0404  LDA $1201,X
+        0x0A,             // 0407  ASL A
+        0xF0, 0x02,       // 0408  BEQ $040C
+        0xEA,             // 040A  NOP
+        0xEA,             // 040B  NOP
+        0xD0, 0xFC,       // 040C  BNE $040A (not taken)
+        0x8D, 0x00, 0x02, // 040E  STA $0200
+    ];
+    let (mut cpu, mut sys) = common::boot(CpuVariant::Nmos6502, &program);
+    let expected = [
+        step(0x0400, 0xA9, AddrMode::Immediate, Some(0x0401), 2, false),
+        step(0x0402, 0xA2, AddrMode::Immediate, Some(0x0403), 2, false),
+        // Crosses a page, so a cycle more.
+        step(0x0404, 0xBD, AddrMode::AbsoluteX, Some(0x1300), 5, false),
+        step(0x0407, 0x0A, AddrMode::Accumulator, None, 2, false),
+        step(0x0408, 0xF0, AddrMode::Relative, Some(0x040C), 3, true),
+        step(0x040C, 0xD0, AddrMode::Relative, Some(0x040A), 2, false),
+        step(0x040E, 0x8D, AddrMode::Absolute, Some(0x0200), 4, false),
+    ];
+    for e in expected.iter() {
+        assert_eq!(cpu.step(&mut sys), *e);
+    }
+}
+
+#[test]
+fn counts_the_interrupt_it_took() {
+    let (mut cpu, mut sys) = common::boot(CpuVariant::Nmos6502, &[0xEA]);
+    sys.load(0x0500, &[0xEA]);
+    sys.load(0xFFFA, &[0x00, 0x05]);
+    cpu.set_nmi(true);
+    assert_eq!(cpu.step(&mut sys), step(0x0500, 0xEA, AddrMode::Implied, None, 7 + 2, false));
+}
+
+// When nothing runs, what's reported is where the CPU is stuck and the instruction that got it
+// there.
+#[test]
+fn jammed() {
+    let (mut cpu, mut sys) = common::boot(CpuVariant::Nmos6502, &[0xA9, 0x10, 0x02]);
+    cpu.step(&mut sys);
+    let jam = cpu.step(&mut sys);
+    assert!(jam.ran);
+    assert_eq!((jam.pc, jam.opcode), (0x0402, 0x02));
+
+    for _ in 0..2 {
+        let r = cpu.step(&mut sys);
+        assert!(!r.ran);
+        assert_eq!((r.opcode, r.mode, r.ea, r.cycles, r.branch_taken), (0x02, AddrMode::Implied, None, 0, false));
+        assert_eq!(r.pc, cpu.pc.0);
+    }
+}
+
+#[test]
+#[cfg(not(feature = "no-65c02"))]
+fn waiting() {
+    let (mut cpu, mut sys) = common::boot(CpuVariant::Wdc65C02, &[0xCB, 0xEA]);
+    assert_eq!(cpu.step(&mut sys), step(0x0400, 0xCB, AddrMode::Implied, None, 3, false));
+
+    let r = cpu.step(&mut sys);
+    assert!(!r.ran);
+    assert_eq!((r.pc, r.opcode, r.ea, r.cycles), (0x0401, 0xCB, None, 1));
+}
+
+#[test]
+fn stalled_on_rdy() {
+    let (mut cpu, mut sys) = common::boot(CpuVariant::Nmos6502, &[0xD0, 0x00, 0xEA]);
+    assert_eq!(cpu.step(&mut sys), step(0x0400, 0xD0, AddrMode::Relative, Some(0x0402), 3, true));
+
+    cpu.hold_rdy(1);
+    let r = cpu.step(&mut sys);
+    assert!(!r.ran);
+    // Not the branch's target or its taken flag, which would look like it had happened again.
+    assert_eq!((r.pc, r.opcode, r.ea, r.cycles, r.branch_taken), (0x0402, 0xD0, None, 1, false));
+
+    assert_eq!(cpu.step(&mut sys), step(0x0402, 0xEA, AddrMode::Implied, None, 2, false));
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use common::FlatSystem;
+use fake6502::trace::{compare_logs, CompareOptions, TraceOptions};
+use fake6502::{CpuVariant, Status, CPU};
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+// The first few lines of nestest.log, and the code they come from.
+const NESTEST_LOG: &str = "\
+C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+C5F5  A2 00     LDX #$00                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 30 CYC:10
+C5F7  86 00     STX $00 = 00                    A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 36 CYC:12
+C5F9  86 10     STX $10 = 00                    A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 45 CYC:15
+C5FB  86 11     STX $11 = 00                    A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 54 CYC:18
+C5FD  20 2D C7  JSR $C72D                       A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 63 CYC:21
+C72D  EA        NOP                             A:00 X:00 Y:00 P:26 SP:FB PPU:  0, 81 CYC:27
+";
+
+const CODE: [(u16, &[u8]); 3] = [
+    (0xC000, &[0x4C, 0xF5, 0xC5]),
+    (0xC5F5, &[0xA2, 0x00, 0x86, 0x00, 0x86, 0x10, 0x86, 0x11, 0x20, 0x2D, 0xC7]),
+    (0xC72D, &[0xEA]),
+];
+
+// A Write the test can still look at after the CPU has taken it.
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedBuffer {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+// Set up the way nestest is run in automation mode: straight into $C000, seven cycles in.
+fn boot() -> (CPU, FlatSystem) {
+    let mut sys = FlatSystem::new();
+    for (address, bytes) in CODE.iter() {
+        sys.load(*address, bytes);
+    }
+    let mut cpu = CPU::new(CpuVariant::Ricoh2A03);
+    cpu.pc.0 = 0xC000;
+    cpu.sp.0 = 0xFD;
+    cpu.status = Status::from_bits(0x24);
+    cpu.clockticks = 7;
+    (cpu, sys)
+}
+
+fn run_traced(options: TraceOptions) -> String {
+    let (mut cpu, mut sys) = boot();
+    let out = SharedBuffer::default();
+    cpu.trace_to(Box::new(out.clone()), options);
+    for _ in 0..NESTEST_LOG.lines().count() {
+        cpu.step(&mut sys);
+    }
+    out.text()
+}
+
+#[test]
+fn matches_nestest_log() {
+    let ours = run_traced(TraceOptions::default());
+    assert_eq!(ours, NESTEST_LOG);
+    assert_eq!(compare_logs(ours.as_bytes(), NESTEST_LOG.as_bytes(), &CompareOptions::default()).unwrap(), None);
+}
+
+#[test]
+fn columns_can_be_left_out() {
+    let ours = run_traced(TraceOptions { memory_values: false, ppu: false, cycles: false });
+    let first = ours.lines().next().unwrap();
+    assert_eq!(first, "C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD");
+    assert!(ours.contains("STX $00 "));
+    assert!(!ours.contains("STX $00 = "));
+
+    // Without the PPU column the registers and cycles still line up with the reference.
+    let ours = run_traced(TraceOptions { ppu: false, ..TraceOptions::default() });
+    assert_eq!(compare_logs(ours.as_bytes(), NESTEST_LOG.as_bytes(), &CompareOptions::default()).unwrap(), None);
+}
+
+#[test]
+fn stop_tracing() {
+    let (mut cpu, mut sys) = boot();
+    let out = SharedBuffer::default();
+    cpu.trace_to(Box::new(out.clone()), TraceOptions::default());
+    cpu.step(&mut sys);
+    cpu.stop_tracing();
+    assert!(!cpu.is_tracing());
+    cpu.step(&mut sys);
+    assert_eq!(out.text().lines().count(), 1);
+}
+
+#[test]
+fn reports_first_mismatch() {
+    let broken = NESTEST_LOG.replace("P:26 SP:FD PPU:  0, 45", "P:A4 SP:FD PPU:  0, 45");
+    let mismatch = compare_logs(broken.as_bytes(), NESTEST_LOG.as_bytes(), &CompareOptions::default()).unwrap().unwrap();
+    assert_eq!(mismatch.line, 4);
+    assert_eq!(mismatch.field, "P");
+    assert!(mismatch.ours.starts_with("C5F9"));
+
+    let broken = NESTEST_LOG.replace("CYC:27", "CYC:28");
+    let mismatch = compare_logs(broken.as_bytes(), NESTEST_LOG.as_bytes(), &CompareOptions::default()).unwrap().unwrap();
+    assert_eq!((mismatch.line, mismatch.field), (7, "CYC"));
+    let options = CompareOptions { cycles: false, ..CompareOptions::default() };
+    assert_eq!(compare_logs(broken.as_bytes(), NESTEST_LOG.as_bytes(), &options).unwrap(), None);
+
+    let short: String = NESTEST_LOG.lines().take(3).map(|l| format!("{}\n", l)).collect();
+    let mismatch = compare_logs(short.as_bytes(), NESTEST_LOG.as_bytes(), &CompareOptions::default()).unwrap().unwrap();
+    assert_eq!((mismatch.line, mismatch.field), (4, "length"));
+    assert_eq!(mismatch.ours, "(end of log)");
+}
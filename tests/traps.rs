@@ -0,0 +1,49 @@
+mod common;
+
+use fake6502::{CpuVariant, StopReason, CPU};
+
+fn run(variant: CpuVariant, program: &[u8], detect: bool) -> (CPU, StopReason) {
+    let (mut cpu, mut sys) = common::boot(variant, program);
+    cpu.detect_traps = detect;
+    let reason = cpu.exec(&mut sys, 1000);
+    (cpu, reason)
+}
+
+#[test]
+fn jmp_to_itself() {
+    // LDX #$03 / loop: DEX / BNE loop / JMP *
+    let program = [0xA2, 0x03, 0xCA, 0xD0, 0xFD, 0x4C, 0x05, 0x04];
+    let (cpu, reason) = run(CpuVariant::Nmos6502, &program, true);
+    assert_eq!(reason, StopReason::TrapDetected(0x0405));
+    assert_eq!(cpu.pc.0, 0x0405);
+}
+
+#[test]
+fn branch_to_itself() {
+    // LDA #$01 / BNE *
+    let program = [0xA9, 0x01, 0xD0, 0xFE];
+    let (_, reason) = run(CpuVariant::Nmos6502, &program, true);
+    assert_eq!(reason, StopReason::TrapDetected(0x0402));
+}
+
+#[cfg(not(feature = "no-65c02"))]
+#[test]
+fn bra_to_itself() {
+    // BRA * on the 65C02.
+    let (_, reason) = run(CpuVariant::Wdc65C02, &[0x80, 0xFE], true);
+    assert_eq!(reason, StopReason::TrapDetected(0x0400));
+}
+
+#[test]
+fn branch_not_taken_is_not_a_trap() {
+    // LDA #$00 / BNE * / JMP $0400
+    let program = [0xA9, 0x00, 0xD0, 0xFE, 0x4C, 0x00, 0x04];
+    let (_, reason) = run(CpuVariant::Nmos6502, &program, true);
+    assert_eq!(reason, StopReason::BudgetExhausted);
+}
+
+#[test]
+fn off_by_default() {
+    let (_, reason) = run(CpuVariant::Nmos6502, &[0x4C, 0x00, 0x04], false);
+    assert_eq!(reason, StopReason::BudgetExhausted);
+}
@@ -0,0 +1,157 @@
+// The undocumented NMOS opcodes, one instruction at a time. (With the "no-undocumented" feature
+// they're all NOPs, so there's nothing to test.)
+
+#![cfg(not(feature = "no-undocumented"))]
+
+mod common;
+
+use common::FlatSystem;
+use fake6502::{CpuVariant, CPU};
+
+// One instruction run from $0400 with the registers, carry and memory ($20 and $0300 both hold 'm',
+// and $30 points at $0300) set up beforehand, and what it should leave behind. 'flags' lists which
+// of N, V, Z and C should be set afterwards.
+struct Case {
+    name: &'static str,
+    code: &'static [u8],
+    a: u8,
+    x: u8,
+    y: u8,
+    carry: bool,
+    m: u8,
+    result: (u8, u8),
+    write: Option<(u16, u8)>,
+    flags: &'static str,
+    cycles: u32,
+}
+
+fn run(case: &Case) -> (CPU, FlatSystem) {
+    let (mut cpu, mut sys) = common::boot(CpuVariant::Nmos6502Illegal, case.code);
+    sys.mem[0x20] = case.m;
+    sys.mem[0x0300] = case.m;
+    sys.load(0x30, &[0x00, 0x03]);
+    cpu.a.0 = case.a;
+    cpu.x.0 = case.x;
+    cpu.y.0 = case.y;
+    cpu.status.set_carry(case.carry);
+    let cycles = cpu.step(&mut sys).cycles;
+    assert_eq!(cycles, case.cycles, "{}: cycles", case.name);
+    (cpu, sys)
+}
+
+fn check(case: &Case) -> CPU {
+    let (cpu, sys) = run(case);
+    assert_eq!((cpu.a.0, cpu.x.0), case.result, "{}: A and X", case.name);
+    if let Some((address, value)) = case.write {
+        assert_eq!(sys.mem[address as usize], value, "{}: memory", case.name);
+    }
+    let s = cpu.status;
+    let flags: String = [(s.negative(), 'N'), (s.overflow(), 'V'), (s.zero(), 'Z'), (s.carry(), 'C')]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, name)| *name)
+        .collect();
+    assert_eq!(flags, case.flags, "{}: flags", case.name);
+    cpu
+}
+
+const CASES: &[Case] = &[
+    Case { name: "LAX zp", code: &[0xA7, 0x20], a: 0, x: 0, y: 0, carry: false, m: 0x80,
+           result: (0x80, 0x80), write: None, flags: "N", cycles: 3 },
+    Case { name: "SAX zp", code: &[0x87, 0x20], a: 0xF0, x: 0x3C, y: 0, carry: false, m: 0,
+           result: (0xF0, 0x3C), write: Some((0x20, 0x30)), flags: "", cycles: 3 },
+    Case { name: "DCP zp", code: &[0xC7, 0x20], a: 0x42, x: 0, y: 0, carry: false, m: 0x43,
+           result: (0x42, 0), write: Some((0x20, 0x42)), flags: "ZC", cycles: 5 },
+    Case { name: "ISB zp", code: &[0xE7, 0x20], a: 0x20, x: 0, y: 0, carry: true, m: 0x0F,
+           result: (0x10, 0), write: Some((0x20, 0x10)), flags: "C", cycles: 5 },
+    Case { name: "SLO zp", code: &[0x07, 0x20], a: 0x01, x: 0, y: 0, carry: false, m: 0x81,
+           result: (0x03, 0), write: Some((0x20, 0x02)), flags: "C", cycles: 5 },
+    Case { name: "RLA zp", code: &[0x27, 0x20], a: 0xFF, x: 0, y: 0, carry: true, m: 0x81,
+           result: (0x03, 0), write: Some((0x20, 0x03)), flags: "C", cycles: 5 },
+    Case { name: "SRE zp", code: &[0x47, 0x20], a: 0xFF, x: 0, y: 0, carry: false, m: 0x81,
+           result: (0xBF, 0), write: Some((0x20, 0x40)), flags: "NC", cycles: 5 },
+    Case { name: "RRA zp", code: &[0x67, 0x20], a: 0x10, x: 0, y: 0, carry: true, m: 0x02,
+           result: (0x91, 0), write: Some((0x20, 0x81)), flags: "N", cycles: 5 },
+    Case { name: "ANC #", code: &[0x0B, 0x81], a: 0xFF, x: 0, y: 0, carry: false, m: 0,
+           result: (0x81, 0), write: None, flags: "NC", cycles: 2 },
+    Case { name: "ALR #", code: &[0x4B, 0x03], a: 0xFF, x: 0, y: 0, carry: false, m: 0,
+           result: (0x01, 0), write: None, flags: "C", cycles: 2 },
+    // C comes from bit 6 of the result, V from bits 6 and 5 differing.
+    Case { name: "ARR #", code: &[0x6B, 0xFF], a: 0xC0, x: 0, y: 0, carry: true, m: 0,
+           result: (0xE0, 0), write: None, flags: "NC", cycles: 2 },
+    Case { name: "ARR # (V)", code: &[0x6B, 0xFF], a: 0x80, x: 0, y: 0, carry: false, m: 0,
+           result: (0x40, 0), write: None, flags: "VC", cycles: 2 },
+    Case { name: "SBX #", code: &[0xCB, 0x01], a: 0xF0, x: 0x3C, y: 0, carry: false, m: 0,
+           result: (0xF0, 0x2F), write: None, flags: "C", cycles: 2 },
+    Case { name: "SBX # (borrow)", code: &[0xCB, 0x31], a: 0xF0, x: 0x3C, y: 0, carry: true, m: 0,
+           result: (0xF0, 0xFF), write: None, flags: "N", cycles: 2 },
+    Case { name: "SBC # ($EB)", code: &[0xEB, 0x01], a: 0x10, x: 0, y: 0, carry: true, m: 0,
+           result: (0x0F, 0), write: None, flags: "C", cycles: 2 },
+    // The stores AND what they store with the high byte of the address plus one.
+    Case { name: "SHA abs,Y", code: &[0x9F, 0x00, 0x03], a: 0xFF, x: 0xF7, y: 0, carry: false, m: 0xAA,
+           result: (0xFF, 0xF7), write: Some((0x0300, 0x04)), flags: "", cycles: 5 },
+    Case { name: "SHA (zp),Y", code: &[0x93, 0x30], a: 0xFF, x: 0xFF, y: 0, carry: false, m: 0xAA,
+           result: (0xFF, 0xFF), write: Some((0x0300, 0x04)), flags: "", cycles: 6 },
+    Case { name: "SHX abs,Y", code: &[0x9E, 0x00, 0x03], a: 0, x: 0xFF, y: 0, carry: false, m: 0xAA,
+           result: (0, 0xFF), write: Some((0x0300, 0x04)), flags: "", cycles: 5 },
+    Case { name: "SHY abs,X", code: &[0x9C, 0x00, 0x03], a: 0, x: 0, y: 0xFF, carry: false, m: 0xAA,
+           result: (0, 0), write: Some((0x0300, 0x04)), flags: "", cycles: 5 },
+    // Crossing a page puts the value in the high byte of the address, too.
+    Case { name: "SHX abs,Y (crossing)", code: &[0x9E, 0xFF, 0x12], a: 0, x: 0x07, y: 1, carry: false, m: 0xAA,
+           result: (0, 0x07), write: Some((0x0300, 0x03)), flags: "", cycles: 5 },
+];
+
+#[test]
+fn each_opcode() {
+    for case in CASES {
+        check(case);
+    }
+}
+
+#[test]
+fn las_and_tas_use_sp() {
+    let las = Case { name: "LAS abs,Y", code: &[0xBB, 0x00, 0x03], a: 0, x: 0, y: 0, carry: false, m: 0xDA,
+                     result: (0xD8, 0xD8), write: None, flags: "N", cycles: 4 };
+    assert_eq!(check(&las).sp.0, 0xD8);
+
+    let tas = Case { name: "TAS abs,Y", code: &[0x9B, 0x00, 0x03], a: 0xF3, x: 0x7F, y: 0, carry: false, m: 0xAA,
+                     result: (0xF3, 0x7F), write: Some((0x0300, 0x00)), flags: "", cycles: 5 };
+    assert_eq!(check(&tas).sp.0, 0x73);
+}
+
+#[test]
+fn ane_and_lxa_magic() {
+    // ANE: A = (A | magic) & X & operand. LXA: A = X = (A | magic) & operand.
+    for &(magic, ane, lxa) in [(0xEE, 0xEF, 0x0E), (0xFF, 0xFF, 0x0F), (0x00, 0x01, 0x00)].iter() {
+        let (mut cpu, mut sys) = common::boot(CpuVariant::Nmos6502Illegal, &[0x8B, 0xFF, 0xAB, 0x0F]);
+        cpu.ane_magic = magic;
+        cpu.lxa_magic = magic;
+        cpu.a.0 = 0x01;
+        cpu.x.0 = 0xFF;
+        cpu.step(&mut sys);
+        assert_eq!(cpu.a.0, ane, "ANE with {:02X}", magic);
+        assert_eq!(cpu.status.negative(), ane & 0x80 != 0);
+
+        cpu.a.0 = 0x10;
+        cpu.step(&mut sys);
+        assert_eq!((cpu.a.0, cpu.x.0), (lxa, lxa), "LXA with {:02X}", magic);
+        assert_eq!(cpu.status.zero(), lxa == 0);
+    }
+}
+
+#[test]
+fn nops_on_a_plain_nmos6502() {
+    for case in CASES {
+        let (mut cpu, mut sys) = common::boot(CpuVariant::Nmos6502, case.code);
+        sys.mem[0x20] = case.m;
+        sys.mem[0x0300] = case.m;
+        cpu.a.0 = case.a;
+        cpu.x.0 = case.x;
+        cpu.y.0 = case.y;
+        let sp = cpu.sp.0;
+        cpu.step(&mut sys);
+        assert_eq!((cpu.a.0, cpu.x.0, cpu.sp.0), (case.a, case.x, sp), "{}", case.name);
+        assert_eq!((sys.mem[0x20], sys.mem[0x0300]), (case.m, case.m), "{}", case.name);
+        assert_eq!(cpu.pc.0, 0x0400 + case.code.len() as u16, "{}", case.name);
+    }
+}
@@ -0,0 +1,75 @@
+// The CPU variant is picked at run time, so one binary can have all of them going at once. (The
+// expectations allow for the Cargo features that compile parts of them out.)
+
+mod common;
+
+use fake6502::{CpuVariant, CPU};
+
+use common::FlatSystem;
+
+const VARIANTS: [CpuVariant; 4] =
+    [CpuVariant::Nmos6502, CpuVariant::Nmos6502Illegal, CpuVariant::Ricoh2A03, CpuVariant::Wdc65C02];
+
+fn run(variant: CpuVariant, program: &[u8], instructions: usize) -> (CPU, FlatSystem) {
+    let (mut cpu, mut sys) = common::boot(variant, program);
+    sys.mem[0x10] = 0x5A;
+    for _ in 0..instructions {
+        cpu.step(&mut sys);
+    }
+    (cpu, sys)
+}
+
+#[test]
+fn decimal_mode() {
+    let program = [
+        0xF8,       // 0400  SED
+        0x18,       // 0401  CLC
+        0xA9, 0x09, // 0402  LDA #$09
+        0x69, 0x01, // 0404  ADC #$01
+        0x85, 0x20, // 0406  STA $20
+        0x38,       // 0408  SEC
+        0xA9, 0x10, // 0409  LDA #$10
+        0xE9, 0x01, // 040B  SBC #$01
+    ];
+    for variant in VARIANTS {
+        let (cpu, sys) = run(variant, &program, 8);
+        // The 2A03 has the decimal flag, but nothing looks at it.
+        let bcd = !cfg!(feature = "no-bcd") && variant != CpuVariant::Ricoh2A03;
+        let expected = if bcd { (0x10, 0x09) } else { (0x0A, 0x0F) };
+        assert_eq!((sys.mem[0x20], cpu.a.0), expected, "{:?}", variant);
+        assert!(cpu.status.decimal());
+    }
+}
+
+#[test]
+fn undocumented_opcodes() {
+    let program = [
+        0xA7, 0x10, // 0400  LAX $10 (SMB2 $10 on the 65C02)
+        0xEA,       // 0402  NOP
+    ];
+    for variant in VARIANTS {
+        let (cpu, sys) = run(variant, &program, 1);
+        assert_eq!(cpu.pc.0, 0x0402, "{:?}", variant);
+
+        let lax = !cfg!(feature = "no-undocumented")
+            && matches!(variant, CpuVariant::Nmos6502Illegal | CpuVariant::Ricoh2A03);
+        let smb = !cfg!(feature = "no-65c02") && variant == CpuVariant::Wdc65C02;
+        let loaded = if lax { 0x5A } else { 0x00 };
+        assert_eq!((cpu.a.0, cpu.x.0), (loaded, loaded), "{:?}", variant);
+        assert_eq!(sys.mem[0x10], if smb { 0x5E } else { 0x5A }, "{:?}", variant);
+    }
+}
+
+#[test]
+fn variants_side_by_side() {
+    // A C64 and an NES in the same program, each doing its own thing with the same code.
+    let program = [0xF8, 0xA9, 0x19, 0x69, 0x01]; // SED, LDA #$19, ADC #$01
+    let (c64, _) = run(CpuVariant::Nmos6502Illegal, &program, 3);
+    let (nes, _) = run(CpuVariant::Ricoh2A03, &program, 3);
+    assert_eq!(c64.variant, CpuVariant::Nmos6502Illegal);
+    assert_eq!(nes.variant, CpuVariant::Ricoh2A03);
+    if !cfg!(feature = "no-bcd") {
+        assert_eq!(c64.a.0, 0x20);
+    }
+    assert_eq!(nes.a.0, 0x1A);
+}
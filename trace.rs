@@ -0,0 +1,233 @@
+// Execution traces in the format of nestest.log, the reference log that goes with the nestest ROM,
+// one line per instruction before it runs:
+//
+//     C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+//
+// Turn it on with CPU::trace_to(). compare_logs() finds the first line where two such logs part
+// ways, and tracecmp.rs wraps that up as a command line tool.
+
+use std::io::{self, BufRead, Write};
+
+use super::disasm::decode;
+use super::{AddrMode, Backplane, CPU};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceOptions {
+    // Follow operands with what's in memory there, like "STA $0200 = 7F" and
+    // "LDA ($80),Y = 0200 @ 0205 = 5A". The values are read through Backplane::read(), before the
+    // instruction runs, so leave this off if reads have side effects on your machine.
+    pub memory_values: bool,
+    // The "PPU:scanline,dot" column. Worked out from the cycle count as NTSC NES timing (three
+    // dots per CPU cycle, 341 dots a line, 262 lines) with rendering off, which is how nestest
+    // runs.
+    pub ppu: bool,
+    pub cycles: bool,
+}
+
+impl Default for TraceOptions {
+    fn default() -> TraceOptions {
+        TraceOptions { memory_values: true, ppu: true, cycles: true }
+    }
+}
+
+pub struct Tracer {
+    out: Box<dyn Write>,
+    options: TraceOptions,
+}
+
+impl Tracer {
+    pub fn new(out: Box<dyn Write>, options: TraceOptions) -> Tracer {
+        Tracer { out, options }
+    }
+
+    // Write errors are ignored; a trace isn't worth stopping the emulation for.
+    pub(crate) fn trace<T: Backplane>(&mut self, cpu: &CPU, mem: &T) {
+        let line = trace_line(cpu, mem, &self.options);
+        let _ = writeln!(self.out, "{}", line);
+    }
+}
+
+fn read16<T: Backplane>(mem: &T, address: u16) -> u16 {
+    mem.read(address) as u16 | (mem.read(address.wrapping_add(1)) as u16) << 8
+}
+
+// A 16-bit pointer in zero page, wrapping within it.
+fn read16_zp<T: Backplane>(mem: &T, address: u8) -> u16 {
+    mem.read(address as u16) as u16 | (mem.read(address.wrapping_add(1) as u16) as u16) << 8
+}
+
+// The line for the instruction the CPU is about to run.
+pub fn trace_line<T: Backplane>(cpu: &CPU, mem: &T, options: &TraceOptions) -> String {
+    let pc = cpu.pc.0;
+    let inst = decode(mem, pc, cpu.variant);
+
+    let mut bytes = String::new();
+    for i in 0..inst.length {
+        if i > 0 {
+            bytes.push(' ');
+        }
+        bytes += &format!("{:02X}", mem.read(pc.wrapping_add(i)));
+    }
+
+    // nestest's name for the illegal SBC at $EB is just SBC.
+    let name = match inst.mnemonic {
+        "usbc" => "SBC".to_string(),
+        _ => inst.name().to_uppercase(),
+    };
+    let mut text = name.clone();
+    let operand = inst.operand_text();
+    if !operand.is_empty() {
+        text = format!("{} {}", text, operand);
+    }
+
+    if options.memory_values {
+        let (x, y) = (cpu.x.0, cpu.y.0);
+        let op = inst.operand;
+        let jump = name == "JMP" || name == "JSR";
+        let annotation = match inst.mode {
+            AddrMode::ZeroPage => Some(format!(" = {:02X}", mem.read(op))),
+            AddrMode::ZeroPageX | AddrMode::ZeroPageY => {
+                let ea = (op as u8).wrapping_add(if inst.mode == AddrMode::ZeroPageX { x } else { y });
+                Some(format!(" @ {:02X} = {:02X}", ea, mem.read(ea as u16)))
+            }
+            AddrMode::Absolute if !jump => Some(format!(" = {:02X}", mem.read(op))),
+            AddrMode::AbsoluteX | AddrMode::AbsoluteY => {
+                let ea = op.wrapping_add(if inst.mode == AddrMode::AbsoluteX { x } else { y } as u16);
+                Some(format!(" @ {:04X} = {:02X}", ea, mem.read(ea)))
+            }
+            AddrMode::Indirect => {
+                // With the NMOS page wrap, since that's what JMP will actually do.
+                let hi = (op & 0xFF00) | (op.wrapping_add(1) & 0x00FF);
+                let target = if cpu.variant.is_cmos() {
+                    read16(mem, op)
+                } else {
+                    mem.read(op) as u16 | (mem.read(hi) as u16) << 8
+                };
+                Some(format!(" = {:04X}", target))
+            }
+            AddrMode::IndirectX => {
+                let ptr = (op as u8).wrapping_add(x);
+                let ea = read16_zp(mem, ptr);
+                Some(format!(" @ {:02X} = {:04X} = {:02X}", ptr, ea, mem.read(ea)))
+            }
+            AddrMode::IndirectY => {
+                let base = read16_zp(mem, op as u8);
+                let ea = base.wrapping_add(y as u16);
+                Some(format!(" = {:04X} @ {:04X} = {:02X}", base, ea, mem.read(ea)))
+            }
+            AddrMode::ZeroPageIndirect => {
+                let ea = read16_zp(mem, op as u8);
+                Some(format!(" = {:04X} = {:02X}", ea, mem.read(ea)))
+            }
+            _ => None,
+        };
+        if let Some(a) = annotation {
+            text += &a;
+        }
+    }
+
+    let mut line = format!("{:04X}  {:<8} {}{:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
+                           pc, bytes, if inst.undocumented { '*' } else { ' ' }, text,
+                           cpu.a.0, cpu.x.0, cpu.y.0, cpu.status.bits(), cpu.sp.0);
+    if options.ppu {
+        let dots = cpu.clockticks * 3;
+        line += &format!(" PPU:{:>3},{:>3}", (dots / 341) % 262, dots % 341);
+    }
+    if options.cycles {
+        line += &format!(" CYC:{}", cpu.clockticks);
+    }
+    line
+}
+
+// Which parts of two trace lines compare_logs() looks at. The registers and CYC are matched by
+// their labels, so logs with and without the PPU column can still be compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompareOptions {
+    // The disassembly, memory values and all; otherwise only the address and the bytes.
+    pub disassembly: bool,
+    pub ppu: bool,
+    pub cycles: bool,
+}
+
+impl Default for CompareOptions {
+    fn default() -> CompareOptions {
+        CompareOptions { disassembly: true, ppu: false, cycles: true }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    // Counting from 1.
+    pub line: usize,
+    // "PC", "bytes", "disassembly", "A", "X", "Y", "P", "SP", "PPU", "CYC", or "length" if one
+    // log ended early.
+    pub field: &'static str,
+    pub ours: String,
+    pub reference: String,
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "line {}: {} differs\n  ours:      {}\n  reference: {}", self.line, self.field, self.ours, self.reference)
+    }
+}
+
+// "A:00" style fields, by label.
+fn register(line: &str, label: &str) -> Option<String> {
+    let start = line.find(&format!(" {}:", label))? + label.len() + 2;
+    let rest = &line[start..];
+    // PPU's value has a comma and maybe spaces in it ("PPU:  0, 21").
+    let end = if label == "PPU" {
+        rest.find(" CYC").unwrap_or(rest.len())
+    } else {
+        rest.find(' ').unwrap_or(rest.len())
+    };
+    Some(rest[..end].trim().to_string())
+}
+
+fn fields(line: &str, options: &CompareOptions) -> Vec<(&'static str, String)> {
+    let column = |from: usize, to: usize| line.get(from..to.min(line.len())).unwrap_or("").trim().to_string();
+    let mut f = vec![("PC", column(0, 4)), ("bytes", column(6, 14))];
+    if options.disassembly {
+        f.push(("disassembly", column(15, 48)));
+    }
+    let mut labels = vec!["A", "X", "Y", "P", "SP"];
+    if options.ppu {
+        labels.push("PPU");
+    }
+    if options.cycles {
+        labels.push("CYC");
+    }
+    for label in labels {
+        f.push((label, register(line, label).unwrap_or_default()));
+    }
+    f
+}
+
+// Reads both logs a line at a time and reports the first difference, or None if they match all
+// the way through.
+pub fn compare_logs<A: BufRead, B: BufRead>(ours: A, reference: B, options: &CompareOptions) -> io::Result<Option<Mismatch>> {
+    let mut ours = ours.lines();
+    let mut reference = reference.lines();
+    let mut n = 0;
+    loop {
+        n += 1;
+        let (o, r) = match (ours.next().transpose()?, reference.next().transpose()?) {
+            (None, None) => return Ok(None),
+            (Some(o), Some(r)) => (o, r),
+            (o, r) => {
+                return Ok(Some(Mismatch {
+                    line: n,
+                    field: "length",
+                    ours: o.unwrap_or_else(|| "(end of log)".to_string()),
+                    reference: r.unwrap_or_else(|| "(end of log)".to_string()),
+                }));
+            }
+        };
+        for ((field, a), (_, b)) in fields(&o, options).into_iter().zip(fields(&r, options)) {
+            if a != b {
+                return Ok(Some(Mismatch { line: n, field, ours: o, reference: r }));
+            }
+        }
+    }
+}
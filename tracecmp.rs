@@ -0,0 +1,56 @@
+use fake6502::trace::{compare_logs, CompareOptions};
+
+use std::fs::File;
+use std::io::BufReader;
+use std::process::exit;
+
+// Compares two nestest.log style traces and points out the first line where they differ:
+//
+//     tracecmp [--no-disasm] [--no-cycles] [--ppu] ours.log nestest.log
+//
+// Exits with 0 if the logs match, 1 if they don't, and 2 if something else went wrong.
+
+fn usage() -> ! {
+    eprintln!("usage: tracecmp [--no-disasm] [--no-cycles] [--ppu] <ours.log> <reference.log>");
+    exit(2);
+}
+
+fn open(path: &str) -> BufReader<File> {
+    match File::open(path) {
+        Ok(f) => BufReader::new(f),
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            exit(2);
+        }
+    }
+}
+
+fn main() {
+    let mut options = CompareOptions::default();
+    let mut paths = Vec::new();
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--no-disasm" => options.disassembly = false,
+            "--no-cycles" => options.cycles = false,
+            "--ppu" => options.ppu = true,
+            "-h" | "--help" => usage(),
+            _ if arg.starts_with("--") => usage(),
+            _ => paths.push(arg),
+        }
+    }
+    if paths.len() != 2 {
+        usage();
+    }
+
+    match compare_logs(open(&paths[0]), open(&paths[1]), &options) {
+        Ok(None) => println!("logs match"),
+        Ok(Some(mismatch)) => {
+            println!("{}", mismatch);
+            exit(1);
+        }
+        Err(e) => {
+            eprintln!("error reading logs: {}", e);
+            exit(2);
+        }
+    }
+}